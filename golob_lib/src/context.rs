@@ -1,14 +1,30 @@
-use crate::{ImageFormat, OutDesc, PythonRunner};
+use crate::{CancellationToken, ImageFormat, OutDesc, Progress, PythonRunner};
 use indexmap::IndexMap;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
-use pyo3::prelude::*;
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PySet, PyTuple},
+};
 
 use crate::{
-    variant::{Cfg, DiscreteCfg, Image},
+    variant::{Cfg, DiscreteCfg, Dtype, Image, ImageSettings, Scale},
     OutputSize, Variant,
 };
 
+/// What `set_matte_mode` asked for, a matte is a single channel output
+/// that gets spread over the real one after `run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum MatteMode {
+    #[default]
+    Off,
+    Alpha,
+    Rgb,
+}
+
 /// The main context sent to the python script as a global variable.
 /// this Allows the user to define inputs, outputs, and properties of
 /// the render environment.
@@ -17,16 +33,37 @@ use crate::{
 pub struct PyContext {
     // vector of input numpy arrays, maybe of varying dimensions and integral type
     inputs: IndexMap<String, (PyObject, ImageFormat)>,
+    // neighbouring frames of the image inputs, keyed by name and frame offset
+    temporal_inputs: HashMap<(String, i32), (PyObject, ImageFormat)>,
+    // frames on either side of the current one requested in setup
+    temporal_window: u32,
     // A single numpy array the same dimensions as the requested frame output
     // this one refers to a pointer over the memory passed in by the user when
     // they started the render pass
     target: PyObject,
     target_width: u32,
     target_height: u32,
+    // fallback image paths for image inputs, only set in setup
+    image_defaults: HashMap<String, PathBuf>,
+    // names registered with `register_output`, besides the primary one
+    output_names: Vec<String>,
+    // the host's buffers for those, missing ones get scratch space
+    outputs: IndexMap<String, PyObject>,
+    // made on first access so every call in a run hands back the same array
+    scratch_outputs: RefCell<HashMap<String, PyObject>>,
     // All registered inputs, types, with default settings and ranges, with a label
     registry: IndexMap<String, crate::Variant>,
     // Context provided time
     time: f32,
+    // Context provided frame index and rate
+    frame: u32,
+    fps: f32,
+    base_seed: u64,
+    // host metadata, None if the host didn't provide it
+    comp_size: Option<OutputSize>,
+    layer_name: Option<String>,
+    // what the script wants hosts to call it, only set in setup
+    name: Option<String>,
     /// A subsection of the output buffer to hand to the user.
     /// If none, it is unconfigured, and we should pass the whole buffer.
     output_size_override: Option<OutputSize>,
@@ -35,12 +72,39 @@ pub struct PyContext {
     // The user can set this variable in setup to indicate that this is a continuous effect,
     // which will render on frame after the other, this is only important in after effects
     is_sequential_mode: bool,
+    // whether AE's "Is Image Filter" starts out ticked, only set in setup
+    image_filter_default: bool,
     // if set to true in setup all textures passed in will be RGBA order with corrected gamme (i'm
     // looking at you ae 16bit), and all output textures will be translated to their proper image
     // format.
     uses_automatic_color_correction: bool,
+    // "straight" in `set_alpha_mode`
+    straight_alpha: bool,
+    matte_mode: MatteMode,
+    // handed out as the primary output instead of the target in matte mode
+    matte: Option<PyObject>,
     /// numpy helper functions,
     helper_module: Py<PyModule>,
+    /// torch helper functions, if torch is installed
+    torch_helper: Arc<OnceLock<Py<PyModule>>>,
+    // shared with the runner, so torch is only asked once
+    has_cuda: Arc<OnceLock<bool>>,
+    // Set by the host to ask a long running script to stop early
+    cancellation: Option<CancellationToken>,
+    // Shared with the runner, written by the script
+    progress: Progress,
+    // the runner's loop, for `gather` and `run_soon`
+    event_loop: PyObject,
+    background_tasks: Py<PySet>,
+    // Owned by the runner, survives between runs of the same script
+    state: Py<PyDict>,
+    // set the first time the script asks for the primary output
+    output_accessed: Cell<bool>,
+    // read only text for the host to show, see `set_status`
+    statuses: IndexMap<String, String>,
+    // copied from the runner for `capabilities`
+    max_inputs: Option<usize>,
+    sequential_supported: bool,
 }
 
 #[pymethods]
@@ -48,46 +112,383 @@ impl PyContext {
     pub fn get_input(&self, py: Python<'_>, name: &str) -> Option<PyObject> {
         if let Some(v) = self.registry.get(name) {
             match v {
-                Variant::Image(_) => self
+                Variant::Image(..) => self
                     .inputs
                     .get(name)
                     .cloned()
-                    .and_then(|t| self.swizzle_to_rgba(py, t.0, t.1).ok())
+                    .and_then(|t| self.swizzle_to_rgba(py, name, t.0, t.1).ok())
                     .map(|t| t.to_object(py)),
                 Variant::Bool(b) => Some(b.current.into_py(py)),
+                Variant::TaggedInt(i) if i.returns_label => i.label().map(|l| l.into_py(py)),
                 Variant::TaggedInt(i) => Some(i.value.into_py(py)),
                 Variant::Color(c) => Some(c.current.into_py(py)),
                 Variant::Int(i) => Some(i.current.into_py(py)),
-                Variant::Float(f) => Some(f.current.into_py(py)),
+                Variant::Float(f, _) => Some(f.current.into_py(py)),
                 Variant::Vector2(v) => Some(v.current.into_py(py)),
+                Variant::Angle(a) => Some(a.current.rem_euclid(360.0).into_py(py)),
             }
         } else {
             None
         }
     }
 
-    /// returns height, width pair
+    /// True if the host provided the input, only image inputs can be missing.
+    /// Names that were never registered raise a KeyError.
+    pub fn has_input(&self, name: &str) -> Result<bool, PyErr> {
+        match self.registry.get(name) {
+            Some(Variant::Image(..)) => Ok(self.inputs.contains_key(name)),
+            Some(_) => Ok(true),
+            None => Err(PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!(
+                "no input named {name} was registered in setup"
+            ))),
+        }
+    }
+
+    /// Every registered input, in registration order.
+    pub fn input_names(&self) -> Vec<String> {
+        self.registry.keys().cloned().collect()
+    }
+
+    /// The image input `frame_offset` frames away from the current one, None if
+    /// that frame doesn't exist or wasn't provided by the host.
+    pub fn get_input_at(
+        &self,
+        py: Python<'_>,
+        name: &str,
+        frame_offset: i32,
+    ) -> Result<Option<PyObject>, PyErr> {
+        if !matches!(self.registry.get(name), Some(Variant::Image(..))) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "{name} is not a registered image input"
+            )));
+        }
+
+        if frame_offset == 0 {
+            return Ok(self.get_input(py, name));
+        }
+
+        self.temporal_inputs
+            .get(&(name.to_owned(), frame_offset))
+            .cloned()
+            .map(|t| self.swizzle_to_rgba(py, name, t.0, t.1))
+            .transpose()
+    }
+
+    /// A display name for hosts, e.g. the effect's label in After Effects.
+    pub fn set_name(&mut self, name: &str) -> Result<(), PyErr> {
+        if !self.is_in_setup {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Cannot set the name outside of setup",
+            ));
+        }
+
+        self.name = Some(name.to_owned());
+        Ok(())
+    }
+
+    pub fn set_temporal_window(&mut self, frames: u32) -> Result<(), PyErr> {
+        if !self.is_in_setup {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Cannot set the temporal window outside of setup",
+            ));
+        }
+
+        self.temporal_window = frames;
+        Ok(())
+    }
+
+    pub fn temporal_window(&self) -> u32 {
+        self.temporal_window
+    }
+
+    /// returns height, width pair. Setup has no output yet, there it's the size asked
+    /// for with `set_output_size`, else the last run's output, else zeros.
     pub fn max_output_size(&self) -> (u32, u32) {
         (self.target_height, self.target_width)
     }
 
-    pub fn output(&self, py: Python) -> Result<PyObject, PyErr> {
-        if let Some(OutputSize { width, height }) = self.output_size_override {
-            self.helper_module
-                .call_method1(py, "center_crop", (&self.target, height, width))
+    /// returns the height, width of the composition, or of the
+    /// output if the host doesn't have one
+    pub fn comp_size(&self) -> (u32, u32) {
+        self.comp_size
+            .as_ref()
+            .map_or(self.max_output_size(), |s| (s.height, s.width))
+    }
+
+    pub fn layer_name(&self) -> Option<String> {
+        self.layer_name.clone()
+    }
+
+    /// With no name, the primary output. Named outputs the host isn't
+    /// displaying are zeroed scratch arrays shaped like the primary output,
+    /// one per name for the whole run.
+    #[pyo3(signature = (name=None))]
+    pub fn output(&self, py: Python, name: Option<&str>) -> Result<PyObject, PyErr> {
+        let Some(name) = name else {
+            self.output_accessed.set(true);
+            return self.crop_to_output_size(py, self.matte.as_ref().unwrap_or(&self.target));
+        };
+
+        if !self.output_names.iter().any(|n| n == name) {
+            return Err(PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!(
+                "no output named {name} was registered in setup"
+            )));
+        }
+
+        if let Some(array) = self.outputs.get(name) {
+            return self.crop_to_output_size(py, array);
+        }
+
+        let mut scratch = self.scratch_outputs.borrow_mut();
+        if !scratch.contains_key(name) {
+            let zeros = py
+                .import_bound("numpy")?
+                .call_method1("zeros_like", (&self.target,))?;
+            scratch.insert(name.to_owned(), zeros.unbind());
+        }
+
+        self.crop_to_output_size(py, &scratch[name])
+    }
+
+    /// False without torch, the answer is cached for as long as the runner lives.
+    pub fn has_cuda(&self, py: Python) -> bool {
+        *self.has_cuda.get_or_init(|| {
+            let Some(torch_helper) = self.torch_helper(py) else {
+                return false;
+            };
+
+            torch_helper
+                .call_method0(py, "has_cuda")
+                .and_then(|available| available.extract(py))
+                .unwrap_or_else(|e| {
+                    log::warn!("could not ask torch for cuda: {e}");
+                    false
+                })
+        })
+    }
+
+    /// "cuda" if `has_cuda`, otherwise "cpu". Ready to pass to torch's `.to()`.
+    pub fn device(&self, py: Python) -> &'static str {
+        if self.has_cuda(py) {
+            "cuda"
         } else {
-            Ok(self.target.clone())
+            "cpu"
         }
     }
 
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    }
+
+    /// Runs the coroutines at the same time on the runner's event loop. From an
+    /// `async def run` this returns an awaitable, anywhere else it waits for
+    /// them and returns their results in order.
+    #[pyo3(signature = (*coros))]
+    pub fn gather(&self, py: Python<'_>, coros: &Bound<'_, PyTuple>) -> PyResult<PyObject> {
+        let asyncio = py.import_bound("asyncio")?;
+
+        // only a coroutine already on the loop can await the result
+        if asyncio.call_method0("get_running_loop").is_ok() {
+            return Ok(asyncio.call_method1("gather", coros.clone())?.unbind());
+        }
+
+        let event_loop = self.event_loop.bind(py);
+        let futures = coros
+            .iter()
+            .map(|coro| asyncio.call_method1("run_coroutine_threadsafe", (coro, event_loop)))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let results = futures
+            .iter()
+            .map(|future| future.call_method0("result"))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        Ok(results.into_py(py))
+    }
+
+    /// Starts `coro` on the runner's event loop without waiting for it, it
+    /// carries on after `run` returns. Returns a `concurrent.futures.Future`.
+    pub fn run_soon(&self, py: Python<'_>, coro: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let asyncio = py.import_bound("asyncio")?;
+        let args = (coro, self.event_loop.bind(py));
+        let future = asyncio.call_method1("run_coroutine_threadsafe", args)?;
+
+        // the loop only keeps weak references to its tasks
+        let tasks = self.background_tasks.bind(py);
+        tasks.add(&future)?;
+        future.call_method1("add_done_callback", (tasks.getattr("discard")?,))?;
+
+        Ok(future.unbind())
+    }
+
+    /// A dict that lives as long as the loaded script, for
+    /// accumulating things across frames.
+    #[getter]
+    pub fn state(&self, py: Python<'_>) -> Py<PyDict> {
+        self.state.clone_ref(py)
+    }
+
+    /// Reports how much of the current run is done, clamped to 0..=1.
+    pub fn set_progress(&self, fraction: f32) {
+        self.progress.set(fraction.clamp(0.0, 1.0));
+    }
+
     pub fn set_automatic_color_correction(&mut self, flag: bool) {
         self.uses_automatic_color_correction = flag;
     }
 
+    /// "straight" unpremultiplies premultiplied inputs when they are read and
+    /// premultiplies the output after `run`, "premultiplied" passes pixels through.
+    pub fn set_alpha_mode(&mut self, mode: &str) -> Result<(), PyErr> {
+        if !self.is_in_setup {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Cannot set the alpha mode outside of setup",
+            ));
+        }
+
+        self.straight_alpha = match mode {
+            "straight" => true,
+            "premultiplied" => false,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "unknown alpha mode {mode}, expected straight or premultiplied"
+                )))
+            }
+        };
+
+        Ok(())
+    }
+
+    /// "alpha" or "rgb" makes `output()` a single channel array that is copied into
+    /// the output's alpha or color channels after `run`, "off" goes back to RGBA.
+    pub fn set_matte_mode(&mut self, mode: &str) -> Result<(), PyErr> {
+        if !self.is_in_setup {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Cannot set the matte mode outside of setup",
+            ));
+        }
+
+        self.matte_mode = match mode {
+            "off" => MatteMode::Off,
+            "alpha" => MatteMode::Alpha,
+            "rgb" => MatteMode::Rgb,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "unknown matte mode {mode}, expected off, alpha or rgb"
+                )))
+            }
+        };
+
+        Ok(())
+    }
+
     pub fn time(&self) -> f32 {
         self.time
     }
 
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// The top 32 bits of splitmix64(base seed + frame), so it's
+    /// small enough for `numpy.random.seed`.
+    pub fn seed(&self) -> u32 {
+        let mut z = self.base_seed.wrapping_add(self.frame as u64);
+        z = z.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        ((z ^ (z >> 31)) >> 32) as u32
+    }
+
+    /// Seeds `random`, `numpy.random` and torch if it's installed with `seed()`.
+    pub fn seed_all(&self, py: Python) -> PyResult<u32> {
+        let seed = self.seed();
+
+        py.import_bound("random")?.call_method1("seed", (seed,))?;
+        py.import_bound("numpy.random")?
+            .call_method1("seed", (seed,))?;
+
+        // a script that never imported torch doesn't need it seeded
+        if torch_imported(py) {
+            py.import_bound("torch")?
+                .call_method1("manual_seed", (seed,))?;
+        }
+
+        Ok(seed)
+    }
+
+    /// Shows `text` to the user under `name`, replacing what was there.
+    /// Unlike `log` it sticks around until it is replaced, None removes it.
+    pub fn set_status(&mut self, name: &str, text: Option<String>) {
+        match text {
+            Some(text) => {
+                self.statuses.insert(name.to_owned(), text);
+            }
+            None => {
+                self.statuses.shift_remove(name);
+            }
+        }
+    }
+
+    /// Routes a message straight into the `log` crate, unknown levels are logged as info.
+    #[pyo3(signature = (message, level="info"))]
+    pub fn log(&self, message: &str, level: &str) {
+        match level {
+            "debug" => log::debug!("{message}"),
+            "warning" => log::warn!("{message}"),
+            "error" => log::error!("{message}"),
+            _ => log::info!("{message}"),
+        }
+    }
+
+    // The helpers below are public API, keep their behaviour stable even if
+    // numpy_helper.py is reorganized.
+
+    /// The centered `height` x `width` region of `array`, as a view.
+    pub fn center_crop(
+        &self,
+        py: Python,
+        array: PyObject,
+        height: u32,
+        width: u32,
+    ) -> Result<PyObject, PyErr> {
+        self.helper_module
+            .call_method1(py, "center_crop", (array, height, width))
+    }
+
+    /// An RGBA ordered view of an ARGB array.
+    pub fn rgba_view(&self, py: Python, array: PyObject) -> Result<PyObject, PyErr> {
+        self.helper_module.call_method1(py, "rgba_view", (array,))
+    }
+
+    /// Reorders an RGBA array to ARGB in place.
+    pub fn swizzle_in_place(&self, py: Python, array: PyObject) -> Result<(), PyErr> {
+        self.helper_module
+            .call_method1(py, "swizzle_in_place", (array,))?;
+        Ok(())
+    }
+
+    /// What the host and this build support, so scripts can check
+    /// before relying on something instead of catching the failure.
+    pub fn capabilities<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let formats: Vec<String> = ImageFormat::ALL.iter().map(|f| format!("{f:?}")).collect();
+
+        let capabilities = PyDict::new_bound(py);
+        capabilities.set_item("formats", formats)?;
+        capabilities.set_item("max_inputs", self.max_inputs)?;
+        capabilities.set_item("sequential", self.sequential_supported)?;
+        capabilities.set_item("async", true)?;
+        capabilities.set_item("version", env!("CARGO_PKG_VERSION"))?;
+
+        Ok(capabilities)
+    }
+
     pub fn build_info(&self) -> String {
         let profile = if cfg!(debug_assertions) {
             String::from("Debug")
@@ -109,7 +510,10 @@ impl PyContext {
 
         self.output_size_override = Some(OutputSize { width, height });
 
+        // it's what `max_output_size` reports for the rest of setup
         if self.is_in_setup {
+            self.target_width = width;
+            self.target_height = height;
             return Ok(());
         }
 
@@ -123,10 +527,48 @@ impl PyContext {
         Ok(())
     }
 
-    #[pyo3(signature = (name, min=-100.0, max=100.0, default=0.0))]
-    pub fn register_float(&mut self, name: &str, min: f32, max: f32, default: f32) {
-        let i = Variant::Float(Cfg::new(default, min, max));
-        self.registry.insert(name.to_owned(), i);
+    #[pyo3(signature = (
+        name,
+        min=-100.0,
+        max=100.0,
+        default=0.0,
+        step=None,
+        scale="linear",
+        description=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn register_float(
+        &mut self,
+        name: &str,
+        min: f32,
+        max: f32,
+        default: f32,
+        step: Option<f32>,
+        scale: &str,
+        description: Option<String>,
+    ) -> Result<(), PyErr> {
+        if step.is_some_and(|s| s <= 0.0) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "step must be greater than zero",
+            ));
+        }
+
+        let Some(scale) = Scale::from_name(scale) else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "unknown scale {scale:?}, expected \"linear\" or \"log\""
+            )));
+        };
+
+        if scale == Scale::Logarithmic && min <= 0.0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "{name}: a log scale float needs min > 0, got {min}"
+            )));
+        }
+
+        let mut cfg = Cfg::new(default, min, max);
+        cfg.step = step;
+        self.register(name, Variant::Float(cfg, scale), description);
+        Ok(())
     }
 
     pub fn set_sequential_mode(&mut self, is_sequential: bool) -> Result<(), PyErr> {
@@ -144,6 +586,31 @@ impl PyContext {
         self.is_sequential_mode
     }
 
+    /// Generators that still take image inputs can have After Effects start
+    /// them with "Is Image Filter" unticked.
+    pub fn set_image_filter_default(&mut self, enabled: bool) -> Result<(), PyErr> {
+        if !self.is_in_setup {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Cannot set the image filter default outside of setup",
+            ));
+        }
+
+        self.image_filter_default = enabled;
+        Ok(())
+    }
+
+    /// Declares another output besides the primary one, written through
+    /// `ctx.output(name)`. Hosts choose which of these to show.
+    pub fn register_output(&mut self, name: &str) -> Result<(), PyErr> {
+        self.bail_if_running()?;
+
+        if !self.output_names.iter().any(|n| n == name) {
+            self.output_names.push(name.to_owned());
+        }
+
+        Ok(())
+    }
+
     #[inline(always)]
     fn bail_if_running(&self) -> Result<(), PyErr> {
         if !self.is_in_setup {
@@ -154,125 +621,465 @@ impl PyContext {
         Ok(())
     }
 
+    #[pyo3(signature = (name, default, map, description=None))]
     pub fn register_enum(
         &mut self,
         name: &str,
         default: i32,
-        map: HashMap<String, i32>,
+        map: IndexMap<String, i32>,
+        description: Option<String>,
     ) -> Result<(), PyErr> {
         self.bail_if_running()?;
         let i = Variant::TaggedInt(crate::variant::TaggedInt::new(default, map));
-        self.registry.insert(name.to_owned(), i);
+        self.register(name, i, description);
         Ok(())
     }
 
-    #[pyo3(signature = (name, min=[-100.0, -100.0], max=[100.0, 100.0], default=[0.0, 0.0]))]
+    /// Like `register_enum`, but `get_input` returns the selected option itself.
+    #[pyo3(signature = (name, options, default=None, description=None))]
+    pub fn register_choice(
+        &mut self,
+        name: &str,
+        options: Vec<String>,
+        default: Option<&str>,
+        description: Option<String>,
+    ) -> Result<(), PyErr> {
+        self.bail_if_running()?;
+
+        if options.is_empty() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "{name}: a choice needs at least one option"
+            )));
+        }
+
+        let default = match default {
+            Some(d) => options.iter().position(|o| o == d).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "{name}: default {d:?} is not one of the options"
+                ))
+            })?,
+            None => 0,
+        };
+
+        let tags = options
+            .into_iter()
+            .enumerate()
+            .map(|(i, o)| (o, i as i32))
+            .collect();
+
+        let mut choice = crate::variant::TaggedInt::new(default as i32, tags);
+        choice.returns_label = true;
+        self.register(name, Variant::TaggedInt(choice), description);
+        Ok(())
+    }
+
+    #[pyo3(signature = (
+        name,
+        min=[-100.0, -100.0],
+        max=[100.0, 100.0],
+        default=[0.0, 0.0],
+        description=None
+    ))]
     pub fn register_vector(
         &mut self,
         name: &str,
         min: [f32; 2],
         max: [f32; 2],
         default: [f32; 2],
+        description: Option<String>,
     ) -> Result<(), PyErr> {
         self.bail_if_running()?;
         let i = Variant::Vector2(Cfg::new(default, min, max));
-        self.registry.insert(name.to_owned(), i);
+        self.register(name, i, description);
         Ok(())
     }
 
-    #[pyo3(signature = (name, min=-100, max=100, default=0))]
+    #[pyo3(signature = (name, min=-100, max=100, default=0, step=None, description=None))]
     pub fn register_int(
         &mut self,
         name: &str,
         min: i32,
         max: i32,
         default: i32,
+        step: Option<i32>,
+        description: Option<String>,
     ) -> Result<(), PyErr> {
         self.bail_if_running()?;
-        let i = Variant::Int(Cfg::new(default, min, max));
-        self.registry.insert(name.to_owned(), i);
+
+        if step.is_some_and(|s| s <= 0) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "step must be greater than zero",
+            ));
+        }
+
+        let mut cfg = Cfg::new(default, min, max);
+        cfg.step = step;
+        self.register(name, Variant::Int(cfg), description);
         Ok(())
     }
 
-    #[pyo3(signature = (name, default=false))]
-    pub fn register_bool(&mut self, name: &str, default: bool) -> Result<(), PyErr> {
+    #[pyo3(signature = (name, default=false, description=None))]
+    pub fn register_bool(
+        &mut self,
+        name: &str,
+        default: bool,
+        description: Option<String>,
+    ) -> Result<(), PyErr> {
         self.bail_if_running()?;
         let i = Variant::Bool(DiscreteCfg::new(default));
-        self.registry.insert(name.to_owned(), i);
+        self.register(name, i, description);
         Ok(())
     }
 
-    #[pyo3(signature = (name, default=[1.0, 1.0, 1.0, 1.0]))]
-    pub fn register_color(&mut self, name: &str, default: [f32; 4]) -> Result<(), PyErr> {
+    #[pyo3(signature = (name, default=[1.0, 1.0, 1.0, 1.0], description=None))]
+    pub fn register_color(
+        &mut self,
+        name: &str,
+        default: [f32; 4],
+        description: Option<String>,
+    ) -> Result<(), PyErr> {
         self.bail_if_running()?;
         let i = Variant::Color(DiscreteCfg::new(default));
-        self.registry.insert(name.to_owned(), i);
+        self.register(name, i, description);
+        Ok(())
+    }
+
+    /// Degrees, `get_input` wraps them into [0, 360).
+    #[pyo3(signature = (name, default=0.0, description=None))]
+    pub fn register_angle(
+        &mut self,
+        name: &str,
+        default: f32,
+        description: Option<String>,
+    ) -> Result<(), PyErr> {
+        self.bail_if_running()?;
+        let i = Variant::Angle(DiscreteCfg::new(default));
+        self.register(name, i, description);
         Ok(())
     }
 
-    pub fn register_image_input(&mut self, name: &str) -> Result<(), PyErr> {
+    #[pyo3(signature = (name, description=None, color_correction=None, default=None))]
+    pub fn register_image_input(
+        &mut self,
+        name: &str,
+        description: Option<String>,
+        color_correction: Option<bool>,
+        default: Option<PathBuf>,
+    ) -> Result<(), PyErr> {
         self.bail_if_running()?;
-        let i = Variant::Image(DiscreteCfg::new(Image::Input));
-        self.registry.insert(name.to_owned(), i);
+        let settings = ImageSettings {
+            color_correction,
+            dtype: None,
+        };
+        let image = Variant::Image(DiscreteCfg::new(Image::Input), settings);
+        self.register(name, image, description);
+
+        if let Some(default) = default {
+            self.image_defaults.insert(name.to_owned(), default);
+        } else {
+            self.image_defaults.remove(name);
+        }
+
         Ok(())
     }
+
+    /// Converts the image input `name` to `dtype` whatever format the host
+    /// sends, "uint8", "uint16" or "float32".
+    pub fn request_input_format(&mut self, name: &str, dtype: &str) -> Result<(), PyErr> {
+        self.bail_if_running()?;
+
+        let Some(dtype) = Dtype::from_name(dtype) else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "unknown format {dtype}, expected uint8, uint16 or float32"
+            )));
+        };
+
+        match self.registry.get_mut(name) {
+            Some(Variant::Image(cfg, settings)) if cfg.current == Image::Input => {
+                settings.dtype = Some(dtype);
+                Ok(())
+            }
+            _ => Err(PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!(
+                "no image input named {name} was registered in setup"
+            ))),
+        }
+    }
 }
 
 impl PyContext {
+    // every register_* ends up here
+    fn register(&mut self, name: &str, mut input: Variant, description: Option<String>) {
+        input.set_description(description);
+        self.registry.insert(name.to_owned(), input);
+    }
+
     pub fn new(
         output_descriptor: &OutDesc,
         inputs: IndexMap<String, (PyObject, ImageFormat)>,
+        temporal_inputs: HashMap<(String, i32), (PyObject, ImageFormat)>,
         target: PyObject,
+        outputs: IndexMap<String, PyObject>,
+        cancellation: Option<CancellationToken>,
         runner: &PythonRunner,
     ) -> Self {
-        let registry = if runner.initialized {
-            runner.registry.clone()
+        let (registry, output_names, statuses) = if runner.initialized {
+            (
+                runner.registry.clone(),
+                runner.outputs.clone(),
+                runner.statuses.clone(),
+            )
         } else {
             Default::default()
         };
 
+        // setup is handed an empty target, so remember a real one for it
+        let (target_width, target_height) = match runner.setup_output_size() {
+            Some(size) if !runner.initialized => (size.width, size.height),
+            _ => (output_descriptor.width, output_descriptor.height),
+        };
+
         Self {
-            target_width: output_descriptor.width,
-            target_height: output_descriptor.height,
+            target_width,
+            target_height,
             inputs,
+            temporal_inputs,
+            temporal_window: runner.temporal_window,
             target,
+            image_defaults: Default::default(),
+            output_names,
+            outputs,
+            scratch_outputs: RefCell::default(),
             registry,
             time: runner.time,
+            frame: runner.frame,
+            fps: runner.fps,
+            base_seed: runner.base_seed,
+            comp_size: runner.comp_size.clone(),
+            layer_name: runner.layer_name.clone(),
+            name: None,
             output_size_override: runner.output_size.clone(),
             is_in_setup: !runner.initialized,
             is_sequential_mode: runner.is_sequential,
+            image_filter_default: true,
             uses_automatic_color_correction: runner.uses_automatic_color_correction,
+            straight_alpha: runner.straight_alpha,
+            matte_mode: runner.matte_mode,
+            matte: None,
             helper_module: runner.helper_module.clone(),
+            torch_helper: runner.torch_helper.clone(),
+            has_cuda: runner.has_cuda.clone(),
+            cancellation,
+            progress: runner.progress.clone(),
+            event_loop: runner.event_loop.clone(),
+            background_tasks: runner.background_tasks.clone(),
+            state: runner.state.clone(),
+            output_accessed: Cell::new(false),
+            statuses,
+            max_inputs: runner.max_inputs,
+            sequential_supported: runner.sequential_supported,
         }
     }
 
+    pub(crate) fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    pub(crate) fn image_filter_default(&self) -> bool {
+        self.image_filter_default
+    }
+
+    /// Whether the script called `output()`, returning a tensor counts.
+    pub(crate) fn output_accessed(&self) -> bool {
+        self.output_accessed.get()
+    }
+
+    // `name` is the image input the array belongs to, it may override
+    // the script wide color correction setting
     fn swizzle_to_rgba<'a>(
         &'a self,
         py: Python<'a>,
+        name: &str,
         array: PyObject,
         array_fmt: ImageFormat,
     ) -> Result<PyObject, PyErr> {
-        if self.uses_automatic_color_correction
+        let ImageSettings {
+            color_correction,
+            dtype,
+        } = match self.registry.get(name) {
+            Some(Variant::Image(_, settings)) => settings.clone(),
+            _ => ImageSettings::default(),
+        };
+
+        let corrected = color_correction.unwrap_or(self.uses_automatic_color_correction)
             && matches!(
                 array_fmt,
                 ImageFormat::Argb16ae | ImageFormat::Argb32 | ImageFormat::Argb8
-            )
-        {
-            self.helper_module.call_method1(py, "rgba_view", (array,))
+            );
+
+        let view = if corrected {
+            self.helper_module.call_method1(py, "rgba_view", (array,))?
         } else {
-            array.call_method0(py, "view")
+            array.call_method0(py, "view")?
+        };
+
+        let view = if self.straight_alpha && array_fmt.is_premultiplied() {
+            let alpha = if corrected { 3 } else { 0 };
+            self.helper_module
+                .call_method1(py, "unpremultiply", (view, alpha, array_fmt.max_value()))?
+        } else {
+            view
+        };
+
+        let Some(dtype) = dtype else {
+            return Ok(view);
+        };
+
+        let args = (view, array_fmt.max_value(), dtype.name(), dtype.max_value());
+        self.helper_module.call_method1(py, "convert", args)
+    }
+
+    // Undoes `unpremultiply` on an output once the script is done with it,
+    // before it is swizzled back to ARGB. Outputs are always RGBA here,
+    // color correction only changes the order of inputs. None is the primary output.
+    pub(crate) fn premultiply_output(
+        &self,
+        py: Python,
+        name: Option<&str>,
+        fmt: ImageFormat,
+    ) -> Result<(), PyErr> {
+        let target = name
+            .and_then(|name| self.outputs.get(name))
+            .unwrap_or(&self.target);
+
+        self.helper_module
+            .call_method1(py, "premultiply_in_place", (target, 3, fmt.max_value()))?;
+
+        Ok(())
+    }
+
+    // Single channel outputs don't need a matte, they already are one.
+    pub(crate) fn allocate_matte(&mut self, py: Python, fmt: ImageFormat) -> Result<(), PyErr> {
+        if self.matte_mode != MatteMode::Off && fmt.channels() > 1 {
+            let matte = self
+                .helper_module
+                .call_method1(py, "matte_like", (&self.target,))?;
+            self.matte = Some(matte);
         }
+
+        Ok(())
     }
 
-    pub fn swizzle_output_to_argb<'a>(&'a self, py: Python<'a>) -> Result<(), PyErr> {
-        // FIXME: this is a temporary holdover, numpy is actually slower when doing this
-        // swizzle than std::simd swizzle, but I want to stay off nightly for now.
+    // Spreads the matte over the primary output. This runs before `premultiply_output`
+    // and the ARGB swizzle, so in alpha mode the color is left white for straight alpha
+    // and comes out premultiplied either way.
+    pub(crate) fn broadcast_matte(&self, py: Python, fmt: ImageFormat) -> Result<(), PyErr> {
+        let Some(matte) = self.matte.as_ref() else {
+            return Ok(());
+        };
+
+        let into_alpha = self.matte_mode == MatteMode::Alpha;
+        let premultiplied = fmt.is_premultiplied() && !self.straight_alpha;
+
+        self.helper_module.call_method1(
+            py,
+            "broadcast_matte",
+            (&self.target, matte, into_alpha, premultiplied, fmt.max_value()),
+        )?;
+
+        Ok(())
+    }
+
+    // Only used when the native swizzle can't handle the buffer,
+    // e.g. if it isn't aligned to the pixel size. None is the primary output.
+    pub fn swizzle_output_to_argb<'a>(
+        &'a self,
+        py: Python<'a>,
+        name: Option<&str>,
+    ) -> Result<(), PyErr> {
+        let target = name
+            .and_then(|name| self.outputs.get(name))
+            .unwrap_or(&self.target);
+
         self.helper_module
-            .call_method1(py, "swizzle_in_place", (&self.target,))?;
+            .call_method1(py, "swizzle_in_place", (target,))?;
+
+        Ok(())
+    }
+
+    /// If `run` returned a tensor, copy it into the output. Contiguous cpu tensors
+    /// laid out exactly like the output buffer are copied straight from the tensor's memory,
+    /// anything else goes through numpy.
+    pub(crate) fn write_returned_tensor(
+        &self,
+        py: Python,
+        returned: &Py<PyAny>,
+        output: &mut OutDesc,
+    ) -> Result<(), PyErr> {
+        // a tensor can't have come back without the script importing torch
+        if !torch_imported(py) {
+            return Ok(());
+        }
+
+        let Some(torch_helper) = self.torch_helper(py) else {
+            return Ok(());
+        };
+
+        if !torch_helper
+            .call_method1(py, "is_tensor", (returned,))?
+            .extract::<bool>(py)?
+        {
+            return Ok(());
+        }
+
+        let target = self.output(py, None)?;
+
+        let direct: Option<(usize, usize)> = torch_helper
+            .call_method1(py, "contiguous_bytes", (returned, &target))?
+            .extract(py)?;
+
+        match direct {
+            // a padded or cropped output won't match the tensor length
+            Some((ptr, len)) if len == output.data.len() => {
+                let src = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+                output.data.copy_from_slice(src);
+            }
+            _ => {
+                torch_helper.call_method1(py, "copy_tensor", (&target, returned))?;
+            }
+        }
 
         Ok(())
     }
 
+    // a view of the middle of `array` if the script asked for a smaller output
+    fn crop_to_output_size(&self, py: Python, array: &PyObject) -> PyResult<PyObject> {
+        if let Some(OutputSize { width, height }) = self.output_size_override {
+            self.helper_module
+                .call_method1(py, "center_crop", (array, height, width))
+        } else {
+            Ok(array.clone_ref(py))
+        }
+    }
+
+    // Compiled the first time it's needed rather than with the runner, it imports torch
+    // which may only be found once the venv and script directory are on the path.
+    // Failures aren't cached, so installing torch later still works.
+    fn torch_helper(&self, py: Python) -> Option<&Py<PyModule>> {
+        if let Some(helper) = self.torch_helper.get() {
+            return Some(helper);
+        }
+
+        let module = PyModule::from_code_bound(
+            py,
+            include_str!("./torch_helper.py"),
+            "torch_helper.py",
+            "torch_helper",
+        )
+        .ok()?;
+
+        Some(self.torch_helper.get_or_init(|| module.unbind()))
+    }
+
     pub fn output_size_requested(&self) -> Option<OutputSize> {
         self.output_size_override.clone()
     }
@@ -281,7 +1088,34 @@ impl PyContext {
         self.uses_automatic_color_correction
     }
 
+    pub(crate) fn straight_alpha(&self) -> bool {
+        self.straight_alpha
+    }
+
+    pub(crate) fn statuses(&self) -> IndexMap<String, String> {
+        self.statuses.clone()
+    }
+
+    pub(crate) fn matte_mode(&self) -> MatteMode {
+        self.matte_mode
+    }
+
     pub(crate) fn clone_registry(&self) -> IndexMap<String, crate::Variant> {
         self.registry.clone()
     }
+
+    pub(crate) fn output_names(&self) -> Vec<String> {
+        self.output_names.clone()
+    }
+
+    pub(crate) fn image_defaults(&self) -> HashMap<String, PathBuf> {
+        self.image_defaults.clone()
+    }
+}
+
+fn torch_imported(py: Python) -> bool {
+    py.import_bound("sys")
+        .and_then(|sys| sys.getattr("modules"))
+        .and_then(|modules| modules.contains("torch"))
+        .unwrap_or(false)
 }
@@ -1,4 +1,5 @@
 use pyo3::prelude::*;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -19,11 +20,23 @@ pub enum GolobulError {
     RuntimeError {
         stderr: String,
         stdout: Option<String>,
+        /// The python exception's class name, e.g. `ImportError`
+        exception_type: Option<String>,
+    },
+    #[error(
+        "invalid buffer size{} (expected {expected:?}, found {found:?})",
+        .input.as_ref().map(|name| format!(" for input {name}")).unwrap_or_default()
+    )]
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+        /// Which input it was, None for outputs
+        input: Option<String>,
     },
-    #[error("invalid buffer size (expected {expected:?}, found {found:?})")]
-    SizeMismatch { expected: usize, found: usize },
     #[error("Output buffer passed with 0 height or width!")]
     ZeroDimension,
+    #[error("stride of {stride} bytes is shorter than a {row_size} byte row")]
+    InvalidStride { stride: u32, row_size: usize },
     #[error("Error Casting py type")]
     CastingError,
     #[error("Tried to get slice from noncontiguous numpy array")]
@@ -34,27 +47,148 @@ pub enum GolobulError {
     BoundError,
     #[error("Error running async code")]
     Asio,
-    #[error("Type mismatch while setting variable.")]
-    TypeMismatch,
+    #[error("Type mismatch setting {name}: expected {expected}, found {found}")]
+    TypeMismatch {
+        name: String,
+        /// The kind of input the script registered, e.g. `float`
+        expected: String,
+        found: String,
+    },
     #[error("No Input {0} found")]
     MissingVar(String),
+    #[error("{0} is not one of the enum's options")]
+    InvalidValue(i32),
+    #[error("Could not set {name}: {source}")]
+    InvalidVar {
+        name: String,
+        source: Box<GolobulError>,
+    },
+    #[error("Script run exceeded the configured timeout")]
+    Timeout,
+    #[error("Script setup exceeded the configured timeout, slow one time work belongs in prime")]
+    SetupTimeout,
+    #[error("Cancelled before the script finished")]
+    Cancelled,
+    #[error("Could not find pip for the embedded interpreter")]
+    PipUnavailable,
+    #[error("Script registered {registered} inputs, but at most {max} are supported")]
+    TooManyInputs { registered: usize, max: usize },
+    #[error("Could not load the default image {path:?}: {reason}")]
+    DefaultImage { path: PathBuf, reason: String },
 }
 
-pub fn traceback(e: PyErr, stdout: &Py<StdOutCatcher>, py: Python) -> GolobulError {
-    let line = e
-        .traceback_bound(py)
-        .and_then(|tb| tb.getattr("tb_lineno").ok())
-        .map(|e| format!("line {e}: "))
-        .unwrap_or_default();
+impl GolobulError {
+    /// True if the script failed to import something, installing its
+    /// requirements or setting the python environment may fix it.
+    pub fn is_missing_dependency(&self) -> bool {
+        matches!(
+            self,
+            GolobulError::RuntimeError {
+                exception_type: Some(t),
+                ..
+            } if t == "ImportError" || t == "ModuleNotFoundError"
+        )
+    }
+
+    // names the input a size or type mismatch came from
+    pub(crate) fn for_input(self, name: &str) -> Self {
+        match self {
+            GolobulError::TypeMismatch {
+                expected,
+                found,
+                ..
+            } => GolobulError::TypeMismatch {
+                name: name.to_owned(),
+                expected,
+                found,
+            },
+            GolobulError::SizeMismatch {
+                expected,
+                found,
+                ..
+            } => GolobulError::SizeMismatch {
+                expected,
+                found,
+                input: Some(name.to_owned()),
+            },
+            e => e,
+        }
+    }
+}
 
+pub fn traceback(
+    e: PyErr,
+    stdout: &Py<StdOutCatcher>,
+    script: &Py<PyModule>,
+    py: Python,
+) -> GolobulError {
     let stdout = stdout.borrow_mut(py).output.take();
 
     GolobulError::RuntimeError {
-        stderr: format!("{line}{e}"),
+        stderr: format_exception(&e, script, py),
         stdout,
+        exception_type: exception_type(&e, py),
     }
 }
 
+pub fn exception_type(e: &PyErr, py: Python) -> Option<String> {
+    e.get_type_bound(py).getattr("__name__").ok()?.extract().ok()
+}
+
+/// A one line summary, followed by the full python traceback if there is one.
+/// The summary's line number is in `script` where possible.
+pub fn format_exception(e: &PyErr, script: &Py<PyModule>, py: Python) -> String {
+    let line = user_line(e, script, py)
+        .map(|line| format!("line {line}: "))
+        .unwrap_or_default();
+
+    let summary = format!("{line}{e}");
+
+    // the three argument form works on every python we support
+    let full = py.import_bound("traceback").and_then(|tb| {
+        tb.call_method1(
+            "format_exception",
+            (e.get_type_bound(py), e.value_bound(py), e.traceback_bound(py)),
+        )?
+        .extract::<Vec<String>>()
+    });
+
+    match full {
+        Ok(lines) => format!("{summary}\n{}", lines.concat().trim_end()),
+        Err(_) => summary,
+    }
+}
+
+// The line in the deepest frame of the script itself, a line inside numpy or
+// some other library the script called is no use to whoever wrote it. Falls
+// back to the outermost frame when the script isn't in the traceback at all.
+fn user_line(e: &PyErr, script: &Py<PyModule>, py: Python) -> Option<u32> {
+    let script_name: String = script.bind(py).getattr("__name__").ok()?.extract().ok()?;
+
+    let mut next = e.traceback_bound(py).map(Bound::into_any);
+    let mut outermost = None;
+    let mut user = None;
+
+    while let Some(tb) = next {
+        let line = tb.getattr("tb_lineno").and_then(|l| l.extract::<u32>()).ok();
+        outermost = outermost.or(line);
+
+        let name = tb
+            .getattr("tb_frame")
+            .and_then(|frame| frame.getattr("f_globals"))
+            .and_then(|globals| globals.get_item("__name__"))
+            .and_then(|name| name.extract::<String>());
+
+        if name.is_ok_and(|name| name == script_name) {
+            user = line.or(user);
+        }
+
+        next = tb.getattr("tb_next").ok().filter(|tb| !tb.is_none());
+    }
+
+    user.or(outermost)
+}
+
 #[pyclass]
 #[derive(Default)]
 pub struct StdOutCatcher {
@@ -76,6 +210,55 @@ impl StdOutCatcher {
     }
 }
 
+/// Replaces `warnings.showwarning` so warnings from the script and its
+/// libraries end up in the log next to `ctx.log`.
+#[pyclass]
+pub struct WarningLogger;
+
+impl WarningLogger {
+    /// Best effort, a script that can't have its warnings routed still runs.
+    /// Only the first call does anything, the filter would otherwise pile up
+    /// in `warnings.filters` with every load.
+    pub fn install(py: Python) {
+        let installed = py.import_bound("warnings").and_then(|warnings| {
+            if warnings.getattr("showwarning")?.is_instance_of::<WarningLogger>() {
+                return Ok(());
+            }
+
+            // deprecations are hidden outside of __main__ by default,
+            // they're the ones users most need to see
+            let deprecation = py.get_type_bound::<pyo3::exceptions::PyDeprecationWarning>();
+            warnings.call_method1("simplefilter", ("default", deprecation))?;
+            warnings.setattr("showwarning", Py::new(py, WarningLogger)?)
+        });
+
+        if let Err(e) = installed {
+            log::debug!("could not route python warnings to the log: {e}");
+        }
+    }
+}
+
+#[pymethods]
+impl WarningLogger {
+    // the trailing file and source line are of no use to us, libraries calling
+    // this directly don't always pass a str and an int for the others
+    #[pyo3(signature = (message, category, filename, lineno, *_rest))]
+    fn __call__(
+        &self,
+        message: &Bound<PyAny>,
+        category: &Bound<PyAny>,
+        filename: &Bound<PyAny>,
+        lineno: &Bound<PyAny>,
+        _rest: &Bound<pyo3::types::PyTuple>,
+    ) {
+        let category = category
+            .getattr("__name__")
+            .map_or_else(|_| String::from("Warning"), |n| n.to_string());
+
+        log::warn!("{category}: {message} ({filename}:{lineno})");
+    }
+}
+
 #[pymethods]
 impl StdOutCatcher {
     fn write(&mut self, data: &str) {
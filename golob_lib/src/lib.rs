@@ -1,57 +1,125 @@
-use errors::{traceback, StdOutCatcher};
+use errors::{traceback, StdOutCatcher, WarningLogger};
 use pyo3::types::IntoPyDict;
 pub mod context;
 mod errors;
 pub mod event_loop;
+mod swizzle;
 mod variant;
+mod watchdog;
 
 use indexmap::IndexMap;
 use numpy::{npyffi, PY_ARRAY_API};
 use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
     path::{Path, PathBuf},
-    sync::mpsc::Receiver,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc, OnceLock,
+    },
+    time::Duration,
 };
 
 pub use errors::GolobulError;
 
 use pyo3::{
     prelude::*,
-    types::{PyFunction, PyModule},
+    types::{PyDict, PyFunction, PyModule, PySet},
 };
 
-pub use variant::{Cfg, DiscreteCfg, Image, Variant};
+pub use variant::{
+    Cfg, DiscreteCfg, Dtype, Image, ImageSettings, InputKind, InputSpec, Scale, TaggedInt,
+    Variant,
+};
 
 /// A list of supported image formats, using varying inputs and outputs
 /// may require additional copies and casting.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ImageFormat {
     Rgba8,
     Argb8,
+    // After Effects' 16 bit, u16 channels that only go up to 32768. Apart from that
+    // range and the ARGB order it is handled exactly like Rgba16, nothing is
+    // rescaled or gamma corrected on the way in or out.
     Argb16ae,
     Rgba16,
     Argb32,
     Rgba32,
+    // Single channel formats are exposed to python as (height, width) arrays,
+    // there is no channel order so swizzling and color correction are no-ops.
+    Gray8,
+    Gray16,
+    // Opaque RGB, there is no alpha to reorder so color correction is skipped.
+    Rgb8,
 }
 
 // allows python results to be polled outside of
 // the GIL
 enum MaybeFuture {
-    Done(Option<String>),
+    Done(RenderReport),
     Channel(
         Receiver<PyResult<Py<PyAny>>>,
         Py<context::PyContext>,
         Py<StdOutCatcher>,
+        // the concurrent future, kept so it can be cancelled
+        Py<PyAny>,
     ),
 }
 
 impl ImageFormat {
+    /// Every format a runner accepts for inputs and outputs.
+    pub const ALL: [ImageFormat; 9] = [
+        ImageFormat::Rgba8,
+        ImageFormat::Argb8,
+        ImageFormat::Argb16ae,
+        ImageFormat::Rgba16,
+        ImageFormat::Argb32,
+        ImageFormat::Rgba32,
+        ImageFormat::Gray8,
+        ImageFormat::Gray16,
+        ImageFormat::Rgb8,
+    ];
+
     pub fn bytes_per_pixel(&self) -> usize {
         match self {
+            ImageFormat::Gray8 => 1,
+            ImageFormat::Gray16 => 2,
+            ImageFormat::Rgb8 => 3,
             ImageFormat::Rgba8 | ImageFormat::Argb8 => 4,
             ImageFormat::Argb16ae | ImageFormat::Rgba16 => 8,
             ImageFormat::Argb32 | ImageFormat::Rgba32 => 16,
         }
     }
+
+    pub fn channels(&self) -> usize {
+        match self {
+            ImageFormat::Gray8 | ImageFormat::Gray16 => 1,
+            ImageFormat::Rgb8 => 3,
+            _ => 4,
+        }
+    }
+
+    /// AE hands over premultiplied pixels, everything else is assumed straight.
+    pub fn is_premultiplied(&self) -> bool {
+        matches!(
+            self,
+            ImageFormat::Argb8 | ImageFormat::Argb16ae | ImageFormat::Argb32
+        )
+    }
+
+    /// The value of a fully opaque alpha channel.
+    pub fn max_value(&self) -> f32 {
+        match self {
+            ImageFormat::Rgba8 | ImageFormat::Argb8 | ImageFormat::Gray8 | ImageFormat::Rgb8 => {
+                255.0
+            }
+            // AE's 16 bit is really 15 bits
+            ImageFormat::Argb16ae => 32768.0,
+            ImageFormat::Rgba16 | ImageFormat::Gray16 => 65535.0,
+            ImageFormat::Argb32 | ImageFormat::Rgba32 => 1.0,
+        }
+    }
 }
 
 /// A borrowed view into an image stored in memory
@@ -87,32 +155,175 @@ impl<'a> OutDesc<'a> {
         }
     }
     pub fn is_well_structured(&self) -> Result<(), GolobulError> {
-        let bytes_per_pixel = self.fmt.bytes_per_pixel();
-        let row_size = self.width as usize * bytes_per_pixel;
-        let padded_row_size = self.stride.unwrap_or(row_size as u32) as usize;
-        let expected_data_size = padded_row_size * self.height as usize;
+        check_layout(self.fmt, self.data.len(), self.width, self.height, self.stride)
+    }
+}
+
+impl<'a> InDesc<'a> {
+    /// The same checks as `OutDesc::is_well_structured`, a short buffer
+    /// would otherwise be read past its end by the numpy view.
+    pub fn is_well_structured(&self) -> Result<(), GolobulError> {
+        check_layout(self.fmt, self.data.len(), self.width, self.height, self.stride)
+    }
+}
 
-        if self.width == 0 || self.height == 0 {
-            return Err(GolobulError::ZeroDimension);
+fn check_layout(
+    fmt: ImageFormat,
+    len: usize,
+    width: u32,
+    height: u32,
+    stride: Option<u32>,
+) -> Result<(), GolobulError> {
+    let bytes_per_pixel = fmt.bytes_per_pixel();
+    let row_size = width as usize * bytes_per_pixel;
+    let padded_row_size = stride.unwrap_or(row_size as u32) as usize;
+    let expected_data_size = padded_row_size * height as usize;
+
+    if width == 0 || height == 0 {
+        return Err(GolobulError::ZeroDimension);
+    }
+
+    // rows would overlap, the length check alone can't catch it
+    if padded_row_size < row_size {
+        return Err(GolobulError::InvalidStride {
+            stride: padded_row_size as u32,
+            row_size,
+        });
+    }
+
+    if len != expected_data_size {
+        return Err(GolobulError::SizeMismatch {
+            expected: expected_data_size,
+            found: len,
+            input: None,
+        });
+    }
+
+    Ok(())
+}
+
+// Identifies the numpy view over a host buffer, hosts can hand the same buffer to
+// several inputs. Only kept across passes for outputs the host gave a generation,
+// a freed buffer's address can come back holding something else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ViewKey {
+    fmt: ImageFormat,
+    ptr: usize,
+    width: u32,
+    height: u32,
+    stride: Option<u32>,
+    writeable: bool,
+}
+
+impl ViewKey {
+    fn of_input(desc: &InDesc) -> Self {
+        Self {
+            fmt: desc.fmt,
+            ptr: desc.data.as_ptr() as usize,
+            width: desc.width,
+            height: desc.height,
+            stride: desc.stride,
+            writeable: false,
         }
+    }
 
-        if self.data.len() != expected_data_size {
-            return Err(GolobulError::SizeMismatch {
-                expected: expected_data_size,
-                found: self.data.len(),
-            });
+    fn of_output(desc: &OutDesc) -> Self {
+        Self {
+            fmt: desc.fmt,
+            ptr: desc.data.as_ptr() as usize,
+            width: desc.width,
+            height: desc.height,
+            stride: desc.stride,
+            writeable: true,
         }
+    }
+}
 
-        Ok(())
+fn cached_view(
+    py: Python,
+    key: ViewKey,
+    views: &mut HashMap<ViewKey, Py<PyAny>>,
+    build: impl FnOnce() -> Py<PyAny>,
+) -> Py<PyAny> {
+    views.entry(key).or_insert_with(build).clone_ref(py)
+}
+
+// an output view from the last pass, if the host vouched for its buffer
+fn reuse_view(kept: &mut HashMap<ViewKey, Py<PyAny>>, key: ViewKey) -> Option<Py<PyAny>> {
+    let view = kept.remove(&key)?;
+    log::debug!("reusing numpy view {key:?}");
+    Some(view)
+}
+
+/// A decoded `register_image_input(default=...)`
+#[derive(Debug)]
+struct DefaultImage {
+    fmt: ImageFormat,
+    // kept as words so 16 and 32 bit views stay aligned
+    data: Vec<u32>,
+    width: u32,
+    height: u32,
+}
+
+impl DefaultImage {
+    // decodes at the bit depth of the output, so defaults look like the
+    // layers the host would pass in
+    fn decode(image: image::DynamicImage, output: ImageFormat) -> Self {
+        let (fmt, data) = match output {
+            ImageFormat::Rgba16 | ImageFormat::Gray16 => {
+                let pixels = image.to_rgba16().into_raw();
+                (ImageFormat::Rgba16, to_words(bytemuck::cast_slice(&pixels)))
+            }
+            // AE's 16 bit layers only go up to 32768
+            ImageFormat::Argb16ae => {
+                let pixels: Vec<u16> = image
+                    .to_rgba16()
+                    .iter()
+                    .map(|&v| ((v as u32 * 32768 + 32767) / 65535) as u16)
+                    .collect();
+                (ImageFormat::Rgba16, to_words(bytemuck::cast_slice(&pixels)))
+            }
+            ImageFormat::Argb32 | ImageFormat::Rgba32 => {
+                let pixels = image.to_rgba32f().into_raw();
+                (ImageFormat::Rgba32, bytemuck::cast_slice(&pixels).to_vec())
+            }
+            _ => (ImageFormat::Rgba8, to_words(&image.to_rgba8().into_raw())),
+        };
+
+        Self {
+            fmt,
+            data,
+            width: image.width(),
+            height: image.height(),
+        }
     }
 }
 
+fn to_words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OutputSize {
     pub width: u32,
     pub height: u32,
 }
 
+/// What a successful `RenderPass::submit` did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderReport {
+    /// The part of the primary output the script was given, a centered region
+    /// smaller than the buffer if it asked for a smaller output size.
+    pub written_size: OutputSize,
+    /// Anything the script printed.
+    pub stdout: Option<String>,
+    /// See `PythonRunner::output_written`.
+    pub wrote_output: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct PythonRunner {
     script_module_uuid: String,
@@ -120,15 +331,44 @@ pub struct PythonRunner {
     script_module: Py<PyModule>,
     /// Numpy helper
     helper_module: Py<PyModule>,
+    /// Torch helper, compiled by the context the first time torch is needed
+    torch_helper: Arc<OnceLock<Py<PyModule>>>,
+    /// `ctx.has_cuda()`, asked of torch the first time a script wants it
+    has_cuda: Arc<OnceLock<bool>>,
     /// Global python asyncio event loop running on a background thread, on windows this
     /// must be initialized form the main thread.
     event_loop: Py<PyAny>,
+    /// Futures from `ctx.run_soon`, held until they finish
+    background_tasks: Py<PySet>,
     /// Registry of all the inputs
     registry: IndexMap<String, Variant>,
+    /// Outputs registered besides the primary one, in registration order
+    outputs: Vec<String>,
+    /// Images shown to the script for image inputs the host leaves empty
+    image_defaults: HashMap<String, PathBuf>,
+    /// Those images, decoded the first time they are needed
+    default_image_cache: HashMap<(PathBuf, ImageFormat), Arc<DefaultImage>>,
+    /// Output views of the last pass, kept while the host passes the same generation
+    output_views: HashMap<ViewKey, Py<PyAny>>,
+    output_generation: Option<u64>,
     /// Time that can be set by the user, single special case float input
     time: f32,
+    /// Frame index matching `time`, zero when the host isn't animating
+    frame: u32,
+    /// Frame rate of the host, zero if it was never provided
+    fps: f32,
+    /// Mixed with the frame index to make `ctx.seed()`
+    base_seed: u64,
+    /// Size of the composition the effect lives in, if the host knows it
+    comp_size: Option<OutputSize>,
+    /// Name of the layer the effect is applied to, if the host knows it
+    layer_name: Option<String>,
+    /// Set by the script with `ctx.set_name` during setup
+    name: Option<String>,
     /// The user requested outputsize that we will do out best to respect
     output_size: Option<OutputSize>,
+    /// Size of the last run's full output, what setup reports before the next one
+    last_output_size: Option<OutputSize>,
     /// Should be named `sites_packages_path`, another module search path.
     pyenv_path: Option<PathBuf>,
     /// Additionaly module search path
@@ -136,11 +376,58 @@ pub struct PythonRunner {
     /// If true then AE instances will have the option to render on a BG thread
     /// serially.
     is_sequential: bool,
+    /// Whether AE instances start out treating the first image input as the layer
+    /// the effect is applied to.
+    image_filter_default: bool,
     /// if true, we call finalization the output arrays and swizzle the views into the input
     /// arrays.
     uses_automatic_color_correction: bool,
+    /// If true premultiplied inputs are divided by alpha before the script sees
+    /// them, and premultiplied outputs are multiplied by it afterwards
+    straight_alpha: bool,
+    /// Set by `ctx.set_matte_mode` in setup
+    matte_mode: context::MatteMode,
     /// If true, setup has run successfully
     initialized: bool,
+    /// If set, runs that take longer than this are interrupted
+    run_timeout: Option<Duration>,
+    /// How long to wait on an `async def run`, falls back to `run_timeout`
+    async_timeout: Option<Duration>,
+    /// Interrupts `setup` when cancelled, see `set_setup_cancellation_token`
+    setup_cancellation: Option<CancellationToken>,
+    /// If set, setups that take longer than this are interrupted
+    setup_timeout: Option<Duration>,
+    /// Script reported progress for the current run
+    progress: Progress,
+    /// How many frames on either side of the current one the script asked for
+    temporal_window: u32,
+    /// Most inputs a script may register, None for no limit
+    max_inputs: Option<usize>,
+    /// `ctx.state`, kept between runs and replaced when a script is loaded
+    state: Py<PyDict>,
+    /// If the last run asked for the primary output at all
+    output_written: bool,
+    /// If the script's `prime` has run since it was loaded, or it has none
+    primed: bool,
+    /// Read only text from `ctx.set_status`, kept until the script replaces it
+    statuses: IndexMap<String, String>,
+    /// If the host can render serially, see `set_sequential_supported`
+    sequential_supported: bool,
+    /// Identifies the script and settings the last successful setup ran with
+    source_hash: Option<u64>,
+    /// What that setup printed, returned again when loading is skipped
+    setup_stdout: Option<String>,
+    /// Modules the script imported from its directory, a load isn't skipped
+    /// if one of them was written to since
+    imported_files: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+// when a file was last written and how long it was
+type FileStamp = (std::time::SystemTime, u64);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
 }
 
 const DEFAULT_SCRIPT: &str = r"
@@ -164,34 +451,213 @@ def run(ctx):
 
 impl Default for PythonRunner {
     fn default() -> Self {
-        Self::new(DEFAULT_SCRIPT.to_owned(), Some("default.py".to_owned())).unwrap()
+        PythonRunnerBuilder::default()
+            .build(DEFAULT_SCRIPT, Some("default.py".to_owned()))
+            .unwrap()
+    }
+}
+
+/// Configures a runner before its first script is loaded, so module search
+/// paths are in place for the script's imports and `setup` only runs once.
+#[derive(Debug, Default, Clone)]
+pub struct PythonRunnerBuilder {
+    venv_path: Option<PathBuf>,
+    script_parent_directory: Option<PathBuf>,
+    base_seed: u64,
+    run_timeout: Option<Duration>,
+    async_timeout: Option<Duration>,
+    setup_timeout: Option<Duration>,
+    max_inputs: Option<usize>,
+    sequential_supported: bool,
+}
+
+impl PythonRunnerBuilder {
+    /// See `PythonRunner::set_venv_path`.
+    pub fn venv_path(mut self, path: PathBuf) -> Self {
+        self.venv_path = Some(path);
+        self
+    }
+
+    /// See `PythonRunner::set_script_parent_directory`.
+    pub fn script_parent_directory(mut self, path: PathBuf) -> Self {
+        self.script_parent_directory = Some(path);
+        self
+    }
+
+    /// See `PythonRunner::set_base_seed`.
+    pub fn base_seed(mut self, seed: u64) -> Self {
+        self.base_seed = seed;
+        self
+    }
+
+    /// See `PythonRunner::set_run_timeout`.
+    pub fn run_timeout(mut self, timeout: Duration) -> Self {
+        self.run_timeout = Some(timeout);
+        self
+    }
+
+    /// See `PythonRunner::set_async_timeout`.
+    pub fn async_timeout(mut self, timeout: Duration) -> Self {
+        self.async_timeout = Some(timeout);
+        self
+    }
+
+    /// See `PythonRunner::set_setup_timeout`.
+    pub fn setup_timeout(mut self, timeout: Duration) -> Self {
+        self.setup_timeout = Some(timeout);
+        self
+    }
+
+    /// See `PythonRunner::set_max_inputs`.
+    pub fn max_inputs(mut self, max: usize) -> Self {
+        self.max_inputs = Some(max);
+        self
+    }
+
+    /// See `PythonRunner::set_sequential_supported`, the first `setup` already sees it.
+    pub fn sequential_supported(mut self, supported: bool) -> Self {
+        self.sequential_supported = supported;
+        self
+    }
+
+    /// Loads `src` and runs its `setup`.
+    pub fn build<S: AsRef<str>>(
+        self,
+        src: S,
+        file_name: Option<String>,
+    ) -> Result<PythonRunner, GolobulError> {
+        PythonRunner::new(src, file_name, self)
+    }
+}
+
+/// A flag shared between the host and a render pass, scripts poll it
+/// with `ctx.is_cancelled()` to bail out of long running work.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Clears the flag so the token can be reused for the next pass.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Progress reported by the script through `ctx.set_progress`, shared with the host
+/// so it can be polled while a run is still in flight.
+#[derive(Debug, Clone)]
+pub struct Progress(Arc<AtomicU32>);
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU32::new(f32::NAN.to_bits())))
+    }
+}
+
+impl Progress {
+    /// None if the script hasn't reported any progress during this run.
+    pub fn get(&self) -> Option<f32> {
+        let fraction = f32::from_bits(self.0.load(Ordering::Relaxed));
+        (!fraction.is_nan()).then_some(fraction)
+    }
+
+    pub(crate) fn set(&self, fraction: f32) {
+        self.0.store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.set(f32::NAN);
     }
 }
 
 pub struct RenderPass<'a> {
     runner: &'a mut PythonRunner,
     inputs: IndexMap<String, InDesc<'a>>,
+    // neighbouring frames keyed by input name and frame offset
+    temporal_inputs: HashMap<(String, i32), InDesc<'a>>,
     output: OutDesc<'a>,
+    // named outputs besides the primary one
+    extra_outputs: IndexMap<String, OutDesc<'a>>,
+    cancellation: Option<CancellationToken>,
+    output_generation: Option<u64>,
 }
 
 impl<'a> RenderPass<'a> {
-    pub fn submit(self) -> Result<Option<String>, GolobulError> {
+    pub fn submit(self) -> Result<RenderReport, GolobulError> {
         let Self {
             runner,
             inputs,
+            temporal_inputs,
             output,
+            extra_outputs,
+            cancellation,
+            output_generation,
         } = self;
 
-        runner.run(inputs, output)
+        runner.run(
+            inputs,
+            temporal_inputs,
+            output,
+            extra_outputs,
+            cancellation,
+            output_generation,
+        )
+    }
+
+    /// Provides the buffer for an output the script registered with
+    /// `ctx.register_output`, see `PythonRunner::outputs`. Outputs that
+    /// aren't loaded are still written by the script, into scratch space.
+    pub fn load_output(&mut self, output: OutDesc<'a>, name: &str) {
+        self.extra_outputs.insert(name.to_owned(), output);
     }
 
     pub fn load_input(&mut self, input: InDesc<'a>, name: &str) {
         self.inputs.insert(name.to_owned(), input);
     }
+
+    /// Loads the frame `frame_offset` frames away from the current one for an image input,
+    /// scripts read it with `ctx.get_input_at`. Offsets that are never loaded, e.g. past
+    /// the ends of a clip, show up as `None`.
+    pub fn load_input_at(&mut self, input: InDesc<'a>, name: &str, frame_offset: i32) {
+        self.temporal_inputs
+            .insert((name.to_owned(), frame_offset), input);
+    }
+
+    /// If the token is cancelled while the script runs `submit` returns
+    /// `GolobulError::Cancelled` and the output is zeroed.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    /// Promises the output buffers stay allocated at the same addresses for as long
+    /// as passes carry this generation, so their numpy views are kept between runs
+    /// instead of rebuilt every frame. Change it whenever an output is reallocated.
+    pub fn set_output_generation(&mut self, generation: u64) {
+        self.output_generation = Some(generation);
+    }
 }
 
 impl PythonRunner {
-    fn new(src: String, file_name: Option<String>) -> Result<Self, GolobulError> {
+    pub fn builder() -> PythonRunnerBuilder {
+        PythonRunnerBuilder::default()
+    }
+
+    fn new<S: AsRef<str>>(
+        src: S,
+        file_name: Option<String>,
+        config: PythonRunnerBuilder,
+    ) -> Result<Self, GolobulError> {
         pyo3::prepare_freethreaded_python();
 
         let event_loop = event_loop::get_event_loop();
@@ -199,18 +665,11 @@ impl PythonRunner {
         let helper_module: Py<PyModule> = Python::with_gil(|py| {
             #[cfg(target_os = "macos")]
             if let Ok(venv) = std::env::var("VIRTUAL_ENV") {
-                let version_info = py.version_info();
                 let sys = py.import_bound("sys").unwrap();
                 let sys_path = sys.getattr("path").unwrap();
 
                 sys_path
-                    .call_method1(
-                        "append",
-                        (format!(
-                            "{}/lib/python{}.{}/site-packages",
-                            venv, version_info.major, version_info.minor
-                        ),),
-                    )
+                    .call_method1("append", (site_packages_in(py, Path::new(&venv)),))
                     .unwrap();
             }
 
@@ -236,21 +695,60 @@ impl PythonRunner {
             Ok::<_, GolobulError>(module.into())
         })?;
 
+        // before the module loads, its top level imports need them
+        for path in config.venv_path.iter().chain(&config.script_parent_directory) {
+            Self::add_path_to_sys(path)?;
+        }
+
         let (uuid, script_module) = load_module(src, file_name)?;
 
         let mut out = PythonRunner {
             script_module_uuid: uuid.into(),
             helper_module,
+            torch_helper: Arc::default(),
+            has_cuda: Arc::default(),
             event_loop,
+            background_tasks: Python::with_gil(|py| PySet::empty_bound(py).map(Bound::unbind))
+                .map_err(|_| GolobulError::BoundError)?,
             script_module,
             registry: IndexMap::new(),
+            outputs: vec![],
+            image_defaults: HashMap::new(),
+            default_image_cache: HashMap::new(),
+            output_views: HashMap::new(),
+            output_generation: None,
             time: 0.,
+            frame: 0,
+            fps: 0.,
+            base_seed: config.base_seed,
+            comp_size: None,
+            layer_name: None,
+            name: None,
             output_size: None,
-            pyenv_path: None,
-            script_parent_directory: None,
+            last_output_size: None,
+            pyenv_path: config.venv_path,
+            script_parent_directory: config.script_parent_directory,
             is_sequential: false,
+            image_filter_default: true,
             uses_automatic_color_correction: true,
+            straight_alpha: false,
+            matte_mode: context::MatteMode::Off,
             initialized: false,
+            run_timeout: config.run_timeout,
+            async_timeout: config.async_timeout,
+            setup_cancellation: None,
+            setup_timeout: config.setup_timeout,
+            progress: Progress::default(),
+            temporal_window: 0,
+            max_inputs: config.max_inputs,
+            state: Python::with_gil(|py| PyDict::new_bound(py).unbind()),
+            output_written: false,
+            primed: false,
+            statuses: IndexMap::new(),
+            sequential_supported: config.sequential_supported,
+            source_hash: None,
+            setup_stdout: None,
+            imported_files: Vec::new(),
         };
 
         out.setup()?;
@@ -270,22 +768,106 @@ impl PythonRunner {
                 modules.del_item(&module_name)?;
             }
 
+            // modules imported from next to the script would otherwise stay
+            // cached until the interpreter restarts
+            if self.script_parent_directory.is_some() {
+                for (name, _) in self.local_modules(py)? {
+                    modules.del_item(name)?;
+                }
+
+                py.import_bound("importlib")?.call_method0("invalidate_caches")?;
+            }
+
             Ok(())
         })
     }
 
-    /// Loads the given python script. returning stdout if it appeared
+    // modules in sys.modules that were loaded from the script's directory,
+    // by name and file. The script itself isn't one of them, and neither is
+    // anything installed in a venv or site-packages that happens to live there.
+    fn local_modules(&self, py: Python) -> PyResult<Vec<(String, PathBuf)>> {
+        let Some(dir) = self.script_parent_directory.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.clone());
+        let venv = self
+            .pyenv_path
+            .as_ref()
+            .map(|venv| std::fs::canonicalize(venv).unwrap_or_else(|_| venv.clone()));
+
+        let mods = py.import_bound("sys")?.getattr("modules")?;
+        let modules: &Bound<pyo3::types::PyDict> = mods.downcast()?;
+        let script = self.script_module.bind(py);
+
+        let mut local = Vec::new();
+        for (name, module) in modules.copy()?.iter() {
+            if module.is(script) {
+                continue;
+            }
+
+            // builtins and namespace packages have no file
+            let Ok(file) = module.getattr("__file__") else {
+                continue;
+            };
+
+            let (Ok(name), Ok(file)) = (name.extract::<String>(), file.extract::<PathBuf>())
+            else {
+                continue;
+            };
+
+            let file = std::fs::canonicalize(&file).unwrap_or(file);
+            if !file.starts_with(&dir) {
+                continue;
+            }
+
+            // C extensions can't be imported twice in one process
+            let is_source = matches!(
+                file.extension().and_then(|e| e.to_str()),
+                Some("py" | "pyc")
+            );
+
+            let is_installed = venv.as_ref().is_some_and(|venv| file.starts_with(venv))
+                || file.components().any(|c| {
+                    matches!(c.as_os_str().to_str(), Some("site-packages" | "dist-packages"))
+                });
+
+            if is_source && !is_installed {
+                local.push((name, file));
+            }
+        }
+
+        Ok(local)
+    }
+
+    /// Loads the given python script. returning stdout if it appeared.
+    /// If the same source is already loaded with the same settings, and the
+    /// modules it imported from its directory haven't been written to, nothing is
+    /// recompiled and setup's stdout from last time is returned, editors that
+    /// touch the file without changing it won't reset the script.
     pub fn load_script<S: AsRef<str>>(
         &mut self,
         src: S,
         file_name: Option<String>,
     ) -> Result<Option<String>, GolobulError> {
+        let hash = self.source_hash(src.as_ref(), file_name.as_deref());
+        let imports_unchanged = self
+            .imported_files
+            .iter()
+            .all(|(path, stamp)| file_stamp(path) == *stamp);
+
+        if self.initialized && self.source_hash == Some(hash) && imports_unchanged {
+            log::debug!("script is unchanged, keeping the loaded module");
+            return Ok(self.setup_stdout.clone());
+        }
+
+        self.source_hash = None;
+
         if let Some(pyenv_path) = self.pyenv_path.as_ref() {
-            self.add_path_to_sys(&pyenv_path.clone())?;
+            Self::add_path_to_sys(pyenv_path)?;
         }
 
         if let Some(script_dir) = self.script_parent_directory.as_ref() {
-            self.add_path_to_sys(&script_dir.clone())?;
+            Self::add_path_to_sys(script_dir)?;
         }
 
         self.delete_module()
@@ -296,14 +878,53 @@ impl PythonRunner {
         self.script_module_uuid = uuid.into();
         self.script_module = new_mod;
         self.initialized = false;
+        // the files may have been edited along with the script
+        self.default_image_cache.clear();
+        self.state = Python::with_gil(|py| PyDict::new_bound(py).unbind());
+        self.primed = false;
+        // a size the last script asked for isn't this one's business
+        self.output_size = None;
+
+        let out = self.setup()?;
+        self.source_hash = Some(hash);
+        self.setup_stdout = out.clone();
+        self.imported_files = Python::with_gil(|py| self.local_modules(py))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, path)| {
+                let stamp = file_stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+
+        Ok(out)
+    }
+
+    /// Like `load_script`, but always recompiles and reruns setup.
+    pub fn reload_script<S: AsRef<str>>(
+        &mut self,
+        src: S,
+        file_name: Option<String>,
+    ) -> Result<Option<String>, GolobulError> {
+        self.source_hash = None;
+        self.load_script(src, file_name)
+    }
 
-        self.setup()
+    // everything that changes what setup would do
+    fn source_hash(&self, src: &str, file_name: Option<&str>) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        src.hash(&mut hasher);
+        file_name.hash(&mut hasher);
+        self.pyenv_path.hash(&mut hasher);
+        self.script_parent_directory.hash(&mut hasher);
+        self.max_inputs.hash(&mut hasher);
+        hasher.finish()
     }
 
     /// This sets the venve path for *the next time*
     /// the runner loads a new script
     pub fn set_venv_path(&mut self, path: PathBuf) {
-        let _ = self.add_path_to_sys(&path);
+        let _ = Self::add_path_to_sys(&path);
         self.pyenv_path = Some(path);
     }
 
@@ -327,6 +948,43 @@ impl PythonRunner {
         Ok(())
     }
 
+    /// Installs a requirements file with pip, into the site packages path if one is set.
+    /// Returns pip's stdout.
+    pub fn ensure_requirements(&self, requirements: &Path) -> Result<Option<String>, GolobulError> {
+        let python = Python::with_gil(interpreter_path).ok_or(GolobulError::PipUnavailable)?;
+
+        let pip_available = std::process::Command::new(&python)
+            .args(["-m", "pip", "--version"])
+            .output()
+            .is_ok_and(|out| out.status.success());
+
+        if !pip_available {
+            return Err(GolobulError::PipUnavailable);
+        }
+
+        let mut pip = std::process::Command::new(&python);
+        pip.args(["-m", "pip", "install", "-r"]).arg(requirements);
+
+        if let Some(site_packages) = self.pyenv_path.as_ref() {
+            pip.arg("--target").arg(site_packages);
+        }
+
+        let out = pip.output().map_err(|_| GolobulError::PipUnavailable)?;
+
+        let stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+        let stdout = (!stdout.is_empty()).then_some(stdout);
+
+        if out.status.success() {
+            Ok(stdout)
+        } else {
+            Err(GolobulError::RuntimeError {
+                stderr: String::from_utf8_lossy(&out.stderr).into_owned(),
+                stdout,
+                exception_type: None,
+            })
+        }
+    }
+
     /// If  this is Some(size) it represent the exepcted dimension
     /// of outputs passed into the renderpass.
     /// If you do not respect this you will incur
@@ -336,51 +994,266 @@ impl PythonRunner {
         self.output_size.clone()
     }
 
+    // what `max_output_size` says in setup
+    pub(crate) fn setup_output_size(&self) -> Option<OutputSize> {
+        self.output_size
+            .clone()
+            .or_else(|| self.last_output_size.clone())
+    }
+
+    /// The display name the script gave itself in setup, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     /// Returns true if the script set "is_sequential"
     /// in setup.
     pub fn is_sequential(&self) -> bool {
         self.is_sequential
     }
 
+    /// What the script passed to `ctx.set_image_filter_default` in setup, true if
+    /// it didn't. Only means something when the script has an image input.
+    pub fn image_filter_default(&self) -> bool {
+        self.image_filter_default
+    }
+
+    /// False if the last run never called `ctx.output()` or returned a tensor,
+    /// the output is then whatever the host passed in. Cleared by failed runs.
+    pub fn output_written(&self) -> bool {
+        self.output_written
+    }
+
+    /// Names the script passed to `ctx.register_output`, load buffers
+    /// for the ones you want with `RenderPass::load_output`.
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+
+    /// True if the script called `ctx.set_alpha_mode("straight")` in setup.
+    pub fn straight_alpha(&self) -> bool {
+        self.straight_alpha
+    }
+
+    /// The number of frames on either side of the current one the script wants
+    /// through `ctx.get_input_at`, hosts only need to load these if it's nonzero.
+    pub fn temporal_window(&self) -> u32 {
+        self.temporal_window
+    }
+
+    /// Limits how long a single `run` may take before it is interrupted
+    /// and `GolobulError::Timeout` is returned, None disables the limit.
+    pub fn set_run_timeout(&mut self, timeout: Option<Duration>) {
+        self.run_timeout = timeout;
+    }
+
+    /// Limits how long the coroutine returned by an `async def run` may take,
+    /// on timeout it is cancelled and `GolobulError::Timeout` is returned.
+    /// None defers to the run timeout.
+    pub fn set_async_timeout(&mut self, timeout: Option<Duration>) {
+        self.async_timeout = timeout;
+    }
+
+    /// Lets the host give up on a `setup` that hangs, loading a script while
+    /// the token is cancelled fails with `GolobulError::Cancelled` and leaves
+    /// no script loaded. Setup is only interrupted between python statements,
+    /// a call into native code that never returns can't be stopped.
+    pub fn set_setup_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.setup_cancellation = token;
+    }
+
+    /// Limits how long `setup` may take before it is interrupted and loading fails
+    /// with `GolobulError::SetupTimeout`, None disables the limit. The same caveat
+    /// about native code as `set_setup_cancellation_token` applies.
+    pub fn set_setup_timeout(&mut self, timeout: Option<Duration>) {
+        self.setup_timeout = timeout;
+    }
+
+    /// The fraction of work the script reported completing during the current run.
+    pub fn progress(&self) -> Option<f32> {
+        self.progress.get()
+    }
+
+    /// A handle to the progress of this runner, useful for polling
+    /// while the runner itself is busy.
+    pub fn progress_handle(&self) -> Progress {
+        self.progress.clone()
+    }
+
     /// Attemp to set a variable, returns an error if missing or if htere is a type mismatch.
     pub fn try_set_var(&mut self, name: &str, value: Variant) -> Result<(), GolobulError> {
         if let Some(entry) = self.registry.get_mut(name) {
-            entry.adopt(&value)?;
-            Ok(())
+            entry.adopt(&value).map_err(|e| e.for_input(name))
         } else {
             Err(GolobulError::MissingVar(name.to_owned()))
         }
     }
 
-    // Runs then returns the contents of stdout if it exists
-    fn run(
+    /// Sets several variables at once, either all of them are applied or none are.
+    /// Unknown names give `GolobulError::MissingVar`, values that can't be adopted
+    /// give `GolobulError::InvalidVar` naming the input.
+    pub fn try_set_vars(&mut self, values: &[(String, Variant)]) -> Result<(), GolobulError> {
+        let mut adopted = Vec::with_capacity(values.len());
+
+        for (name, value) in values {
+            let Some(entry) = self.registry.get(name) else {
+                return Err(GolobulError::MissingVar(name.clone()));
+            };
+
+            let mut entry = entry.clone();
+            entry.adopt(value).map_err(|e| GolobulError::InvalidVar {
+                name: name.clone(),
+                source: Box::new(e.for_input(name)),
+            })?;
+            adopted.push((name, entry));
+        }
+
+        for (name, entry) in adopted {
+            self.registry.insert(name.clone(), entry);
+        }
+
+        Ok(())
+    }
+
+    // Runs then reports what the script wrote and printed
+    fn run<'o>(
         &mut self,
         inputs: IndexMap<String, InDesc>,
-        mut output: OutDesc,
-    ) -> Result<Option<String>, GolobulError> {
+        temporal_inputs: HashMap<(String, i32), InDesc>,
+        mut output: OutDesc<'o>,
+        mut extra_outputs: IndexMap<String, OutDesc<'o>>,
+        cancellation: Option<CancellationToken>,
+        output_generation: Option<u64>,
+    ) -> Result<RenderReport, GolobulError> {
         output.is_well_structured()?;
+        for extra in extra_outputs.values() {
+            extra.is_well_structured()?;
+        }
+        let all_inputs = inputs
+            .iter()
+            .chain(temporal_inputs.iter().map(|((name, _), desc)| (name, desc)));
+        for (name, desc) in all_inputs {
+            desc.is_well_structured().map_err(|e| e.for_input(name))?;
+        }
+        self.progress.clear();
+        self.output_written = false;
+        self.last_output_size = Some(OutputSize {
+            width: output.width,
+            height: output.height,
+        });
+
+        let defaults = self.default_images(&inputs, output.fmt)?;
+        let mut inputs = inputs;
+        for (name, image) in defaults.iter() {
+            let desc = InDesc {
+                fmt: image.fmt,
+                data: bytemuck::cast_slice(&image.data),
+                width: image.width,
+                height: image.height,
+                stride: None,
+            };
+            inputs.insert(name.clone(), desc);
+        }
+
+        // anything else may have been freed since, its address reused
+        let mut kept = match output_generation {
+            Some(generation) if self.output_generation == Some(generation) => {
+                std::mem::take(&mut self.output_views)
+            }
+            _ => HashMap::new(),
+        };
+        let mut views = HashMap::new();
 
         let result = Python::with_gil(|py| -> Result<MaybeFuture, GolobulError> {
             let out_catcher = StdOutCatcher::new(py)?;
+            WarningLogger::install(py);
 
             let inputs = inputs
                 .iter()
                 .map(|(k, v)| {
-                    let view = slice_view(v, &py);
-                    (k.clone(), (view.into_py(py), v.fmt))
+                    let view = cached_view(py, ViewKey::of_input(v), &mut views, || {
+                        slice_view(v, &py).into_py(py)
+                    });
+                    (k.clone(), (view, v.fmt))
                 })
                 .collect();
 
-            let target_image = mutable_slice_view(&mut output, &py);
+            let temporal_inputs = temporal_inputs
+                .iter()
+                .map(|(k, v)| {
+                    let view = cached_view(py, ViewKey::of_input(v), &mut views, || {
+                        slice_view(v, &py).into_py(py)
+                    });
+                    (k.clone(), (view, v.fmt))
+                })
+                .collect();
 
-            let ctx = context::PyContext::new(&output, inputs, target_image.into_py(py), self);
+            let key = ViewKey::of_output(&output);
+            let target_image = cached_view(py, key, &mut views, || {
+                reuse_view(&mut kept, key)
+                    .unwrap_or_else(|| mutable_slice_view(&mut output, &py).into_py(py))
+            });
+
+            let extra_targets = extra_outputs
+                .iter_mut()
+                .map(|(k, v)| {
+                    let key = ViewKey::of_output(v);
+                    let view = cached_view(py, key, &mut views, || {
+                        reuse_view(&mut kept, key)
+                            .unwrap_or_else(|| mutable_slice_view(v, &py).into_py(py))
+                    });
+                    (k.clone(), view)
+                })
+                .collect();
+
+            let mut ctx = context::PyContext::new(
+                &output,
+                inputs,
+                temporal_inputs,
+                target_image,
+                extra_targets,
+                cancellation.clone(),
+                self,
+            );
+
+            ctx.allocate_matte(py, output.fmt)
+                .map_err(|e| traceback(e, &out_catcher, &self.script_module, py))?;
 
             let ctx = Py::new(py, ctx).map_err(|_| GolobulError::BoundError)?;
 
-            let maybe_future = self
-                .script_module
-                .call_method1(py, "run", (&ctx,))
-                .map_err(|e| traceback(e, &out_catcher, py));
+            // runs before the watchdog, loading a model may well take longer than a frame
+            if !self.primed {
+                let module = self.script_module.bind(py);
+                if module.hasattr("prime").unwrap_or(false) {
+                    module
+                        .call_method1("prime", (&ctx,))
+                        .map_err(|e| traceback(e, &out_catcher, &self.script_module, py))?;
+                }
+                // a failed prime is tried again before the next run
+                self.primed = true;
+            }
+
+            let watchdog = self
+                .run_timeout
+                .map(|timeout| watchdog::Watchdog::arm(py, timeout))
+                .transpose()
+                .map_err(|_| GolobulError::BoundError)?;
+
+            let maybe_future = self.script_module.call_method1(py, "run", (&ctx,));
+
+            if watchdog.is_some_and(|w| w.disarm(py)) {
+                let _ = out_catcher.borrow_mut(py).output.take();
+                return Err(GolobulError::Timeout);
+            }
+
+            if cancellation.as_ref().is_some_and(|c| c.is_cancelled()) {
+                output.data.fill(0);
+                extra_outputs.values_mut().for_each(|o| o.data.fill(0));
+                return Err(GolobulError::Cancelled);
+            }
+
+            let maybe_future =
+                maybe_future.map_err(|e| traceback(e, &out_catcher, &self.script_module, py));
 
             // Throw recoverable error if descriptor was bad.
             if maybe_future.is_err()
@@ -417,62 +1290,194 @@ impl PythonRunner {
                 res.call_method1("add_done_callback", (py_chan,))
                     .map_err(|_| GolobulError::Asio)?;
 
-                Ok(MaybeFuture::Channel(rust_chan, ctx, out_catcher))
+                Ok(MaybeFuture::Channel(
+                    rust_chan,
+                    ctx,
+                    out_catcher,
+                    res.into_py(py),
+                ))
             } else {
-                let out = self.finalize(&ctx, &py, &mut output, &out_catcher)?;
+                let out = self.finalize(
+                    &ctx,
+                    &py,
+                    &mut output,
+                    &mut extra_outputs,
+                    &out_catcher,
+                    &maybe_future,
+                )?;
                 Ok(MaybeFuture::Done(out))
             }
         })?;
 
+        // inputs are the host's frames, only its outputs are vouched for
+        if output_generation.is_some() {
+            self.output_views = views
+                .into_iter()
+                .filter(|(key, _)| key.writeable)
+                .collect();
+        }
+        self.output_generation = output_generation;
+
+        let cancelled = || cancellation.as_ref().is_some_and(|c| c.is_cancelled());
+
         match result {
             MaybeFuture::Done(result) => Ok(result),
-            MaybeFuture::Channel(rx, ctx, out_catcher) => match rx.recv() {
-                Ok(Ok(_)) => {
-                    Python::with_gil(|py| self.finalize(&ctx, &py, &mut output, &out_catcher))
-                }
-                Ok(Err(e)) => Err(Python::with_gil(|py| {
-                    if ctx.borrow(py).output_size_requested().is_some_and(|size| {
-                        size.width > output.width || size.height > output.height
-                    }) {
-                        self.output_size = ctx.borrow(py).output_size_requested();
-
-                        let s = self.output_size.clone().unwrap();
-
-                        return GolobulError::OutputSizeTooLarge {
-                            req: (s.height, s.width),
-                            avail: (output.height, output.width),
-                        };
-                    } else {
-                        traceback(e, &out_catcher, py)
+            MaybeFuture::Channel(rx, ctx, out_catcher, future) => {
+                match recv_with_timeout(&rx, self.async_timeout.or(self.run_timeout)) {
+                    Ok(_) if cancelled() => {
+                        output.data.fill(0);
+                        extra_outputs.values_mut().for_each(|o| o.data.fill(0));
+                        Err(GolobulError::Cancelled)
                     }
-                })),
-                Err(_) => Err(GolobulError::Asio),
-            },
+                    Ok(Ok(returned)) => Python::with_gil(|py| {
+                        self.finalize(
+                            &ctx,
+                            &py,
+                            &mut output,
+                            &mut extra_outputs,
+                            &out_catcher,
+                            &returned,
+                        )
+                    }),
+                    Ok(Err(e)) => Err(Python::with_gil(|py| {
+                        if ctx.borrow(py).output_size_requested().is_some_and(|size| {
+                            size.width > output.width || size.height > output.height
+                        }) {
+                            self.output_size = ctx.borrow(py).output_size_requested();
+
+                            let s = self.output_size.clone().unwrap();
+
+                            return GolobulError::OutputSizeTooLarge {
+                                req: (s.height, s.width),
+                                avail: (output.height, output.width),
+                            };
+                        } else {
+                            traceback(e, &out_catcher, &self.script_module, py)
+                        }
+                    })),
+                    Err(RecvTimeoutError::Timeout) => {
+                        Python::with_gil(|py| {
+                            let _ = future.call_method0(py, "cancel");
+                        });
+                        Err(GolobulError::Timeout)
+                    }
+                    Err(RecvTimeoutError::Disconnected) => Err(GolobulError::Asio),
+                }
+            }
         }
     }
 
-    fn finalize(
+    // decodes the defaults of image inputs the host left empty, each file only
+    // once per bit depth
+    fn default_images(
+        &mut self,
+        inputs: &IndexMap<String, InDesc>,
+        output: ImageFormat,
+    ) -> Result<Vec<(String, Arc<DefaultImage>)>, GolobulError> {
+        let missing: Vec<(String, PathBuf)> = self
+            .image_defaults
+            .iter()
+            .filter(|(name, _)| !inputs.contains_key(*name))
+            .map(|(name, path)| (name.clone(), path.clone()))
+            .collect();
+
+        missing
+            .into_iter()
+            .map(|(name, path)| {
+                let key = (path.clone(), output);
+                if let Some(image) = self.default_image_cache.get(&key) {
+                    return Ok((name, image.clone()));
+                }
+
+                log::debug!("loading default image {path:?} for {name}");
+
+                let image = image::open(&path).map_err(|e| GolobulError::DefaultImage {
+                    path,
+                    reason: e.to_string(),
+                })?;
+
+                let image = Arc::new(DefaultImage::decode(image, output));
+
+                self.default_image_cache.insert(key, image.clone());
+                Ok((name, image))
+            })
+            .collect()
+    }
+
+    fn finalize<'o>(
         &mut self,
         ctx: &Py<context::PyContext>,
         py: &Python,
-        output: &mut OutDesc,
+        output: &mut OutDesc<'o>,
+        extra_outputs: &mut IndexMap<String, OutDesc<'o>>,
         out_catcher: &Py<StdOutCatcher>,
-    ) -> Result<Option<String>, GolobulError> {
+        returned: &Py<PyAny>,
+    ) -> Result<RenderReport, GolobulError> {
         let ctx_ref = ctx.borrow(*py);
 
-        if matches!(
-            output.fmt,
-            ImageFormat::Argb8 | ImageFormat::Argb32 | ImageFormat::Argb16ae
-        ) {
-            ctx_ref.swizzle_output_to_argb(*py).unwrap();
+        // This has to land before the swizzle below
+        ctx_ref
+            .write_returned_tensor(*py, returned, output)
+            .map_err(|e| traceback(e, out_catcher, &self.script_module, *py))?;
+
+        // before anything below touches the output on the script's behalf
+        self.output_written = ctx_ref.output_accessed();
+        self.statuses = ctx_ref.statuses();
+
+        // a matte the script never touched would wipe the output
+        if self.output_written {
+            ctx_ref
+                .broadcast_matte(*py, output.fmt)
+                .map_err(|e| traceback(e, out_catcher, &self.script_module, *py))?;
         }
 
-        if let Some(size) = ctx_ref.output_size_requested() {
-            self.output_size = Some(size);
+        let outputs = std::iter::once((None, output))
+            .chain(extra_outputs.iter_mut().map(|(k, v)| (Some(k.as_str()), v)));
+
+        for (name, output) in outputs {
+            if ctx_ref.straight_alpha() && output.fmt.is_premultiplied() {
+                ctx_ref
+                    .premultiply_output(*py, name, output.fmt)
+                    .map_err(|e| traceback(e, out_catcher, &self.script_module, *py))?;
+            }
+
+            if matches!(
+                output.fmt,
+                ImageFormat::Argb8 | ImageFormat::Argb32 | ImageFormat::Argb16ae
+            ) {
+                let row_size = output.width as usize * output.fmt.bytes_per_pixel();
+                let stride = output.stride.map_or(row_size, |s| s as usize);
+                let width = output.width as usize;
+
+                if !swizzle::rgba_to_argb(output.fmt, output.data, width, stride) {
+                    ctx_ref.swizzle_output_to_argb(*py, name).unwrap();
+                }
+            }
         }
 
-        let out = out_catcher.borrow_mut(*py).output.take();
-        Ok(out)
+        let requested = ctx_ref.output_size_requested();
+
+        // the script was handed a center crop of this size
+        let written_size = match &requested {
+            Some(size) => OutputSize {
+                width: size.width.min(output.width),
+                height: size.height.min(output.height),
+            },
+            None => OutputSize {
+                width: output.width,
+                height: output.height,
+            },
+        };
+
+        if requested.is_some() {
+            self.output_size = requested;
+        }
+
+        Ok(RenderReport {
+            written_size,
+            stdout: out_catcher.borrow_mut(*py).output.take(),
+            wrote_output: self.output_written,
+        })
     }
 
     // runs setup, returning stdout
@@ -499,27 +1504,59 @@ impl PythonRunner {
             sys.setattr("stdout", &out_catcher)
                 .map_err(|_| GolobulError::InvalidModule("Could not set stdout".to_owned()))?;
 
+            WarningLogger::install(py);
+
             let ctx = context::PyContext::new(
                 &OutDesc::empty(),
                 Default::default(),
+                Default::default(),
                 ().into_py(py),
+                Default::default(),
+                None,
                 self,
             );
 
             let ctx = Bound::new(py, ctx).map_err(|_| GolobulError::BoundError)?;
 
-            self.script_module
-                .call_method1(py, "setup", (&ctx,))
-                .map_err(|e| {
-                    let stdout = out_catcher.borrow_mut().output.take();
-                    GolobulError::RuntimeError {
-                        stderr: format!("{e}"),
-                        stdout,
-                    }
-                })?;
+            let cancellation = self.setup_cancellation.clone();
+            let watchdog = (self.setup_timeout.is_some() || cancellation.is_some())
+                .then(|| watchdog::Watchdog::arm_until(py, self.setup_timeout, cancellation))
+                .transpose()
+                .map_err(|_| GolobulError::BoundError)?;
+
+            let out = self.script_module.call_method1(py, "setup", (&ctx,));
+
+            if watchdog.is_some_and(|w| w.disarm(py)) {
+                let _ = out_catcher.borrow_mut().output.take();
+                let cancelled = self
+                    .setup_cancellation
+                    .as_ref()
+                    .is_some_and(|token| token.is_cancelled());
+                return Err(if cancelled {
+                    GolobulError::Cancelled
+                } else {
+                    GolobulError::SetupTimeout
+                });
+            }
+
+            out.map_err(|e| {
+                let stdout = out_catcher.borrow_mut().output.take();
+                GolobulError::RuntimeError {
+                    stderr: errors::format_exception(&e, &self.script_module, py),
+                    stdout,
+                    exception_type: errors::exception_type(&e, py),
+                }
+            })?;
 
             let mut registry = ctx.borrow().clone_registry();
 
+            if let Some(max) = self.max_inputs.filter(|max| registry.len() > *max) {
+                return Err(GolobulError::TooManyInputs {
+                    registered: registry.len(),
+                    max,
+                });
+            }
+
             for (k, v) in registry.iter_mut() {
                 if let Some(entry) = self.registry.get_mut(k) {
                     let _ = v.adopt(entry);
@@ -528,7 +1565,24 @@ impl PythonRunner {
 
             self.output_size = ctx.borrow().output_size_requested();
             self.is_sequential = ctx.borrow().is_sequential_mode();
+            self.image_filter_default = ctx.borrow().image_filter_default();
+            self.temporal_window = ctx.borrow().temporal_window();
+            self.name = ctx.borrow().name();
+            self.outputs = ctx.borrow().output_names();
+            // relative defaults are relative to the script
+            self.image_defaults = ctx
+                .borrow()
+                .image_defaults()
+                .into_iter()
+                .map(|(name, path)| match self.script_parent_directory.as_ref() {
+                    Some(dir) => (name, dir.join(path)),
+                    None => (name, path),
+                })
+                .collect();
             self.uses_automatic_color_correction = ctx.borrow().color_corrected();
+            self.straight_alpha = ctx.borrow().straight_alpha();
+            self.matte_mode = ctx.borrow().matte_mode();
+            self.statuses = ctx.borrow().statuses();
             self.registry = registry;
             self.initialized = true;
 
@@ -537,15 +1591,53 @@ impl PythonRunner {
         })
     }
 
+    /// For hosts with a fixed number of slots for inputs, loading a script
+    /// that registers more than `max` fails with `GolobulError::TooManyInputs`.
+    pub fn set_max_inputs(&mut self, max: Option<usize>) {
+        self.max_inputs = max;
+    }
+
+    /// Hosts that honor `ctx.set_sequential_mode` should say so, scripts
+    /// see it in `ctx.capabilities()`.
+    pub fn set_sequential_supported(&mut self, supported: bool) {
+        self.sequential_supported = supported;
+    }
+
     pub fn set_time(&mut self, time: f32) {
         self.time = time;
     }
 
+    pub fn set_frame(&mut self, frame: u32) {
+        self.frame = frame;
+    }
+
+    pub fn set_fps(&mut self, fps: f32) {
+        self.fps = fps;
+    }
+
+    /// Changes every `ctx.seed()` without changing how it varies per frame.
+    pub fn set_base_seed(&mut self, seed: u64) {
+        self.base_seed = seed;
+    }
+
+    /// What `ctx.comp_size()` reports, it falls back to the output size if never set.
+    pub fn set_comp_size(&mut self, width: u32, height: u32) {
+        self.comp_size = Some(OutputSize { width, height });
+    }
+
+    pub fn set_layer_name(&mut self, name: Option<String>) {
+        self.layer_name = name;
+    }
+
     pub fn create_render_pass<'a>(&'a mut self, output: OutDesc<'a>) -> RenderPass<'a> {
         RenderPass {
             runner: self,
             inputs: Default::default(),
+            temporal_inputs: Default::default(),
             output,
+            extra_outputs: Default::default(),
+            cancellation: None,
+            output_generation: None,
         }
     }
 
@@ -559,7 +1651,7 @@ impl PythonRunner {
         .map_err(|_| GolobulError::PathUpdateError)
     }
 
-    fn add_path_to_sys(&mut self, new_path: &Path) -> Result<(), GolobulError> {
+    fn add_path_to_sys(new_path: &Path) -> Result<(), GolobulError> {
         Python::with_gil(|py| -> PyResult<()> {
             let sys = py.import_bound("sys")?;
             let path = sys.getattr("path")?;
@@ -584,6 +1676,20 @@ impl PythonRunner {
         self.registry.iter()
     }
 
+    /// Every input the script registered, in registration order, without the
+    /// current values. Outputs are left out.
+    pub fn input_specs(&self) -> Vec<InputSpec> {
+        self.registry
+            .iter()
+            .filter_map(|(name, variant)| InputSpec::new(name, variant))
+            .collect()
+    }
+
+    /// What the script reported with `ctx.set_status`, in the order it was first set.
+    pub fn iter_statuses(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.statuses.iter()
+    }
+
     pub fn iter_inputs_mut(&mut self) -> impl Iterator<Item = (&String, &mut Variant)> {
         self.registry.iter_mut()
     }
@@ -642,18 +1748,26 @@ fn mutable_slice_view<'a>(out_desc: &mut OutDesc, py: &'a Python) -> pyo3::Bound
     } = out_desc;
 
     let bytes_per_pixel = fmt.bytes_per_pixel();
+    let channels = fmt.channels();
 
-    let mut dims = [*height as isize, *width as isize, 4];
+    let mut dims = [*height as isize, *width as isize, channels as isize];
 
     let mut stride = [
         stride.unwrap_or({ *width } * bytes_per_pixel as u32) as isize,
         bytes_per_pixel as isize,
-        bytes_per_pixel as isize / 4,
+        (bytes_per_pixel / channels) as isize,
     ];
 
+    // single channel formats drop the trailing channel axis
+    let nd = if channels == 1 { 2 } else { 3 };
+
     let ty = match fmt {
-        ImageFormat::Rgba8 | ImageFormat::Argb8 => npyffi::types::NPY_TYPES::NPY_UBYTE,
-        ImageFormat::Rgba16 | ImageFormat::Argb16ae => npyffi::types::NPY_TYPES::NPY_USHORT,
+        ImageFormat::Rgba8 | ImageFormat::Argb8 | ImageFormat::Gray8 | ImageFormat::Rgb8 => {
+            npyffi::types::NPY_TYPES::NPY_UBYTE
+        }
+        ImageFormat::Rgba16 | ImageFormat::Argb16ae | ImageFormat::Gray16 => {
+            npyffi::types::NPY_TYPES::NPY_USHORT
+        }
         ImageFormat::Argb32 | ImageFormat::Rgba32 => npyffi::types::NPY_TYPES::NPY_FLOAT,
     };
 
@@ -663,7 +1777,7 @@ fn mutable_slice_view<'a>(out_desc: &mut OutDesc, py: &'a Python) -> pyo3::Bound
         let pyarray_ptr = PY_ARRAY_API.PyArray_New(
             *py,
             PY_ARRAY_API.get_type_object(*py, npyffi::NpyTypes::PyArray_Type) as *mut _,
-            3,
+            nd,
             dims.as_mut_ptr(),
             ty as i32,
             stride.as_mut_ptr(),
@@ -688,18 +1802,26 @@ fn slice_view<'a>(in_desc: &InDesc, py: &'a Python) -> pyo3::Bound<'a, PyAny> {
     } = in_desc;
 
     let bytes_per_pixel = fmt.bytes_per_pixel();
+    let channels = fmt.channels();
 
-    let mut dims = [*height as isize, *width as isize, 4];
+    let mut dims = [*height as isize, *width as isize, channels as isize];
 
     let mut stride = [
         stride.unwrap_or(*width * bytes_per_pixel as u32) as isize,
         bytes_per_pixel as isize,
-        bytes_per_pixel as isize / 4,
+        (bytes_per_pixel / channels) as isize,
     ];
 
+    // single channel formats drop the trailing channel axis
+    let nd = if channels == 1 { 2 } else { 3 };
+
     let ty = match fmt {
-        ImageFormat::Rgba8 | ImageFormat::Argb8 => npyffi::types::NPY_TYPES::NPY_UBYTE,
-        ImageFormat::Rgba16 | ImageFormat::Argb16ae => npyffi::types::NPY_TYPES::NPY_USHORT,
+        ImageFormat::Rgba8 | ImageFormat::Argb8 | ImageFormat::Gray8 | ImageFormat::Rgb8 => {
+            npyffi::types::NPY_TYPES::NPY_UBYTE
+        }
+        ImageFormat::Rgba16 | ImageFormat::Argb16ae | ImageFormat::Gray16 => {
+            npyffi::types::NPY_TYPES::NPY_USHORT
+        }
         ImageFormat::Argb32 | ImageFormat::Rgba32 => npyffi::types::NPY_TYPES::NPY_FLOAT,
     };
 
@@ -709,7 +1831,7 @@ fn slice_view<'a>(in_desc: &InDesc, py: &'a Python) -> pyo3::Bound<'a, PyAny> {
         let py_array_slice = PY_ARRAY_API.PyArray_New(
             *py,
             PY_ARRAY_API.get_type_object(*py, npyffi::NpyTypes::PyArray_Type) as *mut _,
-            3,
+            nd,
             dims.as_mut_ptr(),
             ty as i32,
             stride.as_mut_ptr(),
@@ -723,6 +1845,60 @@ fn slice_view<'a>(in_desc: &InDesc, py: &'a Python) -> pyo3::Bound<'a, PyAny> {
     }
 }
 
+/// The site packages directory inside a virtual environment, for the
+/// version of python we are embedding.
+pub fn venv_site_packages(venv: &Path) -> PathBuf {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| site_packages_in(py, venv))
+}
+
+fn site_packages_in(py: Python, venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Lib").join("site-packages")
+    } else {
+        let version = py.version_info();
+        venv.join("lib")
+            .join(format!("python{}.{}", version.major, version.minor))
+            .join("site-packages")
+    }
+}
+
+/// Finds a python executable matching the embedded interpreter. When embedded
+/// `sys.executable` is usually the host application, so fall back to the install prefix.
+fn interpreter_path(py: Python) -> Option<PathBuf> {
+    let sys = py.import_bound("sys").ok()?;
+
+    let executable: PathBuf = sys.getattr("executable").ok()?.extract().ok()?;
+    let is_python = executable
+        .file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().starts_with("python"));
+
+    if is_python && executable.exists() {
+        return Some(executable);
+    }
+
+    let prefix: PathBuf = sys.getattr("base_prefix").ok()?.extract().ok()?;
+
+    let candidates = if cfg!(windows) {
+        vec![prefix.join("python.exe")]
+    } else {
+        vec![prefix.join("bin/python3"), prefix.join("bin/python")]
+    };
+
+    candidates.into_iter().find(|path| path.exists())
+}
+
+/// Waits on a coroutine result, giving up after `timeout` if one is set.
+fn recv_with_timeout<T>(
+    rx: &Receiver<T>,
+    timeout: Option<Duration>,
+) -> Result<T, RecvTimeoutError> {
+    match timeout {
+        Some(timeout) => rx.recv_timeout(timeout),
+        None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    }
+}
+
 fn is_awaitable(py: Python, obj: &Py<PyAny>) -> PyResult<bool> {
     let inspect = py.import_bound("inspect")?;
     let is_awaitable = inspect.getattr("isawaitable")?;
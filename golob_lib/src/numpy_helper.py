@@ -1,14 +1,75 @@
+import numpy as np
+
+
 def center_crop(image, crop_height, crop_width):
-    h, w, _ = image.shape
-    start_y = (h - crop_height) // 2
-    start_x = (w - crop_width) // 2
+    # basic slicing keeps the row stride of the parent view, so padded
+    # host buffers crop correctly. Never reshape or ravel here.
+    h, w = image.shape[:2]
+    start_y = max((h - crop_height) // 2, 0)
+    start_x = max((w - crop_width) // 2, 0)
 
-    return image[start_y : start_y + crop_height, start_x : start_x + crop_width, :]
+    return image[start_y : start_y + crop_height, start_x : start_x + crop_width]
 
 
+# The Argb formats only differ from their Rgba twins in channel order, so these
+# two are exact inverses for every dtype. Argb16ae is no exception, its values
+# run 0..=32768 instead of 0..=65535 and are passed through unscaled.
 def rgba_view(arr):
     return arr[..., [1, 2, 3, 0]].view()
 
 
 def swizzle_in_place(arr):
     arr[:] = arr[..., [3, 0, 1, 2]]
+
+
+def _color_channels(alpha):
+    return [c for c in range(4) if c != alpha]
+
+
+def _to_dtype(values, dtype, max_value):
+    if np.issubdtype(dtype, np.integer):
+        return np.clip(np.rint(values), 0, max_value).astype(dtype)
+    return values.astype(dtype)
+
+
+def convert(arr, max_value, dtype, target_max):
+    dtype = np.dtype(dtype)
+    if arr.dtype == dtype and max_value == target_max:
+        return arr
+    scaled = arr.astype(np.float32) * (target_max / max_value)
+    return _to_dtype(scaled, dtype, target_max)
+
+
+def unpremultiply(arr, alpha, max_value):
+    # a copy, the input views are read only
+    out = arr.astype(np.float32)
+    a = out[..., alpha : alpha + 1]
+    color = _color_channels(alpha)
+    # fully transparent pixels are black either way
+    out[..., color] *= max_value / np.where(a > 0, a, max_value)
+    return _to_dtype(out, arr.dtype, max_value)
+
+
+def premultiply_in_place(arr, alpha, max_value):
+    f = arr.astype(np.float32)
+    color = _color_channels(alpha)
+    f[..., color] *= f[..., alpha : alpha + 1] / max_value
+    arr[..., color] = _to_dtype(f[..., color], arr.dtype, max_value)
+
+
+def matte_like(target):
+    return np.zeros(target.shape[:2], target.dtype)
+
+
+def broadcast_matte(target, matte, into_alpha, premultiplied, max_value):
+    # target is still in the script's RGBA order here
+    m = matte[..., None]
+    if target.shape[-1] != 4:
+        target[...] = m
+    elif into_alpha:
+        target[..., 3:4] = m
+        # premultiplied white under the matte is the matte itself
+        target[..., :3] = m if premultiplied else max_value
+    else:
+        target[..., :3] = m
+        target[..., 3] = max_value
@@ -0,0 +1,100 @@
+use wide::{u32x8, u64x4};
+
+use crate::ImageFormat;
+
+/// Reorders RGBA pixels to ARGB in place, one row at a time so padding
+/// is left untouched. Returns false if the buffer couldn't be handled here,
+/// in which case the caller should fall back to numpy.
+pub(crate) fn rgba_to_argb(
+    fmt: ImageFormat,
+    data: &mut [u8],
+    width: usize,
+    stride: usize,
+) -> bool {
+    // the rotations below assume the first channel is the low bits
+    if cfg!(target_endian = "big") {
+        return false;
+    }
+
+    let row_size = width * fmt.bytes_per_pixel();
+
+    let rotate: fn(&mut [u8]) -> bool = match fmt {
+        ImageFormat::Argb8 => rotate_u8_pixels,
+        ImageFormat::Argb16ae => rotate_u16_pixels,
+        ImageFormat::Argb32 => rotate_f32_pixels,
+        _ => return false,
+    };
+
+    // check everything up front, bailing halfway through would leave
+    // the numpy fallback swizzling rows that are already done.
+    let align = fmt.bytes_per_pixel().min(8);
+    if stride == 0
+        || stride < row_size
+        || stride % align != 0
+        || data.len() % stride != 0
+        || data.as_ptr() as usize % align != 0
+    {
+        return false;
+    }
+
+    for row in data.chunks_exact_mut(stride) {
+        if !rotate(&mut row[..row_size]) {
+            return false;
+        }
+    }
+
+    true
+}
+
+// one pixel per u32 lane, alpha is the top byte
+fn rotate_u8_pixels(row: &mut [u8]) -> bool {
+    let Ok(pixels) = bytemuck::try_cast_slice_mut::<u8, u32>(row) else {
+        return false;
+    };
+
+    let mut chunks = pixels.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let lanes = u32x8::new(chunk.try_into().unwrap());
+        let rotated = (lanes << 8) | (lanes >> 24);
+        chunk.copy_from_slice(&rotated.to_array());
+    }
+
+    for px in chunks.into_remainder() {
+        *px = px.rotate_left(8);
+    }
+
+    true
+}
+
+// one pixel per u64 lane, alpha is the top 16 bits
+fn rotate_u16_pixels(row: &mut [u8]) -> bool {
+    let Ok(pixels) = bytemuck::try_cast_slice_mut::<u8, u64>(row) else {
+        return false;
+    };
+
+    let mut chunks = pixels.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        let lanes = u64x4::new(chunk.try_into().unwrap());
+        let rotated = (lanes << 16) | (lanes >> 48);
+        chunk.copy_from_slice(&rotated.to_array());
+    }
+
+    for px in chunks.into_remainder() {
+        *px = px.rotate_left(16);
+    }
+
+    true
+}
+
+// a pixel is a whole 128 bit register, so this is just a lane rotation
+fn rotate_f32_pixels(row: &mut [u8]) -> bool {
+    let Ok(pixels) = bytemuck::try_cast_slice_mut::<u8, [u32; 4]>(row) else {
+        return false;
+    };
+
+    for px in pixels {
+        px.rotate_right(1);
+    }
+
+    true
+}
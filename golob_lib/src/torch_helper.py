@@ -0,0 +1,34 @@
+import numpy as np
+import torch
+
+_TORCH_DTYPES = {
+    np.dtype("uint8"): torch.uint8,
+    np.dtype("uint16"): getattr(torch, "uint16", None),
+    np.dtype("float32"): torch.float32,
+}
+
+
+def has_cuda():
+    return torch.cuda.is_available()
+
+
+def is_tensor(obj):
+    return isinstance(obj, torch.Tensor)
+
+
+# (pointer, byte length) if the tensor can be copied straight into target.
+def contiguous_bytes(tensor, target):
+    if tensor.device.type != "cpu" or not tensor.is_contiguous():
+        return None
+
+    if tuple(tensor.shape) != target.shape:
+        return None
+
+    if _TORCH_DTYPES.get(target.dtype) != tensor.dtype:
+        return None
+
+    return (tensor.data_ptr(), tensor.numel() * tensor.element_size())
+
+
+def copy_tensor(target, tensor):
+    target[...] = tensor.detach().cpu().numpy()
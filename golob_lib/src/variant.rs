@@ -1,51 +1,111 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 use crate::GolobulError;
 
 type Color = [f32; 4];
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Variant {
-    Image(DiscreteCfg<Image>),
+    Image(DiscreteCfg<Image>, ImageSettings),
     Bool(DiscreteCfg<bool>),
     TaggedInt(TaggedInt),
     Color(DiscreteCfg<Color>),
     Int(Cfg<i32>),
-    Float(Cfg<f32>),
+    Float(Cfg<f32>, Scale),
     Vector2(Cfg<[f32; 2]>),
+    // degrees, may be outside 0..360 when keyframed
+    Angle(DiscreteCfg<f32>),
 }
 
 impl Variant {
+    /// What the script said this input is for, if anything.
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Variant::Image(c, _) => c.description.as_deref(),
+            Variant::Bool(c) => c.description.as_deref(),
+            Variant::TaggedInt(c) => c.description.as_deref(),
+            Variant::Color(c) => c.description.as_deref(),
+            Variant::Int(c) => c.description.as_deref(),
+            Variant::Float(c, _) => c.description.as_deref(),
+            Variant::Vector2(c) => c.description.as_deref(),
+            Variant::Angle(c) => c.description.as_deref(),
+        }
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        let slot = match self {
+            Variant::Image(c, _) => &mut c.description,
+            Variant::Bool(c) => &mut c.description,
+            Variant::TaggedInt(c) => &mut c.description,
+            Variant::Color(c) => &mut c.description,
+            Variant::Int(c) => &mut c.description,
+            Variant::Float(c, _) => &mut c.description,
+            Variant::Vector2(c) => &mut c.description,
+            Variant::Angle(c) => &mut c.description,
+        };
+
+        *slot = description;
+    }
+
+    /// The kind of input, named after the `register_*` call that makes it.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Variant::Image(..) => "image",
+            Variant::Bool(_) => "bool",
+            Variant::TaggedInt(_) => "enum",
+            Variant::Color(_) => "color",
+            Variant::Int(_) => "int",
+            Variant::Float(..) => "float",
+            Variant::Vector2(_) => "point",
+            Variant::Angle(_) => "angle",
+        }
+    }
+
+    /// Takes the current value of `other`. A `TypeMismatch` from here doesn't
+    /// know the input's name, `PythonRunner::try_set_var` fills it in.
     pub fn adopt(&mut self, other: &Self) -> Result<(), GolobulError> {
+        let (expected, found) = (self.type_name(), other.type_name());
+
         match (self, other) {
-            (Variant::Image(_), Variant::Image(_)) => {}
+            (Variant::Image(..), Variant::Image(..)) => {}
             (Variant::TaggedInt(self_i), Variant::TaggedInt(i)) => {
-                if let Some(k) = i.tags.iter().find(|(_, v)| **v == i.value) {
-                    if self_i.tags.contains_key(k.0) {
-                        self_i.value = i.value;
-                    }
+                // the incoming tags don't matter, only ours
+                if !self_i.tags.values().any(|v| *v == i.value) {
+                    return Err(GolobulError::InvalidValue(i.value));
                 }
+                self_i.value = i.value;
             }
             (Variant::Color(self_c), Variant::Color(other)) => {
                 self_c.current = other.current;
             }
             (Variant::Int(i_me), Variant::Int(i_other)) => {
-                if i_other.current < i_me.max && i_other.current > i_me.min {
-                    i_me.current = i_other.current;
+                if i_other.current <= i_me.max && i_other.current >= i_me.min {
+                    i_me.current = i_me.snap(i_other.current);
                 }
             }
-            (Variant::Float(i_me), Variant::Float(i_other)) => {
-                if i_other.current < i_me.max && i_other.current > i_me.min {
-                    i_me.current = i_other.current;
+            (Variant::Float(i_me, _), Variant::Float(i_other, _)) => {
+                if i_other.current <= i_me.max && i_other.current >= i_me.min {
+                    i_me.current = i_me.snap(i_other.current);
                 }
             }
+            (Variant::Angle(self_a), Variant::Angle(other)) => {
+                self_a.current = other.current;
+            }
             (Variant::Vector2(i_me), Variant::Vector2(i_other)) => {
-                if i_other.current < i_me.max && i_other.current > i_me.min {
-                    i_me.current = i_other.current;
+                // array ordering is lexicographic, so bound each axis on its own
+                for axis in 0..2 {
+                    i_me.current[axis] = i_other.current[axis]
+                        .max(i_me.min[axis])
+                        .min(i_me.max[axis]);
                 }
             }
             _ => {
-                return Err(GolobulError::TypeMismatch);
+                return Err(GolobulError::TypeMismatch {
+                    name: String::new(),
+                    expected: expected.to_owned(),
+                    found: found.to_owned(),
+                });
             }
         }
 
@@ -55,33 +115,119 @@ impl Variant {
 
 // Continuous bounded values
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cfg<T: Clone + PartialEq> {
     pub default: T,
     pub current: T,
     pub min: T,
     pub max: T,
+    // values snap to min plus multiples of this, continuous if None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub step: Option<T>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
 }
 
 // Discrete values
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiscreteCfg<T: Clone + PartialEq> {
     pub current: T,
     pub default: T,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
+}
+
+/// The numpy dtypes image inputs can be converted to. Values are rescaled
+/// so each type's full range covers the same 0 to 1 intensity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dtype {
+    Uint8,
+    Uint16,
+    Float32,
+}
+
+impl Dtype {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "uint8" => Some(Dtype::Uint8),
+            "uint16" => Some(Dtype::Uint16),
+            "float32" => Some(Dtype::Float32),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dtype::Uint8 => "uint8",
+            Dtype::Uint16 => "uint16",
+            Dtype::Float32 => "float32",
+        }
+    }
+
+    /// What full intensity becomes, 16 bit uses the whole range even for AE's 15 bit.
+    pub fn max_value(&self) -> f32 {
+        match self {
+            Dtype::Uint8 => 255.0,
+            Dtype::Uint16 => 65535.0,
+            Dtype::Float32 => 1.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TaggedInt {
     pub value: i32,
     pub default: i32,
-    pub tags: HashMap<String, i32>,
+    // in registration order, which is the order hosts list them in
+    pub tags: IndexMap<String, i32>,
+    // scripts get the selected label instead of its value
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub returns_label: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Image {
     Input,
     Output,
 }
 
+/// How hosts should lay out a float's slider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Scale {
+    #[default]
+    Linear,
+    Logarithmic,
+}
+
+impl Scale {
+    /// From `register_float(scale=...)`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Scale::Linear),
+            "log" => Some(Scale::Logarithmic),
+            _ => None,
+        }
+    }
+}
+
+/// What only image inputs can be told in `register_image_input` and friends.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ImageSettings {
+    /// Overrides `set_automatic_color_correction` when set.
+    pub color_correction: Option<bool>,
+    /// What `request_input_format` asked the input converted to.
+    pub dtype: Option<Dtype>,
+}
+
 impl<T: Clone + PartialEq> Cfg<T> {
     pub fn new(default: T, min: T, max: T) -> Self {
         Self {
@@ -89,25 +235,196 @@ impl<T: Clone + PartialEq> Cfg<T> {
             default,
             min,
             max,
+            step: None,
+            description: None,
         }
     }
 }
 
+impl Cfg<i32> {
+    /// Rounds to the nearest `min + n * step` that is still in bounds.
+    pub fn snap(&self, value: i32) -> i32 {
+        let Some(step) = self.step.filter(|s| *s > 0) else {
+            return value;
+        };
+
+        // in i64, the distance from min doesn't always fit in an i32
+        let (min, max, step) = (self.min as i64, self.max as i64, step as i64);
+        let value = (value as i64).max(min).min(max);
+
+        let steps = ((value - min) as f64 / step as f64).round() as i64;
+        let mut snapped = steps
+            .checked_mul(step)
+            .and_then(|offset| offset.checked_add(min))
+            .unwrap_or(value);
+
+        // rounding up can step past max
+        if snapped > max {
+            snapped -= step;
+        }
+
+        snapped.max(min).min(max) as i32
+    }
+}
+
+impl Cfg<f32> {
+    /// Rounds to the nearest `min + n * step` that is still in bounds.
+    pub fn snap(&self, value: f32) -> f32 {
+        let Some(step) = self.step.filter(|s| *s > 0.0) else {
+            return value;
+        };
+
+        let value = value.max(self.min).min(self.max);
+        let mut snapped = self.min + ((value - self.min) / step).round() * step;
+
+        // unbounded ranges overflow, those are left as they are
+        if !snapped.is_finite() {
+            snapped = value;
+        }
+
+        // rounding up can step past max
+        if snapped > self.max {
+            snapped -= step;
+        }
+
+        snapped.max(self.min).min(self.max)
+    }
+}
+
 impl<T: Clone + PartialEq> DiscreteCfg<T> {
     pub fn new(current: T) -> Self {
         Self {
             default: current.clone(),
             current,
+            description: None,
         }
     }
 }
 
 impl TaggedInt {
-    pub fn new(default: i32, tags: HashMap<String, i32>) -> Self {
+    pub fn new(default: i32, tags: IndexMap<String, i32>) -> Self {
         Self {
             value: default,
             default,
             tags,
+            returns_label: false,
+            description: None,
         }
     }
+
+    /// Where the current value sits among the tags, hosts with
+    /// positional dropdowns should use this rather than the value.
+    pub fn position(&self) -> Option<usize> {
+        self.tags.values().position(|v| *v == self.value)
+    }
+
+    pub fn default_position(&self) -> Option<usize> {
+        self.tags.values().position(|v| *v == self.default)
+    }
+
+    /// Selects the tag at `position`, out of range positions are ignored.
+    pub fn select(&mut self, position: usize) {
+        if let Some((_, v)) = self.tags.get_index(position) {
+            self.value = *v;
+        }
+    }
+
+    /// The label of the current value, if it has one.
+    pub fn label(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(_, v)| **v == self.value)
+            .map(|(k, _)| k.as_str())
+    }
+}
+
+/// A plain description of one input, for hosts that build their own
+/// parameter panels or docs without touching the registry.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: InputKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputKind {
+    Image,
+    Bool {
+        default: bool,
+    },
+    Enum {
+        default: i32,
+        // label and value, in the order hosts should list them
+        options: Vec<(String, i32)>,
+    },
+    Color {
+        default: Color,
+    },
+    Int {
+        default: i32,
+        min: i32,
+        max: i32,
+        step: Option<i32>,
+    },
+    Float {
+        default: f32,
+        min: f32,
+        max: f32,
+        step: Option<f32>,
+        logarithmic: bool,
+    },
+    Vector2 {
+        default: [f32; 2],
+        min: [f32; 2],
+        max: [f32; 2],
+    },
+    Angle {
+        default: f32,
+    },
+}
+
+impl InputSpec {
+    /// None for outputs, they aren't something a host sets.
+    pub fn new(name: &str, variant: &Variant) -> Option<Self> {
+        let kind = match variant {
+            Variant::Image(c, _) => match c.current {
+                Image::Input => InputKind::Image,
+                Image::Output => return None,
+            },
+            Variant::Bool(c) => InputKind::Bool { default: c.default },
+            Variant::TaggedInt(t) => InputKind::Enum {
+                default: t.default,
+                options: t.tags.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+            },
+            Variant::Color(c) => InputKind::Color { default: c.default },
+            Variant::Int(c) => InputKind::Int {
+                default: c.default,
+                min: c.min,
+                max: c.max,
+                step: c.step,
+            },
+            Variant::Float(c, scale) => InputKind::Float {
+                default: c.default,
+                min: c.min,
+                max: c.max,
+                step: c.step,
+                logarithmic: *scale == Scale::Logarithmic,
+            },
+            Variant::Vector2(c) => InputKind::Vector2 {
+                default: c.default,
+                min: c.min,
+                max: c.max,
+            },
+            Variant::Angle(c) => InputKind::Angle { default: c.default },
+        };
+
+        Some(Self {
+            name: name.to_owned(),
+            description: variant.description().map(str::to_owned),
+            kind,
+        })
+    }
 }
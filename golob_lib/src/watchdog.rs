@@ -0,0 +1,106 @@
+use pyo3::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::CancellationToken;
+
+// how often a cancellation token is checked
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// Raises a `TimeoutError` inside the python thread that armed it
+/// if it isn't disarmed before the deadline, or before its token is cancelled.
+pub(crate) struct Watchdog {
+    thread_id: u64,
+    disarm: Sender<()>,
+    fired: Arc<AtomicBool>,
+    done: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    /// Must be called with the GIL held on the thread that runs the script.
+    pub fn arm(py: Python, timeout: Duration) -> PyResult<Self> {
+        Self::arm_until(py, Some(timeout), None)
+    }
+
+    /// Like `arm`, but also fires once `cancellation` is cancelled.
+    /// With neither it never fires.
+    pub fn arm_until(
+        py: Python,
+        timeout: Option<Duration>,
+        cancellation: Option<CancellationToken>,
+    ) -> PyResult<Self> {
+        let thread_id: u64 = py
+            .import_bound("threading")?
+            .call_method0("get_ident")?
+            .extract()?;
+
+        let (disarm, rx) = channel::<()>();
+        let fired = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let (fired_th, done_th) = (fired.clone(), done.clone());
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        thread::spawn(move || {
+            loop {
+                let left = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                let poll = cancellation.as_ref().map(|_| CANCEL_POLL);
+                let wait = left.into_iter().chain(poll).min().unwrap_or(Duration::MAX);
+
+                // anything but a timeout means we were disarmed
+                let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(wait) else {
+                    return;
+                };
+
+                let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+                let cancelled = cancellation.as_ref().is_some_and(|c| c.is_cancelled());
+
+                if expired || cancelled {
+                    break;
+                }
+            }
+
+            // `done` is only written with the GIL held, so checking it
+            // under the GIL means we can't interrupt a finished run.
+            Python::with_gil(|_py| {
+                if !done_th.load(Ordering::SeqCst) {
+                    fired_th.store(true, Ordering::SeqCst);
+                    unsafe {
+                        pyo3::ffi::PyThreadState_SetAsyncExc(
+                            thread_id as _,
+                            pyo3::ffi::PyExc_TimeoutError,
+                        );
+                    }
+                }
+            });
+        });
+
+        Ok(Self {
+            thread_id,
+            disarm,
+            fired,
+            done,
+        })
+    }
+
+    /// Stops the watchdog, returning true if it interrupted the script.
+    /// Must be called with the GIL held.
+    pub fn disarm(self, _py: Python) -> bool {
+        self.done.store(true, Ordering::SeqCst);
+        let _ = self.disarm.send(());
+
+        let fired = self.fired.load(Ordering::SeqCst);
+
+        if fired {
+            // the script may have returned before the exception was raised,
+            // make sure it doesn't fire in unrelated code later.
+            unsafe {
+                pyo3::ffi::PyThreadState_SetAsyncExc(self.thread_id as _, std::ptr::null_mut());
+            }
+        }
+
+        fired
+    }
+}
@@ -63,6 +63,202 @@ fn q_ident() {
     assert_eq!(input, output);
 }
 
+#[test]
+fn gray_ident() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(IDENT, None).unwrap();
+
+    let input: Vec<u8> = (0..10 * 10).map(|i| i as u8).collect();
+
+    let mut output = vec![0u8; 10 * 10];
+
+    let i = InDesc {
+        fmt: ImageFormat::Gray8,
+        data: &input,
+        width: 10,
+        height: 10,
+        stride: None,
+    };
+
+    let o = OutDesc {
+        fmt: ImageFormat::Gray8,
+        data: &mut output,
+        width: 10,
+        height: 10,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(o);
+    pass.load_input(i, "input");
+
+    pass.submit().unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn rgb_ident_padded() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(IDENT, None).unwrap();
+
+    // 5 pixels of 3 bytes each, padded out to a 16 byte stride
+    let (width, height, stride) = (5, 4, 16);
+
+    let input: Vec<u8> = (0..stride * height)
+        .map(|i| if i % stride < width * 3 { i as u8 } else { 0 })
+        .collect();
+
+    let mut output = vec![0u8; input.len()];
+
+    let i = InDesc {
+        fmt: ImageFormat::Rgb8,
+        data: &input,
+        width: width as u32,
+        height: height as u32,
+        stride: Some(stride as u32),
+    };
+
+    let o = OutDesc {
+        fmt: ImageFormat::Rgb8,
+        data: &mut output,
+        width: width as u32,
+        height: height as u32,
+        stride: Some(stride as u32),
+    };
+
+    let mut pass = runner.create_render_pass(o);
+    pass.load_input(i, "input");
+
+    pass.submit().unwrap();
+    assert_eq!(input, output);
+}
+
+const ARGB_BENCH: &str = r"
+import time
+import numpy as np
+
+def setup(ctx):
+    ctx.register_image_input('input')
+
+def run(ctx):
+    output = ctx.output()
+    np.copyto(output, ctx.get_input('input'))
+
+    # the swizzle we used to run on every frame, for comparison
+    scratch = output.copy()
+    start = time.perf_counter()
+    scratch[:] = scratch[..., [3, 0, 1, 2]]
+    print(time.perf_counter() - start)
+";
+
+#[test]
+fn argb_swizzle_4k() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(ARGB_BENCH, None).unwrap();
+
+    let (width, height) = (3840, 2160);
+
+    // u32 backed so the buffers are aligned like AE's
+    let input: Vec<u32> = (0..width * height).map(|i| i.wrapping_mul(2654435761)).collect();
+    let input: &[u8] = bytemuck::cast_slice(&input);
+    let mut output = vec![0u32; (width * height) as usize];
+    let output: &mut [u8] = bytemuck::cast_slice_mut(&mut output);
+
+    let mut render = |fmt| {
+        let i = InDesc {
+            fmt,
+            data: input,
+            width,
+            height,
+            stride: None,
+        };
+
+        let o = OutDesc {
+            fmt,
+            data: &mut *output,
+            width,
+            height,
+            stride: None,
+        };
+
+        let mut pass = runner.create_render_pass(o);
+        pass.load_input(i, "input");
+
+        let start = std::time::Instant::now();
+        let stdout = pass.submit().unwrap().stdout.unwrap();
+        (start.elapsed().as_secs_f64(), stdout.trim().parse::<f64>().unwrap())
+    };
+
+    // the difference between the two runs is the cost of the output swizzle
+    let (unswizzled, _) = render(ImageFormat::Rgba8);
+    let (swizzled, numpy) = render(ImageFormat::Argb8);
+
+    eprintln!(
+        "4k argb swizzle: native {:.2}ms, numpy {:.2}ms",
+        (swizzled - unswizzled).max(0.0) * 1000.0,
+        numpy * 1000.0
+    );
+
+    assert_eq!(input, &output[..]);
+}
+
+const TEMPORAL: &str = r"
+import numpy as np
+
+def setup(ctx):
+    ctx.register_image_input('input')
+    ctx.set_temporal_window(1)
+
+def run(ctx):
+    assert ctx.get_input_at('input', -1) is None
+    assert ctx.get_input_at('input', 2) is None
+    np.copyto(ctx.output(), ctx.get_input_at('input', 1))
+";
+
+#[test]
+fn temporal_inputs() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(TEMPORAL, None).unwrap();
+    assert_eq!(runner.temporal_window(), 1);
+
+    let current = vec![10u8; 4 * 4 * 4];
+    let next = vec![20u8; 4 * 4 * 4];
+    let mut output = vec![0u8; 4 * 4 * 4];
+
+    let current_desc = InDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &current,
+        width: 4,
+        height: 4,
+        stride: None,
+    };
+
+    let next_desc = InDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &next,
+        width: 4,
+        height: 4,
+        stride: None,
+    };
+
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &mut output,
+        width: 4,
+        height: 4,
+        stride: None,
+    };
+
+    // no previous frame, as if we were on the first frame of a clip
+    let mut pass = runner.create_render_pass(o);
+    pass.load_input(current_desc, "input");
+    pass.load_input_at(next_desc, "input", 1);
+
+    pass.submit().unwrap();
+    assert_eq!(output, next);
+}
+
 // really stupid *async* test
 const ASYNC_IDENT: &str = r"
 import numpy as np
@@ -111,6 +307,45 @@ fn async_ident() {
     assert_eq!(input, output);
 }
 
+const TENSOR_OUT: &str = r"
+import torch
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    return torch.full(ctx.output().shape, 7, dtype=torch.uint8)
+
+";
+
+#[test]
+fn tensor_output() {
+    let mut runner = PythonRunner::default();
+
+    let has_torch = pyo3::Python::with_gil(|py| py.import_bound("torch").is_ok());
+    if !has_torch {
+        eprintln!("torch is not installed, skipping");
+        return;
+    }
+
+    runner.load_script(TENSOR_OUT, None).unwrap();
+
+    let mut output = vec![0u8; 10 * 10 * 4];
+
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &mut output,
+        width: 10,
+        height: 10,
+        stride: None,
+    };
+
+    let pass = runner.create_render_pass(o);
+    pass.submit().unwrap();
+
+    assert!(output.iter().all(|b| *b == 7));
+}
+
 const CHASE_MODS: &str = r"
 from local_mods import speaker 
 from local_mods.deeper import deepest 
@@ -384,86 +619,2124 @@ fn error_logging() {
     ));
 }
 
-const REG_TEST: &str = r"
+const DEEP_ERROR: &str = r"
 
 def setup(ctx):
-    ctx.register_float('float 1', default=0, min=-100, max=100)
-    ctx.register_float('float 2', min=100, max=1000, default=200)
-    ctx.register_image_input('input')
     pass
 
+def helper(x):
+    return 1 / x
+
 def run(ctx):
-    pass
+    helper(0)
 
 ";
 
 #[test]
-fn registry() {
+fn full_traceback() {
     let mut runner = PythonRunner::default();
 
-    runner.load_script(REG_TEST, None).unwrap();
+    runner.load_script(DEEP_ERROR, None).unwrap();
 
-    {
-        let next = runner.iter_inputs().find(|(n, _)| *n == "float 1").unwrap();
-        assert_eq!(*next.1, Variant::Float(Cfg::new(0.0, -100.0, 100.0)));
+    let mut data = vec![0u8; 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 1,
+        data: &mut data,
+        height: 1,
+        stride: None,
+    };
 
-        let next = runner.iter_inputs().find(|(n, _)| *n == "float 2").unwrap();
-        assert_eq!(*next.1, Variant::Float(Cfg::new(200.0, 100.0, 1000.0)));
+    let Err(GolobulError::RuntimeError { stderr, .. }) = runner.create_render_pass(o).submit()
+    else {
+        panic!("expected a runtime error");
+    };
 
-        let next = runner.iter_inputs().find(|(n, _)| *n == "input").unwrap();
-        assert_eq!(*next.1, Variant::Image(DiscreteCfg::new(Image::Input)));
-    }
+    let summary = stderr.lines().next().unwrap();
+    assert!(summary.contains("ZeroDivisionError"));
+    // the deepest line of the script, inside helper
+    assert!(summary.starts_with("line 7: "), "{summary}");
+
+    assert!(stderr.contains("Traceback"));
+    assert!(stderr.contains("in helper"));
 }
-const SIZE_CONFIG: &str = r"
+
+const LIBRARY_ERROR: &str = r"
+import json
 
 def setup(ctx):
-    ctx.set_output_size(20, 20)
     pass
 
 def run(ctx):
-    assert ctx.output().shape == (20, 20, 4)
-    pass
+    json.loads('not json')
 
 ";
 
 #[test]
-fn size_config() {
+fn traceback_points_at_script() {
     let mut runner = PythonRunner::default();
-    runner.load_script(SIZE_CONFIG, None).unwrap();
-
-    assert_eq!(
-        runner.requested_output_resize(),
-        Some(OutputSize {
-            width: 20,
-            height: 20
-        })
-    );
 
-    let mut wrong_out = [0u8; 40 * 40 * 4];
+    runner.load_script(LIBRARY_ERROR, None).unwrap();
 
+    let mut data = vec![0u8; 4];
     let o = OutDesc {
         fmt: ImageFormat::Rgba8,
-        data: &mut wrong_out,
-        width: 40,
-        height: 40,
+        width: 1,
+        data: &mut data,
+        height: 1,
         stride: None,
     };
 
-    let pass = runner.create_render_pass(o);
-    pass.submit().unwrap();
+    let Err(GolobulError::RuntimeError { stderr, .. }) = runner.create_render_pass(o).submit()
+    else {
+        panic!("expected a runtime error");
+    };
 
-    let mut right_out = [0u8; 20 * 20 * 4];
+    // the json call in run, not the line in json's decoder that raised
+    let summary = stderr.lines().next().unwrap();
+    assert!(summary.starts_with("line 8: "), "{summary}");
+
+    // the library frames are still in the full trace
+    assert!(stderr.contains("decoder.py"));
+}
+
+const MISSING_IMPORT: &str = r"
+
+def setup(ctx):
+    import definitely_not_a_real_module
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn exception_type() {
+    let mut runner = PythonRunner::default();
+
+    let error = runner.load_script(MISSING_IMPORT, None).unwrap_err();
+    assert!(error.is_missing_dependency());
+
+    let error = runner.load_script(DEEP_ERROR, None).and_then(|_| {
+        let mut data = vec![0u8; 4];
+        let o = OutDesc {
+            fmt: ImageFormat::Rgba8,
+            width: 1,
+            data: &mut data,
+            height: 1,
+            stride: None,
+        };
+        runner.create_render_pass(o).submit()
+    });
+
+    assert!(matches!(
+        error,
+        Err(GolobulError::RuntimeError {
+            exception_type: Some(ref t),
+            ..
+        }) if t == "ZeroDivisionError"
+    ));
+}
+
+const WARNS: &str = r"
+import warnings
+
+def setup(ctx):
+    warnings.warn('setup is deprecated', DeprecationWarning)
+
+def run(ctx):
+    warnings.warn('careful')
+    ctx.output().fill(255)
+
+";
+
+#[test]
+fn warnings_dont_fail_the_render() {
+    let mut runner = PythonRunner::default();
 
+    runner.load_script(WARNS, None).unwrap();
+
+    let mut data = vec![0u8; 4];
     let o = OutDesc {
         fmt: ImageFormat::Rgba8,
-        data: &mut right_out,
-        width: 20,
-        height: 20,
+        width: 1,
+        data: &mut data,
+        height: 1,
         stride: None,
     };
 
-    let pass = runner.create_render_pass(o);
-    pass.submit().unwrap();
+    runner.create_render_pass(o).submit().unwrap();
+    assert_eq!(data, [255; 4]);
+}
+
+const ODD_WARNING: &str = r"
+import warnings
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    # some libraries call it themselves, without a real file or line
+    warnings.showwarning('odd', UserWarning, None, None)
+    print(len(warnings.filters))
+
+";
+
+#[test]
+fn warning_logger_installs_once() {
+    let mut runner = PythonRunner::default();
+    let mut data = vec![0u8; 4];
+
+    let mut filters = vec![];
+    for _ in 0..2 {
+        runner.reload_script(ODD_WARNING, None).unwrap();
+
+        let report = runner
+            .create_render_pass(one_pixel(&mut data))
+            .submit()
+            .unwrap();
+        filters.push(report.stdout);
+    }
+
+    assert_eq!(filters[0], filters[1]);
+}
+
+const RUNAWAY: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    while True:
+        pass
+
+";
+
+#[test]
+fn run_timeout() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(RUNAWAY, None).unwrap();
+    runner.set_run_timeout(Some(std::time::Duration::from_millis(500)));
+
+    let mut data = vec![0u8; 10 * 10 * 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 10,
+        data: &mut data,
+        height: 10,
+        stride: None,
+    };
+
+    let pass = runner.create_render_pass(o);
+
+    assert!(matches!(pass.submit(), Err(GolobulError::Timeout)));
+}
+
+const HANGING_SETUP: &str = r"
+
+def setup(ctx):
+    while True:
+        pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn cancel_setup() {
+    let mut runner = PythonRunner::default();
+
+    let token = CancellationToken::new();
+    runner.set_setup_cancellation_token(Some(token.clone()));
+
+    let cancel = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        cancel.cancel();
+    });
+
+    let out = runner.load_script(HANGING_SETUP, None);
+    assert!(matches!(out, Err(GolobulError::Cancelled)));
+
+    // once reset the next load goes through
+    token.reset();
+    runner.load_script(RUNAWAY, None).unwrap();
+}
+
+#[test]
+fn setup_timeout() {
+    let mut runner = PythonRunner::default();
+    runner.set_setup_timeout(Some(std::time::Duration::from_millis(200)));
+
+    let out = runner.load_script(HANGING_SETUP, None);
+    assert!(matches!(out, Err(GolobulError::SetupTimeout)));
+
+    runner.load_script(RUNAWAY, None).unwrap();
+}
+
+const HOST_INFO: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    assert ctx.comp_size() == (1080, 1920)
+    assert ctx.layer_name() == 'Adjustment Layer 1'
+
+";
+
+#[test]
+fn host_info() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(HOST_INFO, None).unwrap();
+    runner.set_comp_size(1920, 1080);
+    runner.set_layer_name(Some("Adjustment Layer 1".to_owned()));
+
+    let mut data = vec![0u8; 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 1,
+        data: &mut data,
+        height: 1,
+        stride: None,
+    };
+
+    runner.create_render_pass(o).submit().unwrap();
+}
+
+const CAPABILITIES: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    caps = ctx.capabilities()
+    assert 'Argb16ae' in caps['formats']
+    assert caps['async']
+    assert caps['version'] in ctx.build_info()
+    print(caps['max_inputs'], caps['sequential'])
+
+";
+
+#[test]
+fn capabilities() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(CAPABILITIES, None).unwrap();
+
+    let mut output = [0u8; 4];
+    let report = runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+    assert_eq!(report.stdout.as_deref().map(str::trim), Some("None False"));
+
+    runner.set_max_inputs(Some(16));
+    runner.set_sequential_supported(true);
+    let report = runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+    assert_eq!(report.stdout.as_deref().map(str::trim), Some("16 True"));
+}
+
+const NOISE: &str = r"
+import numpy as np
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    ctx.seed_all()
+    output = ctx.output()
+    output[:] = np.random.randint(0, 256, output.shape)
+
+";
+
+#[test]
+fn seeded_noise_is_stable() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(NOISE, None).unwrap();
+
+    let mut render = |frame: u32| {
+        runner.set_frame(frame);
+
+        let mut data = vec![0u8; 8 * 8 * 4];
+        let o = OutDesc {
+            fmt: ImageFormat::Rgba8,
+            width: 8,
+            data: &mut data,
+            height: 8,
+            stride: None,
+        };
+
+        runner.create_render_pass(o).submit().unwrap();
+        data
+    };
+
+    let first = render(3);
+    assert_eq!(first, render(3));
+    assert_ne!(first, render(4));
+}
+
+const TWO_OUTPUTS: &str = r"
+
+def setup(ctx):
+    ctx.register_output('depth')
+
+def run(ctx):
+    ctx.output().fill(255)
+    ctx.output('depth').fill(7)
+    assert (ctx.output('depth') == 7).all()
+
+";
+
+#[test]
+fn named_outputs() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(TWO_OUTPUTS, None).unwrap();
+    assert_eq!(runner.outputs(), ["depth"]);
+
+    let mut color = vec![0u8; 4];
+    let mut depth = vec![0u8; 4];
+
+    let mut pass = runner.create_render_pass(one_pixel(&mut color));
+    pass.load_output(one_pixel(&mut depth), "depth");
+    pass.submit().unwrap();
+
+    assert_eq!(color, [255; 4]);
+    assert_eq!(depth, [7; 4]);
+
+    // outputs the host doesn't load still get written somewhere
+    color.fill(0);
+    runner
+        .create_render_pass(one_pixel(&mut color))
+        .submit()
+        .unwrap();
+    assert_eq!(color, [255; 4]);
+}
+
+const KEEPS_OUTPUT: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    out = ctx.output()
+    assert out is ctx.output()
+    kept = ctx.state.get('output') is out
+    ctx.state['output'] = out
+    out.fill(255 if kept else 0)
+
+";
+
+#[test]
+fn output_views_kept_per_generation() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(KEEPS_OUTPUT, None).unwrap();
+
+    // the same buffer at the same address every time, 255 once its view is kept
+    let mut output = [0u8; 4];
+    let mut render = |generation: Option<u64>| {
+        let mut pass = runner.create_render_pass(one_pixel(&mut output));
+        if let Some(generation) = generation {
+            pass.set_output_generation(generation);
+        }
+        pass.submit().unwrap();
+        output[0]
+    };
+
+    // without a generation a view never outlives its pass
+    assert_eq!(render(None), 0);
+    assert_eq!(render(None), 0);
+
+    assert_eq!(render(Some(1)), 0);
+    assert_eq!(render(Some(1)), 255);
+
+    assert_eq!(render(Some(2)), 0);
+}
+
+fn one_pixel(data: &mut [u8]) -> OutDesc<'_> {
+    OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 1,
+        data,
+        height: 1,
+        stride: None,
+    }
+}
+
+const STRAIGHT: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('input')
+    ctx.set_alpha_mode('straight')
+
+def run(ctx):
+    try:
+        ctx.set_alpha_mode('premultiplied')
+        assert False
+    except RuntimeError:
+        pass
+
+    input = ctx.get_input('input')
+    assert input[0, 0].tolist() == [128, 128, 128, 128]
+    ctx.output()[:] = input
+
+";
+
+#[test]
+fn straight_alpha_round_trip() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(STRAIGHT, None).unwrap();
+
+    // half transparent mid gray, premultiplied ARGB like AE sends it
+    let input = [128u8, 64, 64, 64];
+    let mut output = [0u8; 4];
+
+    let i = InDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &input,
+        height: 1,
+        stride: None,
+    };
+
+    let o = OutDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &mut output,
+        height: 1,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(o);
+    pass.load_input(i, "input");
+    pass.submit().unwrap();
+
+    assert_eq!(input, output);
+}
+
+const STRAIGHT_RAW: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('input')
+    ctx.set_automatic_color_correction(False)
+    ctx.set_alpha_mode('straight')
+
+def run(ctx):
+    input = ctx.get_input('input')
+    assert input[0, 0].tolist() == [128, 128, 64, 32]
+    # inputs stay ARGB, the output is RGBA either way
+    ctx.output()[:] = input[..., [1, 2, 3, 0]]
+
+";
+
+#[test]
+fn straight_alpha_round_trip_without_color_correction() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(STRAIGHT_RAW, None).unwrap();
+
+    let input = [128u8, 64, 32, 16];
+    let mut output = [0u8; 4];
+
+    let i = InDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &input,
+        height: 1,
+        stride: None,
+    };
+
+    let o = OutDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &mut output,
+        height: 1,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(o);
+    pass.load_input(i, "input");
+    pass.submit().unwrap();
+
+    assert_eq!(input, output);
+}
+
+const PREMULTIPLIED: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('input')
+
+def run(ctx):
+    ctx.output()[:] = ctx.get_input('input')
+
+";
+
+// sequential renders hand over RGBA outputs, which are never premultiplied again
+fn render_rgba(script: &str) -> [u8; 4] {
+    let mut runner = PythonRunner::default();
+    runner.load_script(script, None).unwrap();
+
+    // half transparent mid gray, premultiplied ARGB like AE sends it
+    let input = [128u8, 64, 64, 64];
+    let mut output = [0u8; 4];
+
+    let i = InDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &input,
+        height: 1,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(one_pixel(&mut output));
+    pass.load_input(i, "input");
+    pass.submit().unwrap();
+
+    output
+}
+
+#[test]
+fn rgba_outputs_left_as_written() {
+    assert_eq!(render_rgba(STRAIGHT), [128, 128, 128, 128]);
+    assert_eq!(render_rgba(PREMULTIPLIED), [64, 64, 64, 128]);
+}
+
+const MATTE: &str = r"
+
+def setup(ctx):
+    ctx.set_matte_mode('MODE')
+
+def run(ctx):
+    try:
+        ctx.set_matte_mode('off')
+        assert False
+    except RuntimeError:
+        pass
+
+    out = ctx.output()
+    assert out.shape == (1, 1)
+    out[:] = 200
+
+";
+
+#[test]
+fn matte_modes() {
+    let mut runner = PythonRunner::default();
+
+    for (mode, expected) in [("alpha", [255, 255, 255, 200]), ("rgb", [200, 200, 200, 255])] {
+        runner
+            .load_script(&MATTE.replace("MODE", mode), None)
+            .unwrap();
+
+        let mut output = [0u8; 4];
+        runner
+            .create_render_pass(one_pixel(&mut output))
+            .submit()
+            .unwrap();
+
+        assert_eq!(output, expected, "{mode}");
+    }
+
+    // premultiplied ARGB gets the matte in every channel
+    runner
+        .load_script(&MATTE.replace("MODE", "alpha"), None)
+        .unwrap();
+
+    let mut output = [0u8; 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &mut output,
+        height: 1,
+        stride: None,
+    };
+    runner.create_render_pass(o).submit().unwrap();
+
+    assert_eq!(output, [200; 4]);
+}
+
+const ROUND_TRIP: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('input')
+
+def run(ctx):
+    input = ctx.get_input('input')
+    print(input[0, 0].tolist())
+    ctx.output()[:] = input
+
+";
+
+#[test]
+fn format_round_trips() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(ROUND_TRIP, None).unwrap();
+
+    let u16s = |px: [u16; 4]| px.iter().flat_map(|c| c.to_ne_bytes()).collect::<Vec<u8>>();
+    let f32s = |px: [f32; 4]| px.iter().flat_map(|c| c.to_ne_bytes()).collect::<Vec<u8>>();
+
+    // host bytes, then what the script should see in RGBA order.
+    // Argb16ae tops out at 32768 and is not rescaled to Rgba16's range
+    let cases = [
+        (
+            ImageFormat::Rgba8,
+            vec![10, 20, 30, 255],
+            "[10, 20, 30, 255]",
+        ),
+        (
+            ImageFormat::Argb8,
+            vec![255, 10, 20, 30],
+            "[10, 20, 30, 255]",
+        ),
+        (
+            ImageFormat::Rgba16,
+            u16s([1000, 2000, 3000, 65535]),
+            "[1000, 2000, 3000, 65535]",
+        ),
+        (
+            ImageFormat::Argb16ae,
+            u16s([32768, 1000, 2000, 3000]),
+            "[1000, 2000, 3000, 32768]",
+        ),
+        (
+            ImageFormat::Rgba32,
+            f32s([0.25, 0.5, 2.0, 1.0]),
+            "[0.25, 0.5, 2.0, 1.0]",
+        ),
+        (
+            ImageFormat::Argb32,
+            f32s([1.0, 0.25, 0.5, 2.0]),
+            "[0.25, 0.5, 2.0, 1.0]",
+        ),
+    ];
+
+    for (fmt, data, expected) in cases {
+        let mut output = vec![0u8; data.len()];
+
+        let i = InDesc {
+            fmt,
+            width: 1,
+            data: &data,
+            height: 1,
+            stride: None,
+        };
+
+        let o = OutDesc {
+            fmt,
+            width: 1,
+            data: &mut output,
+            height: 1,
+            stride: None,
+        };
+
+        let mut pass = runner.create_render_pass(o);
+        pass.load_input(i, "input");
+        let stdout = pass.submit().unwrap().stdout.unwrap();
+
+        assert_eq!(stdout.trim(), expected, "{fmt:?}");
+        assert_eq!(output, data, "{fmt:?}");
+    }
+}
+
+const INPUT_FORMAT: &str = r"
+import numpy as np
+
+def setup(ctx):
+    ctx.register_image_input('input')
+    ctx.request_input_format('input', 'float32')
+
+def run(ctx):
+    input = ctx.get_input('input')
+    assert input.dtype == np.float32
+    assert np.allclose(input[0, 0], [0.0, 0.2, 1.0, 1.0])
+
+";
+
+#[test]
+fn input_upconverted_to_float() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(INPUT_FORMAT, None).unwrap();
+
+    let input = [0u8, 51, 255, 255];
+    let mut output = [0u8; 4];
+
+    let i = InDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 1,
+        data: &input,
+        height: 1,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(one_pixel(&mut output));
+    pass.load_input(i, "input");
+    pass.submit().unwrap();
+}
+
+const MIXED_CORRECTION: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('color')
+    ctx.register_image_input('normals', color_correction=False)
+
+def run(ctx):
+    assert ctx.get_input('color')[0, 0].tolist() == [2, 3, 4, 1]
+    assert ctx.get_input('normals')[0, 0].tolist() == [1, 2, 3, 4]
+
+";
+
+#[test]
+fn per_input_color_correction() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(MIXED_CORRECTION, None).unwrap();
+
+    let pixel = [1u8, 2, 3, 4];
+    let mut output = [0u8; 4];
+
+    let input = || InDesc {
+        fmt: ImageFormat::Argb8,
+        width: 1,
+        data: &pixel,
+        height: 1,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(one_pixel(&mut output));
+    pass.load_input(input(), "color");
+    pass.load_input(input(), "normals");
+    pass.submit().unwrap();
+}
+
+const HELPERS: &str = r"
+import numpy as np
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    image = np.arange(4 * 4 * 4, dtype=np.uint8).reshape(4, 4, 4)
+
+    crop = ctx.center_crop(image, 2, 2)
+    assert crop.shape == (2, 2, 4)
+    assert (crop == image[1:3, 1:3]).all()
+
+    argb = image.copy()
+    ctx.swizzle_in_place(argb)
+    assert (ctx.rgba_view(argb) == image).all()
+
+";
+
+#[test]
+fn exposed_helpers() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(HELPERS, None).unwrap();
+
+    let mut output = [0u8; 4];
+    runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+}
+
+const FALLBACK: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('input', default='golob_fallback_test.png')
+
+def run(ctx):
+    input = ctx.get_input('input')
+    assert input.shape == (2, 3, 4)
+    ctx.output()[:] = input[0, 0]
+
+";
+
+#[test]
+fn default_image() {
+    let dir = std::env::temp_dir();
+    let fallback = ImageBuffer::from_pixel(3, 2, Rgba([10u8, 20, 30, 255]));
+    fallback.save(dir.join("golob_fallback_test.png")).unwrap();
+
+    let mut runner = PythonRunner::default();
+    runner.set_script_parent_directory(dir);
+    runner.load_script(FALLBACK, None).unwrap();
+
+    let mut output = [0u8; 4];
+    runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+
+    assert_eq!(output, [10, 20, 30, 255]);
+
+    let broken = FALLBACK.replace("golob_fallback_test.png", "not_an_image.png");
+    runner.load_script(broken, None).unwrap();
+
+    let pass = runner.create_render_pass(one_pixel(&mut output));
+    assert!(matches!(pass.submit(), Err(GolobulError::DefaultImage { .. })));
+}
+
+const DEEP_FALLBACK: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('input', default='golob_deep_fallback_test.png')
+
+def run(ctx):
+    input = ctx.get_input('input')
+    assert input.dtype == ctx.output().dtype
+    ctx.output()[:] = input[0, 0]
+
+";
+
+#[test]
+fn default_image_matches_output_depth() {
+    let dir = std::env::temp_dir();
+    let fallback = ImageBuffer::from_pixel(1, 1, Rgba([0u8, 51, 255, 255]));
+    fallback
+        .save(dir.join("golob_deep_fallback_test.png"))
+        .unwrap();
+
+    let mut runner = PythonRunner::default();
+    runner.set_script_parent_directory(dir);
+    runner.load_script(DEEP_FALLBACK, None).unwrap();
+
+    let mut output = [0u16; 4];
+    runner
+        .create_render_pass(OutDesc {
+            fmt: ImageFormat::Rgba16,
+            data: bytemuck::cast_slice_mut(&mut output),
+            width: 1,
+            height: 1,
+            stride: None,
+        })
+        .submit()
+        .unwrap();
+    assert_eq!(output, [0, 13107, 65535, 65535]);
+
+    let mut output = [0f32; 4];
+    runner
+        .create_render_pass(OutDesc {
+            fmt: ImageFormat::Rgba32,
+            data: bytemuck::cast_slice_mut(&mut output),
+            width: 1,
+            height: 1,
+            stride: None,
+        })
+        .submit()
+        .unwrap();
+    assert_eq!(output, [0.0, 0.2, 1.0, 1.0]);
+}
+
+const IMPORTS_HELPER: &str = r"
+import golob_reload_helper
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    ctx.output().fill(golob_reload_helper.VALUE)
+
+";
+
+#[test]
+fn reload_picks_up_module_edits() {
+    let dir = std::env::temp_dir().join("golob_reload_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let helper = dir.join("golob_reload_helper.py");
+
+    let mut runner = PythonRunner::default();
+    runner.set_script_parent_directory(dir.clone());
+
+    let mut output = [0u8; 4];
+
+    // different lengths, so a stale .pyc can't match on size and mtime
+    for (src, expected) in [("VALUE = 1\n", 1), ("VALUE = 200\n", 200)] {
+        std::fs::write(&helper, src).unwrap();
+        runner.load_script(IMPORTS_HELPER, None).unwrap();
+
+        runner
+            .create_render_pass(one_pixel(&mut output))
+            .submit()
+            .unwrap();
+
+        assert_eq!(output, [expected; 4]);
+    }
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn builder_adds_paths_before_the_first_load() {
+    let dir = std::env::temp_dir().join("golob_builder_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("golob_builder_helper.py"), "VALUE = 9\n").unwrap();
+
+    let src = IMPORTS_HELPER.replace("golob_reload_helper", "golob_builder_helper");
+    let mut runner = PythonRunner::builder()
+        .script_parent_directory(dir.clone())
+        .build(src, None)
+        .unwrap();
+
+    let mut output = [0u8; 4];
+    runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+
+    assert_eq!(output, [9; 4]);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+const SEQUENTIAL_IN_SETUP: &str = r"
+
+def setup(ctx):
+    ctx.set_sequential_mode(ctx.capabilities()['sequential'])
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn builder_sequential_supported_in_first_setup() {
+    let runner = PythonRunner::builder()
+        .sequential_supported(true)
+        .build(SEQUENTIAL_IN_SETUP, None)
+        .unwrap();
+
+    assert!(runner.is_sequential());
+}
+
+const COUNTER: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    ctx.state['n'] = ctx.state.get('n', 0) + 1
+    ctx.output().fill(ctx.state['n'])
+
+";
+
+#[test]
+fn state_persists_between_runs() {
+    let mut runner = PythonRunner::default();
+    let mut data = vec![0u8; 4];
+
+    for expected in [1, 2, 1] {
+        // loading, and reloading, starts over
+        if expected == 1 {
+            runner.load_script(COUNTER, None).unwrap();
+        }
+
+        let o = OutDesc {
+            fmt: ImageFormat::Rgba8,
+            width: 1,
+            data: &mut data,
+            height: 1,
+            stride: None,
+        };
+
+        runner.create_render_pass(o).submit().unwrap();
+        assert_eq!(data, [expected; 4]);
+    }
+}
+
+const OPTIONAL_INPUTS: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('a')
+    ctx.register_image_input('b')
+    ctx.register_float('gain')
+
+def run(ctx):
+    assert ctx.input_names() == ['a', 'b', 'gain']
+    assert ctx.has_input('a')
+    assert not ctx.has_input('b')
+    assert ctx.has_input('gain')
+
+    try:
+        ctx.has_input('nope')
+        assert False
+    except KeyError:
+        pass
+
+";
+
+#[test]
+fn connected_inputs() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(OPTIONAL_INPUTS, None).unwrap();
+
+    let pixel = [0u8; 4];
+    let mut output = [0u8; 4];
+    let mut pass = runner.create_render_pass(one_pixel(&mut output));
+    let input = InDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &pixel,
+        width: 1,
+        height: 1,
+        stride: None,
+    };
+    pass.load_input(input, "a");
+    pass.submit().unwrap();
+}
+
+const NAMED: &str = r"
+
+def setup(ctx):
+    ctx.set_name('Edge Detect')
+
+def run(ctx):
+    try:
+        ctx.set_name('Something Else')
+        assert False
+    except RuntimeError:
+        pass
+
+";
+
+#[test]
+fn script_name() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(NAMED, None).unwrap();
+    assert_eq!(runner.name(), Some("Edge Detect"));
+
+    let mut output = [0u8; 4];
+    runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+    assert_eq!(runner.name(), Some("Edge Detect"));
+
+    // the next script doesn't inherit it
+    runner.load_script(DOES_NOTHING, None).unwrap();
+    assert_eq!(runner.name(), None);
+}
+
+const GENERATOR: &str = r"
+
+def setup(ctx):
+    ctx.register_image_input('texture')
+    ctx.set_image_filter_default(False)
+
+def run(ctx):
+    try:
+        ctx.set_image_filter_default(True)
+        assert False
+    except RuntimeError:
+        pass
+
+";
+
+#[test]
+fn image_filter_default() {
+    let mut runner = PythonRunner::default();
+    assert!(runner.image_filter_default());
+
+    runner.load_script(GENERATOR, None).unwrap();
+    assert!(!runner.image_filter_default());
+
+    let mut output = [0u8; 4];
+    runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+    assert!(!runner.image_filter_default());
+
+    runner.load_script(DOES_NOTHING, None).unwrap();
+    assert!(runner.image_filter_default());
+}
+
+const PRIMED: &str = r"
+primes = 0
+
+def setup(ctx):
+    pass
+
+def prime(ctx):
+    global primes
+    primes += 1
+    ctx.state['primes'] = primes
+
+def run(ctx):
+    print(ctx.state['primes'])
+
+";
+
+#[test]
+fn prime_runs_once() {
+    let mut runner = PythonRunner::default();
+    let mut output = [0u8; 4];
+
+    for _ in 0..2 {
+        runner.load_script(PRIMED, None).unwrap();
+
+        for _ in 0..3 {
+            let report = runner
+                .create_render_pass(one_pixel(&mut output))
+                .submit()
+                .unwrap();
+            assert_eq!(report.stdout.as_deref().map(str::trim), Some("1"));
+        }
+    }
+}
+
+const SETUP_SIZE: &str = r"
+
+def setup(ctx):
+    print(ctx.max_output_size())
+
+def run(ctx):
+    pass
+
+";
+
+const SETUP_REQUESTED_SIZE: &str = r"
+
+def setup(ctx):
+    ctx.set_output_size(20, 20)
+    print(ctx.max_output_size())
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn output_size_in_setup() {
+    let mut runner = PythonRunner::default();
+    let size = |runner: &mut PythonRunner| runner.reload_script(SETUP_SIZE, None).unwrap();
+
+    // nothing has been rendered or requested yet
+    assert_eq!(size(&mut runner).as_deref().map(str::trim), Some("(0, 0)"));
+
+    // the last run's output is the next best guess
+    let mut output = [0u8; 4];
+    runner
+        .create_render_pass(one_pixel(&mut output))
+        .submit()
+        .unwrap();
+    assert_eq!(size(&mut runner).as_deref().map(str::trim), Some("(1, 1)"));
+
+    // a requested size wins, it's what `output()` will be
+    let requested = runner.reload_script(SETUP_REQUESTED_SIZE, None).unwrap();
+    assert_eq!(requested.as_deref().map(str::trim), Some("(20, 20)"));
+
+    // and it isn't carried over to the next script
+    assert_eq!(size(&mut runner).as_deref().map(str::trim), Some("(1, 1)"));
+}
+
+const STATUS: &str = r"
+
+def setup(ctx):
+    ctx.set_status('faces', 'none yet')
+
+def run(ctx):
+    runs = ctx.state.get('runs', 0) + 1
+    ctx.state['runs'] = runs
+    ctx.set_status('faces', f'detected {runs}')
+    if runs > 1:
+        ctx.set_status('warning', 'slow')
+    if runs > 2:
+        ctx.set_status('warning', None)
+
+";
+
+#[test]
+fn statuses() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(STATUS, None).unwrap();
+
+    let statuses = |runner: &PythonRunner| {
+        runner
+            .iter_statuses()
+            .map(|(k, v)| format!("{k}: {v}"))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(statuses(&runner), ["faces: none yet"]);
+
+    let mut output = [0u8; 4];
+    let expected = [
+        vec!["faces: detected 1"],
+        vec!["faces: detected 2", "warning: slow"],
+        vec!["faces: detected 3"],
+    ];
+
+    for expected in expected {
+        runner
+            .create_render_pass(one_pixel(&mut output))
+            .submit()
+            .unwrap();
+        assert_eq!(statuses(&runner), expected);
+    }
+
+    // a new script starts with none
+    runner.load_script(DOES_NOTHING, None).unwrap();
+    assert_eq!(runner.iter_statuses().count(), 0);
+}
+
+#[test]
+fn stride_checks() {
+    // 2 pixel wide Rgba8, rows are 8 bytes
+    let mut data = [0u8; 16];
+
+    let exact = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 2,
+        data: &mut data,
+        height: 2,
+        stride: Some(8),
+    };
+    assert!(exact.is_well_structured().is_ok());
+
+    // 4 rows of 4 bytes fill the buffer, but each 8 byte row overlaps the next
+    let too_small = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 2,
+        data: &mut data,
+        height: 4,
+        stride: Some(4),
+    };
+    assert!(matches!(
+        too_small.is_well_structured(),
+        Err(GolobulError::InvalidStride {
+            stride: 4,
+            row_size: 8
+        })
+    ));
+}
+
+#[test]
+fn short_input_buffer() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(STRAIGHT, None).unwrap();
+
+    // a 2x2 input needs 16 bytes
+    let input = [0u8; 12];
+    let mut output = [0u8; 4];
+
+    let i = InDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 2,
+        data: &input,
+        height: 2,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(one_pixel(&mut output));
+    pass.load_input(i, "input");
+
+    match pass.submit() {
+        Err(GolobulError::SizeMismatch {
+            expected: 16,
+            found: 12,
+            input: Some(name),
+        }) => assert_eq!(name, "input"),
+        other => panic!("expected a size mismatch, got {other:?}"),
+    }
+}
+
+const DEVICE: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    try:
+        import torch
+        expected = torch.cuda.is_available()
+    except ImportError:
+        expected = False
+
+    assert ctx.has_cuda() == expected
+    assert ctx.device() == ('cuda' if expected else 'cpu')
+
+";
+
+#[test]
+fn device() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(DEVICE, None).unwrap();
+
+    // the second run answers from the cache
+    let mut output = [0u8; 4];
+    for _ in 0..2 {
+        runner
+            .create_render_pass(one_pixel(&mut output))
+            .submit()
+            .unwrap();
+    }
+}
+
+const SETUP_COUNTER: &str = r"
+import builtins
+
+def setup(ctx):
+    builtins.golob_setups = getattr(builtins, 'golob_setups', 0) + 1
+    print(builtins.golob_setups)
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn unchanged_source_skips_setup() {
+    let mut runner = PythonRunner::default();
+    let setups = |out: Option<String>| out.unwrap().trim().parse::<u32>().unwrap();
+
+    let first = setups(runner.load_script(SETUP_COUNTER, None).unwrap());
+
+    // the same source hands back the first setup's stdout
+    let again = setups(runner.load_script(SETUP_COUNTER, None).unwrap());
+    assert_eq!(again, first);
+
+    // forcing it runs setup in a fresh module
+    let forced = setups(runner.reload_script(SETUP_COUNTER, None).unwrap());
+    assert_eq!(forced, first + 1);
+}
+
+const DOES_NOTHING: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    return
+
+";
+
+#[test]
+fn output_written() {
+    let mut runner = PythonRunner::default();
+    let mut data = [7u8; 4];
+
+    runner.load_script(DOES_NOTHING, None).unwrap();
+    let report = runner
+        .create_render_pass(one_pixel(&mut data))
+        .submit()
+        .unwrap();
+
+    assert!(!report.wrote_output);
+    assert!(!runner.output_written());
+    assert_eq!(data, [7; 4]);
+
+    runner.load_script(COUNTER, None).unwrap();
+    runner
+        .create_render_pass(one_pixel(&mut data))
+        .submit()
+        .unwrap();
+
+    assert!(runner.output_written());
+}
+
+const NEVER_RESOLVES: &str = r"
+import asyncio
+
+def setup(ctx):
+    pass
+
+async def run(ctx):
+    await asyncio.sleep(100)
+
+";
+
+#[test]
+fn async_timeout() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(NEVER_RESOLVES, None).unwrap();
+    runner.set_async_timeout(Some(std::time::Duration::from_millis(200)));
+
+    let mut data = vec![0u8; 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 1,
+        data: &mut data,
+        height: 1,
+        stride: None,
+    };
+
+    let pass = runner.create_render_pass(o);
+
+    assert!(matches!(pass.submit(), Err(GolobulError::Timeout)));
+}
+
+const GATHER: &str = r"
+import asyncio
+
+async def double(x):
+    await asyncio.sleep(0.01)
+    return x * 2
+
+def setup(ctx):
+    # not on the loop, so this waits for the results
+    ctx.state['doubled'] = ctx.gather(double(1), double(2))
+
+async def run(ctx):
+    assert ctx.state['doubled'] == [2, 4]
+    assert await ctx.gather(double(3), double(4)) == [6, 8]
+
+    later = ctx.run_soon(double(5))
+    assert await asyncio.wrap_future(later) == 10
+
+    ctx.output().fill(255)
+
+";
+
+#[test]
+fn async_gather() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(GATHER, None).unwrap();
+
+    let mut data = vec![0u8; 4];
+    runner.create_render_pass(one_pixel(&mut data)).submit().unwrap();
+
+    assert_eq!(data, [255; 4]);
+}
+
+const CANCELLABLE: &str = r"
+
+def setup(ctx):
+    pass
+
+def run(ctx):
+    while not ctx.is_cancelled():
+        pass
+    ctx.output().fill(255)
+
+";
+
+#[test]
+fn cancelled_pass() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(CANCELLABLE, None).unwrap();
+
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        canceller.cancel();
+    });
+
+    let mut data = vec![0u8; 10 * 10 * 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 10,
+        data: &mut data,
+        height: 10,
+        stride: None,
+    };
+
+    let mut pass = runner.create_render_pass(o);
+    pass.set_cancellation_token(token);
+
+    assert!(matches!(pass.submit(), Err(GolobulError::Cancelled)));
+    assert!(data.iter().all(|b| *b == 0));
+}
+
+const REG_TEST: &str = r"
+
+def setup(ctx):
+    ctx.register_float('float 1', default=0, min=-100, max=100)
+    ctx.register_float('float 2', min=100, max=1000, default=200)
+    ctx.register_image_input('input')
+    pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn registry() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(REG_TEST, None).unwrap();
+
+    {
+        let next = runner.iter_inputs().find(|(n, _)| *n == "float 1").unwrap();
+        assert_eq!(
+            *next.1,
+            Variant::Float(Cfg::new(0.0, -100.0, 100.0), Scale::Linear)
+        );
+
+        let next = runner.iter_inputs().find(|(n, _)| *n == "float 2").unwrap();
+        assert_eq!(
+            *next.1,
+            Variant::Float(Cfg::new(200.0, 100.0, 1000.0), Scale::Linear)
+        );
+
+        let next = runner.iter_inputs().find(|(n, _)| *n == "input").unwrap();
+        assert_eq!(
+            *next.1,
+            Variant::Image(DiscreteCfg::new(Image::Input), ImageSettings::default())
+        );
+    }
+}
+
+const MANY_INPUTS: &str = r"
+
+def setup(ctx):
+    for i in range(40):
+        ctx.register_float(f'knob {i}')
+
+def run(ctx):
+    pass
+";
+
+#[test]
+fn input_limit() {
+    let mut runner = PythonRunner::default();
+
+    runner.set_max_inputs(Some(64));
+    runner.load_script(MANY_INPUTS, None).unwrap();
+    assert_eq!(runner.iter_inputs().count(), 40);
+
+    runner.set_max_inputs(Some(32));
+    let e = runner.load_script(MANY_INPUTS, None);
+    assert!(matches!(
+        e,
+        Err(GolobulError::TooManyInputs {
+            registered: 40,
+            max: 32
+        })
+    ));
+}
+
+const BOUNDARY: &str = r"
+
+def setup(ctx):
+    ctx.register_float('float', min=0, max=100, default=50)
+    pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn boundary_values_survive_reload() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(BOUNDARY, None).unwrap();
+
+    runner
+        .try_set_var(
+            "float",
+            Variant::Float(Cfg::new(100.0, 0.0, 100.0), Scale::Linear),
+        )
+        .unwrap();
+
+    runner.reload_script(BOUNDARY, None).unwrap();
+
+    let (_, float) = runner.iter_inputs().find(|(n, _)| *n == "float").unwrap();
+    let Variant::Float(float, _) = float else {
+        panic!("expected a float input");
+    };
+
+    assert_eq!(float.current, 100.0);
+}
+
+#[test]
+fn type_mismatch_names_input() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(BOUNDARY, None).unwrap();
+
+    let wrong = Variant::Bool(DiscreteCfg::new(true));
+    let Err(GolobulError::TypeMismatch {
+        name,
+        expected,
+        found,
+    }) = runner.try_set_var("float", wrong.clone())
+    else {
+        panic!("expected a type mismatch");
+    };
+
+    assert_eq!(name, "float");
+    assert_eq!(expected, "float");
+    assert_eq!(found, "bool");
+
+    // try_set_vars wraps it, but it's still named
+    let res = runner.try_set_vars(&[("float".to_owned(), wrong)]);
+    let Err(GolobulError::InvalidVar { source, .. }) = res else {
+        panic!("expected an invalid var");
+    };
+    assert!(matches!(*source, GolobulError::TypeMismatch { ref name, .. } if name == "float"));
+}
+
+const STEPPED: &str = r"
+
+def setup(ctx):
+    ctx.register_int('tile', min=0, max=64, default=16, step=8)
+    pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn int_step_snaps() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(STEPPED, None).unwrap();
+
+    runner
+        .try_set_var("tile", Variant::Int(Cfg::new(11, 0, 64)))
+        .unwrap();
+
+    let (_, tile) = runner.iter_inputs().find(|(n, _)| *n == "tile").unwrap();
+    let Variant::Int(tile) = tile else {
+        panic!("expected an int input");
+    };
+
+    assert_eq!(tile.step, Some(8));
+    assert_eq!(tile.current, 8);
+}
+
+#[test]
+fn steps_count_from_min() {
+    let int = Cfg {
+        step: Some(4),
+        ..Cfg::new(1, 1, 10)
+    };
+
+    assert_eq!(int.snap(6), 5);
+    assert_eq!(int.snap(7), 9);
+    // 13 would be past max
+    assert_eq!(int.snap(12), 9);
+    assert_eq!(int.snap(i32::MAX), 9);
+    assert_eq!(int.snap(i32::MIN), 1);
+
+    let float = Cfg {
+        step: Some(0.5),
+        ..Cfg::new(0.25, 0.25, 2.0)
+    };
+
+    assert_eq!(float.snap(0.6), 0.75);
+    assert_eq!(float.snap(2.0), 1.75);
+    assert_eq!(float.snap(f32::MAX), 1.75);
+}
+
+const LOG_FLOAT: &str = r"
+
+def setup(ctx):
+    ctx.register_float('exposure', min=0.01, max=100, default=1, scale='log')
+    pass
+
+def run(ctx):
+    assert ctx.get_input('exposure') == 1.0
+
+";
+
+const BAD_LOG_FLOAT: &str = r"
+
+def setup(ctx):
+    ctx.register_float('exposure', min=0, max=100, default=1, scale='log')
+    pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn log_float() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(LOG_FLOAT, None).unwrap();
+
+    let is_log = |runner: &PythonRunner| {
+        matches!(
+            runner.iter_inputs().find(|(n, _)| *n == "exposure"),
+            Some((_, Variant::Float(_, Scale::Logarithmic)))
+        )
+    };
+
+    assert!(is_log(&runner));
+
+    let mut data = vec![0u8; 4 * 4 * 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 4,
+        data: &mut data,
+        height: 4,
+        stride: None,
+    };
+    runner.create_render_pass(o).submit().unwrap();
+
+    assert!(is_log(&runner));
+
+    assert!(runner.load_script(BAD_LOG_FLOAT, None).is_err());
+}
+
+const ANGLE: &str = r"
+
+def setup(ctx):
+    ctx.register_angle('rotation', default=-90)
+    pass
+
+def run(ctx):
+    assert ctx.get_input('rotation') == 270.0
+
+";
+
+#[test]
+fn angle_wraps() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(ANGLE, None).unwrap();
+
+    let mut data = vec![0u8; 4 * 4 * 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 4,
+        data: &mut data,
+        height: 4,
+        stride: None,
+    };
+    runner.create_render_pass(o).submit().unwrap();
+}
+
+const CHOICES: &str = r"
+
+def setup(ctx):
+    ctx.register_enum('quality', 2, {'low': 1, 'high': 3, 'medium': 2})
+    ctx.register_choice('filter', ['nearest', 'bilinear', 'bicubic'], default='bilinear')
+    pass
+
+def run(ctx):
+    assert ctx.get_input('quality') == 2
+    assert ctx.get_input('filter') == 'bilinear'
+
+";
+
+#[test]
+fn enum_labels() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(CHOICES, None).unwrap();
+
+    let (_, quality) = runner.iter_inputs().find(|(n, _)| *n == "quality").unwrap();
+    let Variant::TaggedInt(quality) = quality else {
+        panic!("expected an enum input");
+    };
+
+    // options keep the order they were registered in
+    let labels: Vec<_> = quality.tags.keys().map(|k| k.as_str()).collect();
+    assert_eq!(labels, ["low", "high", "medium"]);
+
+    let mut data = vec![0u8; 4 * 4 * 4];
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        width: 4,
+        data: &mut data,
+        height: 4,
+        stride: None,
+    };
+    runner.create_render_pass(o).submit().unwrap();
+}
+
+const TEN_OPTIONS: &str = r"
+
+def setup(ctx):
+    ctx.register_choice('letter', list('abcdefghij'), default='j')
+    pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn enum_keeps_every_option() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(TEN_OPTIONS, None).unwrap();
+
+    let (_, letter) = runner.iter_inputs().find(|(n, _)| *n == "letter").unwrap();
+    let Variant::TaggedInt(letter) = letter else {
+        panic!("expected an enum input");
+    };
+
+    let mut letter = letter.clone();
+    assert_eq!(letter.tags.len(), 10);
+    assert_eq!(letter.position(), Some(9));
+
+    letter.select(2);
+    assert_eq!(letter.label(), Some("c"));
+
+    // out of range positions leave the selection alone
+    letter.select(10);
+    assert_eq!(letter.label(), Some("c"));
+}
+
+#[test]
+fn set_enum_by_value() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(CHOICES, None).unwrap();
+
+    let (_, quality) = runner.iter_inputs().find(|(n, _)| *n == "quality").unwrap();
+    let Variant::TaggedInt(mut quality) = quality.clone() else {
+        panic!("expected an enum input");
+    };
+
+    quality.value = 3;
+    let high = Variant::TaggedInt(quality.clone());
+    runner.try_set_var("quality", high.clone()).unwrap();
+
+    let (_, set) = runner.iter_inputs().find(|(n, _)| *n == "quality").unwrap();
+    assert_eq!(*set, high);
+
+    // 7 isn't one of the options, so nothing changes
+    quality.value = 7;
+    let res = runner.try_set_var("quality", Variant::TaggedInt(quality));
+    assert!(matches!(res, Err(GolobulError::InvalidValue(7))));
+
+    let (_, set) = runner.iter_inputs().find(|(n, _)| *n == "quality").unwrap();
+    assert_eq!(*set, high);
+}
+
+#[test]
+fn set_vars_all_or_nothing() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(CHOICES, None).unwrap();
+
+    let enum_input = |runner: &PythonRunner, name: &str| {
+        let (_, var) = runner.iter_inputs().find(|(n, _)| *n == name).unwrap();
+        let Variant::TaggedInt(var) = var.clone() else {
+            panic!("expected an enum input");
+        };
+        var
+    };
+
+    let before_quality = enum_input(&runner, "quality");
+    let before_filter = enum_input(&runner, "filter");
+
+    let mut filter = before_filter.clone();
+    filter.select(2);
+    let mut quality = before_quality.clone();
+    quality.value = 7;
+
+    let values = [
+        ("filter".to_owned(), Variant::TaggedInt(filter.clone())),
+        ("quality".to_owned(), Variant::TaggedInt(quality.clone())),
+    ];
+
+    let res = runner.try_set_vars(&values);
+    assert!(matches!(res, Err(GolobulError::InvalidVar { ref name, .. }) if name == "quality"));
+
+    // the valid filter wasn't applied either
+    assert_eq!(enum_input(&runner, "filter"), before_filter);
+    assert_eq!(enum_input(&runner, "quality"), before_quality);
+
+    quality.value = 3;
+    let values = [
+        ("filter".to_owned(), Variant::TaggedInt(filter.clone())),
+        ("quality".to_owned(), Variant::TaggedInt(quality)),
+    ];
+
+    runner.try_set_vars(&values).unwrap();
+    assert_eq!(enum_input(&runner, "filter").label(), Some("bicubic"));
+    assert_eq!(enum_input(&runner, "quality").value, 3);
+}
+
+#[test]
+fn input_specs() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(CHOICES, None).unwrap();
+
+    let specs = runner.input_specs();
+    let names: Vec<_> = specs.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["quality", "filter"]);
+
+    let options = vec![
+        ("low".to_owned(), 1),
+        ("high".to_owned(), 3),
+        ("medium".to_owned(), 2),
+    ];
+    assert_eq!(specs[0].kind, InputKind::Enum { default: 2, options });
+}
+
+const DESCRIBED: &str = r"
+
+def setup(ctx):
+    ctx.register_float('gain', default=1.0, description='multiplies the input')
+    ctx.register_bool('plain')
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn descriptions_survive_reload() {
+    let mut runner = PythonRunner::default();
+
+    for _ in 0..2 {
+        runner.load_script(DESCRIBED, None).unwrap();
+
+        let (_, gain) = runner.iter_inputs().find(|(n, _)| *n == "gain").unwrap();
+        assert_eq!(gain.description(), Some("multiplies the input"));
+
+        let (_, plain) = runner.iter_inputs().find(|(n, _)| *n == "plain").unwrap();
+        assert_eq!(plain.description(), None);
+    }
+}
+
+const VECTOR: &str = r"
+
+def setup(ctx):
+    ctx.register_vector('point', min=[0, 0], max=[100, 100], default=[0, 0])
+    pass
+
+def run(ctx):
+    pass
+
+";
+
+#[test]
+fn vector_clamped_per_component() {
+    let mut runner = PythonRunner::default();
+
+    runner.load_script(VECTOR, None).unwrap();
+
+    runner
+        .try_set_var(
+            "point",
+            Variant::Vector2(Cfg::new([50.0, 200.0], [0.0, 0.0], [100.0, 100.0])),
+        )
+        .unwrap();
+
+    runner.reload_script(VECTOR, None).unwrap();
+
+    let (_, point) = runner.iter_inputs().find(|(n, _)| *n == "point").unwrap();
+    let Variant::Vector2(point) = point else {
+        panic!("expected a vector input");
+    };
+
+    assert_eq!(point.current, [50.0, 100.0]);
+}
+const SIZE_CONFIG: &str = r"
+
+def setup(ctx):
+    ctx.set_output_size(20, 20)
+    pass
+
+def run(ctx):
+    assert ctx.output().shape == (20, 20, 4)
+    pass
+
+";
+
+#[test]
+fn size_config() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(SIZE_CONFIG, None).unwrap();
+
+    assert_eq!(
+        runner.requested_output_resize(),
+        Some(OutputSize {
+            width: 20,
+            height: 20
+        })
+    );
+
+    let mut wrong_out = [0u8; 40 * 40 * 4];
+
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &mut wrong_out,
+        width: 40,
+        height: 40,
+        stride: None,
+    };
+
+    let pass = runner.create_render_pass(o);
+    let report = pass.submit().unwrap();
+
+    // the script only sees the middle of the oversized buffer
+    assert_eq!(
+        report.written_size,
+        OutputSize {
+            width: 20,
+            height: 20
+        }
+    );
+    assert!(report.wrote_output);
+    assert_eq!(report.stdout, None);
+
+    let mut right_out = [0u8; 20 * 20 * 4];
+
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &mut right_out,
+        width: 20,
+        height: 20,
+        stride: None,
+    };
+
+    let pass = runner.create_render_pass(o);
+    pass.submit().unwrap();
+}
+
+const STRIDED_CROP: &str = r"
+
+def setup(ctx):
+    ctx.set_output_size(4, 6)
+
+def run(ctx):
+    output = ctx.output()
+    assert output.shape == (4, 6, 4)
+    output.fill(255)
+
+";
+
+#[test]
+fn strided_center_crop() {
+    let mut runner = PythonRunner::default();
+    runner.load_script(STRIDED_CROP, None).unwrap();
+
+    // 10x8 pixels with 8 bytes of padding on every row
+    let (width, height, stride) = (10, 8, 48);
+    let mut output = vec![0u8; stride * height];
+
+    let o = OutDesc {
+        fmt: ImageFormat::Rgba8,
+        data: &mut output,
+        width: width as u32,
+        height: height as u32,
+        stride: Some(stride as u32),
+    };
+
+    let pass = runner.create_render_pass(o);
+    pass.submit().unwrap();
+
+    for (i, byte) in output.iter().enumerate() {
+        let (y, x) = (i / stride, (i % stride) / 4);
+        let inside = (2..6).contains(&y) && (2..8).contains(&x);
+        assert_eq!(*byte, if inside { 255 } else { 0 }, "row {y}, column {x}");
+    }
 }
 
 const GRAYSCALE: &str = r"
@@ -1,35 +1,214 @@
 use egui::mutex::RwLock;
-use golob_lib::{GolobulError, PythonRunner};
-use image::imageops::FilterType::Triangle;
+use golob_lib::{CancellationToken, GolobulError, Progress, PythonRunner};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::FilterType;
 use notify::{RecursiveMode, Watcher};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{mpsc::Sender, Arc};
 
+// The UI repaints roughly every 16ms, so animated scripts see ~60 fps.
+pub const PLAYGROUND_FPS: f32 = 60.0;
+
+// How many renders the HUD averages over.
+const RENDER_HISTORY: usize = 30;
+
+// Saves closer together than this trigger a single reload.
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(250);
+
+// Setups that run longer than this are given up on, the UI can't respond while
+// they run on the main thread. Slow one time work belongs in `prime`.
+pub const SETUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Timings for the HUD, written by the render thread after every render.
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    // seconds, oldest first
+    times: std::collections::VecDeque<f32>,
+    // everything the render thread keeps around for the current size
+    pub buffer_bytes: usize,
+}
+
+impl RenderStats {
+    fn record(&mut self, secs: f32) {
+        if self.times.len() == RENDER_HISTORY {
+            self.times.pop_front();
+        }
+        self.times.push_back(secs);
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.times.back().copied()
+    }
+
+    pub fn average(&self) -> Option<f32> {
+        (!self.times.is_empty()).then(|| self.times.iter().sum::<f32>() / self.times.len() as f32)
+    }
+
+    pub fn samples(&self) -> usize {
+        self.times.len()
+    }
+}
+
+// Every resize the playground does itself (screenshots, the clipboard,
+// sequence and gif exports) follows the filter picked under Options.
+// Linear is the default and maps to a triangle filter.
+fn resize_filter(mode: egui::TextureFilter) -> FilterType {
+    match mode {
+        egui::TextureFilter::Nearest => FilterType::Nearest,
+        egui::TextureFilter::Linear => FilterType::Triangle,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum RunnerStatus {
     InitFailed,
     Busy,
     RunFailed,
-    NeedsReload(PathBuf),
+    // the main thread is running setup
+    Loading(PathBuf),
     Normal { width: usize, height: usize },
 }
 
+/// A script for the main thread to load, see `RunnerState::pending_load`.
+#[derive(Debug, Clone)]
+pub struct LoadRequest {
+    pub path: PathBuf,
+    // rerun setup even if the script hasn't changed
+    pub force: bool,
+    // freshly opened scripts get their saved inputs back, reloads keep the current ones
+    pub restore_inputs: bool,
+}
+
+impl LoadRequest {
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            path,
+            force: false,
+            restore_inputs: true,
+        }
+    }
+
+    pub fn reload(path: PathBuf, force: bool) -> Self {
+        Self {
+            path,
+            force,
+            restore_inputs: false,
+        }
+    }
+}
+
 // This is super disorganized, do this in a
 // more principled way when you get a chance
 pub struct BgThreadState {
     pub runner: PythonRunner,
     pub dimensions: (usize, usize),
     pub watcher: notify::RecommendedWatcher,
+    // everything `watcher` is watching for the current script
+    pub watched: Vec<PathBuf>,
     pub image_inputs: HashMap<String, crate::ImageDesc>,
+    // decoded alongside `image_inputs` so the UI never touches the disk
+    pub previews: crate::thumbnails::Previews,
     pub staging_buffer: Vec<u8>,
+    // bumped whenever staging_buffer or output_buffer is reallocated
+    pub buffer_generation: u64,
     pub current_path: Option<PathBuf>,
     pub filter_mode: egui::TextureFilter,
+    pub cancel_token: CancellationToken,
+    // handed to the runner to interrupt setup
+    pub load_token: CancellationToken,
+    // seconds, set by the timeline
+    pub time: f32,
+    pub fps: f32,
+    // a copy of the last finished render for the UI to read from
+    pub last_frame: Arc<RwLock<crate::ImageDesc>>,
+    // the finished render before that, for the diff view
+    pub previous_frame: crate::ImageDesc,
+    // when set the difference from the previous frame is shown, scaled by this
+    pub diff_gain: Option<f32>,
+    pub diff_buffer: Vec<u8>,
+    // a named output to display instead of the primary one
+    pub shown_output: Option<String>,
+    pub output_buffer: Vec<u8>,
+    pub render_stats: Arc<RwLock<RenderStats>>,
+    // from `ctx.set_name`, for the window title
+    pub script_name: Arc<RwLock<Option<String>>>,
+}
+
+// Folders that never hold the user's modules, venvs especially are
+// far too big to watch.
+fn skip_watching(dir: &Path) -> bool {
+    let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    name.starts_with('.')
+        || matches!(name, "__pycache__" | "node_modules" | "site-packages" | "venv")
+        || dir.join("pyvenv.cfg").exists()
+}
+
+// How far below the script's folder local modules are looked for, and how
+// many folders are watched at most, a script saved somewhere like the
+// downloads folder shouldn't have us watching thousands of them.
+const WATCH_DEPTH: usize = 4;
+const MAX_WATCHED: usize = 256;
+
+// The script's folder and the folders below it, skipping venvs and the like at
+// every level. Only the folder itself if it's the home folder or a root.
+fn watched_dirs(dir: &Path) -> Vec<PathBuf> {
+    let is_home = homedir::get_my_home().ok().flatten().is_some_and(|home| home == dir);
+    if is_home || dir.parent().is_none() {
+        return vec![dir.to_owned()];
+    }
+
+    let mut dirs = vec![dir.to_owned()];
+    let mut level = vec![dir.to_owned()];
+
+    for depth in 0..WATCH_DEPTH {
+        level = level
+            .iter()
+            .flat_map(|parent| std::fs::read_dir(parent).into_iter().flatten().flatten())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && !skip_watching(path))
+            .collect();
+
+        if dirs.len() + level.len() > MAX_WATCHED {
+            log::warn!("too many folders under {dir:?}, only watching {depth} deep");
+            break;
+        }
+
+        dirs.extend(level.iter().cloned());
+    }
+
+    dirs
 }
 
 impl BgThreadState {
-    pub fn load_script(&mut self, path: &PathBuf) -> Result<Option<String>, GolobulError> {
+    // Watches the script's folder and the folders below it,
+    // so edits to local modules reload the script too.
+    fn watch_script_dir(&mut self, script: &Path) {
+        for old in self.watched.drain(..) {
+            let _ = self.watcher.unwatch(&old);
+        }
+
+        let Some(dir) = script.parent() else {
+            return;
+        };
+
+        // each one non recursively, a recursive watch would take in the skipped folders
+        for path in watched_dirs(dir) {
+            match self.watcher.watch(&path, RecursiveMode::NonRecursive) {
+                Ok(()) => self.watched.push(path),
+                Err(e) => log::warn!("not watching {path:?}: {e}"),
+            }
+        }
+    }
+
+    pub fn load_script(
+        &mut self,
+        path: &PathBuf,
+        force: bool,
+    ) -> Result<Option<String>, GolobulError> {
         log::info!("loading script {path:?}");
+        self.load_token.reset();
 
         let contents = match std::fs::read_to_string(&path) {
             Ok(c) => c,
@@ -39,14 +218,7 @@ impl BgThreadState {
             }
         };
 
-        if let Some(old) = self.current_path.take() {
-            self.watcher.unwatch(&old).unwrap();
-        }
-
-        self.watcher
-            .watch(&path, RecursiveMode::NonRecursive)
-            .unwrap();
-
+        self.watch_script_dir(path);
         self.current_path = Some(path.clone());
 
         if let Err(e) = self.runner.clear_script_parent_directory() {
@@ -57,26 +229,269 @@ impl BgThreadState {
             self.runner.set_script_parent_directory(parent.to_owned());
         }
 
-        let out = self
-            .runner
-            .load_script(contents, path.to_str().map(|s| s.to_owned()));
+        let file_name = path.to_str().map(|s| s.to_owned());
+        let out = if force {
+            self.runner.reload_script(contents, file_name)
+        } else {
+            self.runner.load_script(contents, file_name)
+        };
 
         log_run(&out);
+        self.publish_name();
 
         out
     }
-    pub fn render(
+
+    // failed loads leave the runner without a name, so this clears it too
+    fn publish_name(&self) {
+        *self.script_name.write() = self.runner.name().map(str::to_owned);
+    }
+
+    /// Decodes an image from disk into the named input, returns false if it couldn't be read.
+    pub fn load_image(&mut self, var: String, path: &Path) -> bool {
+        let image = match image::open(path) {
+            Ok(image) => image,
+            Err(e) => {
+                log::error!("failed to open {path:?}: {e}");
+                return false;
+            }
+        };
+
+        let preview = crate::thumbnails::Preview::new(path, &image);
+        let image = crate::ImageDesc::from_image(image);
+
+        let [im_width, im_height] = [image.width, image.height];
+        log::info!(
+            "loading image {path:?} with dimensions width : {im_width} height: {im_height} as {:?}",
+            image.fmt
+        );
+
+        self.previews.write().insert(var.clone(), preview);
+        self.image_inputs.insert(var, image);
+
+        true
+    }
+
+    pub fn save_state(&self, images: &HashMap<String, PathBuf>) {
+        let Some(path) = self.current_path.as_ref() else {
+            return;
+        };
+
+        let state = crate::persist::SavedState::capture(&self.runner, images);
+        match state.save(path) {
+            Ok(()) => log::info!("saved inputs to {:?}", crate::persist::sidecar_path(path)),
+            Err(e) => log::error!("failed to save inputs: {e:?}"),
+        }
+    }
+
+    /// Restores values saved for the current script, recording the images
+    /// that were reloaded in `images`.
+    pub fn load_state(&mut self, images: &std::sync::RwLock<HashMap<String, PathBuf>>) {
+        let Some(path) = self.current_path.as_ref() else {
+            return;
+        };
+
+        let Some(state) = crate::persist::SavedState::load(path) else {
+            return;
+        };
+
+        state.restore(&mut self.runner);
+
+        for (var, image_path) in state.images {
+            let is_image = self
+                .runner
+                .iter_inputs()
+                .any(|(name, v)| *name == var && matches!(v, golob_lib::Variant::Image(..)));
+
+            if is_image && self.load_image(var.clone(), &image_path) {
+                images.write().unwrap().insert(var, image_path);
+            }
+        }
+    }
+
+    /// Loads the images an inputs manifest names, recording them in `images`.
+    /// Names the script doesn't have as image inputs are skipped with a warning,
+    /// returns false if nothing was loaded.
+    pub fn load_manifest(
+        &mut self,
+        manifest: &Path,
+        images: &std::sync::RwLock<HashMap<String, PathBuf>>,
+    ) -> bool {
+        let entries = match crate::manifest::load(manifest) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::error!("failed to read manifest {manifest:?}: {e}");
+                return false;
+            }
+        };
+
+        let image_inputs: Vec<String> = self
+            .runner
+            .iter_inputs()
+            .filter(|(_, v)| {
+                matches!(v, golob_lib::Variant::Image(d, _) if d.current == golob_lib::Image::Input)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in image_inputs.iter() {
+            if !entries.iter().any(|(n, _)| n == name) {
+                log::warn!("{manifest:?} has no image for the input {name}");
+            }
+        }
+
+        let mut loaded = false;
+        for (var, image_path) in entries {
+            if !image_inputs.contains(&var) {
+                log::warn!(
+                    "{manifest:?} names {var}, but the script has no image input by that name"
+                );
+                continue;
+            }
+
+            if self.load_image(var.clone(), &image_path) {
+                images.write().unwrap().insert(var, image_path);
+                loaded = true;
+            }
+        }
+
+        loaded
+    }
+
+    /// Scripts that call `set_output_size` still get the size they asked for.
+    pub fn resize_output(&mut self, width: u32, height: u32, target: &mut egui::TextureHandle) {
+        if width == 0 || height == 0 {
+            log::warn!("ignoring empty output size {width}x{height}");
+            return;
+        }
+
+        log::info!("resizing output to {width}x{height}");
+        self.dimensions = (height as usize, width as usize);
+        self.staging_buffer = vec![0; width as usize * height as usize * 4];
+        self.buffer_generation += 1;
+
+        // blank the texture, if the render fails the old frame would
+        // otherwise be stretched into the new size
+        let mut frame = self.last_frame.write();
+        frame.data.clone_from(&self.staging_buffer);
+        frame.width = width;
+        frame.height = height;
+        drop(frame);
+
+        self.previous_frame.data.clear();
+        self.previous_frame.width = 0;
+        self.previous_frame.height = 0;
+
+        self.present(target);
+    }
+
+    /// The last render, optionally resized to `(width, height)` with the current filter.
+    pub fn snapshot(&self, size: Option<(u32, u32)>) -> Option<image::RgbaImage> {
+        let (height, width) = self.dimensions;
+        let image =
+            image::RgbaImage::from_raw(width as u32, height as u32, self.staging_buffer.clone())?;
+
+        let Some((width, height)) = size else {
+            return Some(image);
+        };
+
+        let filter = resize_filter(self.filter_mode);
+        Some(image::imageops::resize(&image, width, height, filter))
+    }
+
+    pub fn render(&mut self, target: egui::TextureHandle, status: Arc<RwLock<RunnerStatus>>) {
+        let _ = self.try_render(target, status);
+    }
+
+    /// Puts the last frame on screen, or its difference from the one before it.
+    pub fn present(&mut self, target: &mut egui::TextureHandle) {
+        let last_frame = self.last_frame.clone();
+        let current = last_frame.read();
+        let size = [current.width as usize, current.height as usize];
+
+        let previous = &self.previous_frame;
+        let same_size = previous.width == current.width && previous.height == current.height;
+
+        let data = match self.diff_gain {
+            Some(gain) if same_size => {
+                self.diff_buffer.clear();
+                self.diff_buffer.extend(
+                    current
+                        .data
+                        .chunks_exact(4)
+                        .zip(previous.data.chunks_exact(4))
+                        .map(|(a, b)| {
+                            let diff = |c: usize| (a[c].abs_diff(b[c]) as f32 * gain).min(255.0);
+                            // alpha changes show up in every channel
+                            let alpha = diff(3);
+                            [diff(0), diff(1), diff(2)].map(|d| d.max(alpha) as u8)
+                        })
+                        .flat_map(|[r, g, b]| [r, g, b, 255]),
+                );
+                &self.diff_buffer
+            }
+            _ => &current.data,
+        };
+
+        if data.is_empty() || data.len() != size[0] * size[1] * 4 {
+            return;
+        }
+
+        target.set(
+            egui::ColorImage::from_rgba_unmultiplied(size, data),
+            egui::TextureOptions {
+                magnification: self.filter_mode,
+                minification: self.filter_mode,
+                wrap_mode: egui::TextureWrapMode::ClampToEdge,
+            },
+        );
+    }
+
+    // like `render`, but hands back what went wrong
+    pub fn try_render(
         &mut self,
-        time: f32,
         mut target: egui::TextureHandle,
         status: Arc<RwLock<RunnerStatus>>,
-    ) {
+    ) -> Result<(), GolobulError> {
         let start = std::time::Instant::now();
+        let time = self.time;
         *status.write() = RunnerStatus::Busy;
+
+        {
+            let last = self.last_frame.read();
+            self.previous_frame.data.clone_from(&last.data);
+            self.previous_frame.width = last.width;
+            self.previous_frame.height = last.height;
+        }
         self.runner.set_time(time);
+        self.runner.set_frame((time * self.fps).round() as u32);
+        self.runner.set_fps(self.fps);
+        // there is no layer, the script is the closest thing. comp_size
+        // falls back to the output size on its own.
+        let script_name = self
+            .current_path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned());
+        self.runner.set_layer_name(script_name);
 
         self.staging_buffer.fill(0);
 
+        // the script may have dropped the output since it was picked
+        let shown_output = self
+            .shown_output
+            .clone()
+            .filter(|name| self.runner.outputs().contains(name));
+
+        if shown_output.is_some() {
+            let capacity = self.output_buffer.capacity();
+            self.output_buffer.clear();
+            self.output_buffer.resize(self.staging_buffer.len(), 0);
+            if self.output_buffer.capacity() != capacity {
+                self.buffer_generation += 1;
+            }
+        }
+
         let o = golob_lib::OutDesc {
             fmt: golob_lib::ImageFormat::Rgba8,
             data: &mut self.staging_buffer,
@@ -85,20 +500,48 @@ impl BgThreadState {
             stride: None,
         };
 
+        self.cancel_token.reset();
+        let window = self.runner.temporal_window() as i32;
         let mut pass = self.runner.create_render_pass(o);
+        pass.set_cancellation_token(self.cancel_token.clone());
+        pass.set_output_generation(self.buffer_generation);
 
-        for (name, image) in self.image_inputs.iter() {
-            let i = golob_lib::InDesc {
+        if let Some(name) = shown_output.as_ref() {
+            let o = golob_lib::OutDesc {
                 fmt: golob_lib::ImageFormat::Rgba8,
+                data: &mut self.output_buffer,
+                height: self.dimensions.0 as u32,
+                width: self.dimensions.1 as u32,
+                stride: None,
+            };
+            pass.load_output(o, name);
+        }
+
+        for (name, image) in self.image_inputs.iter() {
+            let desc = || golob_lib::InDesc {
+                fmt: image.fmt,
                 data: &image.data,
                 width: image.width,
                 height: image.height,
                 stride: None,
             };
-            pass.load_input(i, name);
+            pass.load_input(desc(), name);
+
+            // inputs are stills, so every neighbouring frame is the same one
+            for offset in (-window..=window).filter(|o| *o != 0) {
+                pass.load_input_at(desc(), name, offset);
+            }
         }
 
-        let out = pass.submit();
+        let report = pass.submit();
+        let wrote_output = report.as_ref().is_ok_and(|r| r.wrote_output);
+        let out = report.map(|r| r.stdout);
+
+        if let Err(GolobulError::Cancelled) = out {
+            // a fresh render was queued by whoever cancelled this one
+            log::info!("render cancelled");
+            return Err(GolobulError::Cancelled);
+        }
 
         if out.is_err() {
             *status.write() = RunnerStatus::RunFailed;
@@ -106,6 +549,10 @@ impl BgThreadState {
 
         log_run(&out);
 
+        if out.is_ok() && !wrote_output {
+            log::warn!("script produced no output, it never called ctx.output()");
+        }
+
         let dur = start.elapsed().as_secs_f32();
 
         log::info!("render took: {dur} secs",);
@@ -116,23 +563,37 @@ impl BgThreadState {
             log::debug!("Rerendering with exact buffer specified");
             let size = self.runner.requested_output_resize().unwrap();
             self.staging_buffer = vec![0; (size.width * size.height * 4) as usize];
+            self.buffer_generation += 1;
             self.dimensions.1 = size.width as usize;
             self.dimensions.0 = size.height as usize;
-            self.render(time, target, status);
+            self.try_render(target, status)
         } else {
-            let data = egui::ColorImage::from_rgba_unmultiplied(
-                [self.dimensions.1, self.dimensions.0],
-                &self.staging_buffer,
-            );
-
-            target.set(
-                data,
-                egui::TextureOptions {
-                    magnification: self.filter_mode,
-                    minification: self.filter_mode,
-                    wrap_mode: egui::TextureWrapMode::ClampToEdge,
-                },
-            );
+            let shown = if shown_output.is_some() {
+                &self.output_buffer
+            } else {
+                &self.staging_buffer
+            };
+
+            let mut frame = self.last_frame.write();
+            frame.data.clear();
+            frame.data.extend_from_slice(shown);
+            frame.width = self.dimensions.1 as u32;
+            frame.height = self.dimensions.0 as u32;
+            let frame_bytes = frame.data.len();
+            drop(frame);
+
+            let input_bytes: usize = self.image_inputs.values().map(|i| i.data.len()).sum();
+            let mut stats = self.render_stats.write();
+            stats.record(dur);
+            stats.buffer_bytes = self.staging_buffer.len()
+                + self.output_buffer.len()
+                + self.diff_buffer.len()
+                + self.previous_frame.data.len()
+                + frame_bytes
+                + input_bytes;
+            drop(stats);
+
+            self.present(&mut target);
 
             if out.is_err() {
                 *status.write() = RunnerStatus::RunFailed;
@@ -142,14 +603,181 @@ impl BgThreadState {
                     width: self.dimensions.1,
                 };
             }
+
+            out.map(|_| ())
+        }
+    }
+}
+
+// Renders one frame at a time and lets go of the lock in between,
+// so the UI keeps drawing while a long sequence renders.
+fn render_sequence(
+    state: &RwLock<BgThreadState>,
+    (start, end, fps): (f32, f32, f32),
+    size: Option<(u32, u32)>,
+    directory: &Path,
+    target: &egui::TextureHandle,
+    status: &Arc<RwLock<RunnerStatus>>,
+    progress: &RwLock<Option<(u32, u32)>>,
+) {
+    let frames = ((end - start) * fps).floor().max(0.0) as u32 + 1;
+    let pad = (frames - 1).to_string().len();
+    let (old_time, old_fps) = {
+        let bg = state.read();
+        (bg.time, bg.fps)
+    };
+
+    let mut written = 0;
+
+    for frame in 0..frames {
+        *progress.write() = Some((frame, frames));
+
+        let mut bg = state.write();
+        bg.time = start + frame as f32 / fps;
+        bg.fps = fps;
+
+        if let Err(e) = bg.try_render(target.clone(), status.clone()) {
+            log::error!("sequence stopped at frame {frame} ({:.3}s): {e}", bg.time);
+            break;
+        }
+
+        let path = directory.join(format!("{frame:0pad$}.png"));
+
+        let saved = match bg.snapshot(size) {
+            Some(image) => image.save(&path).map_err(|e| e.to_string()),
+            None => Err("output buffer does not match the output size".to_owned()),
+        };
+
+        if let Err(e) = saved {
+            log::error!("sequence stopped, could not write {path:?}: {e}");
+            break;
+        }
+
+        written += 1;
+    }
+
+    *progress.write() = None;
+
+    let mut bg = state.write();
+    bg.time = old_time;
+    bg.fps = old_fps;
+
+    log::info!("wrote {written} of {frames} frames to {directory:?}");
+}
+
+// GIF palettes have one fully transparent entry and nothing in between,
+// so mostly transparent pixels become that entry and the rest turn opaque.
+fn flatten_alpha(frame: &mut image::RgbaImage) {
+    for pixel in frame.pixels_mut() {
+        if pixel[3] < 128 {
+            *pixel = image::Rgba([0, 0, 0, 0]);
+        } else {
+            pixel[3] = 255;
+        }
+    }
+}
+
+// Like `render_sequence`, but `frames` are spread evenly over start..end
+// with the end left out, so the gif loops without a repeated frame.
+fn render_gif(
+    state: &RwLock<BgThreadState>,
+    (start, end): (f32, f32),
+    (frames, delay_ms): (u32, u32),
+    size: Option<(u32, u32)>,
+    file: &Path,
+    target: &egui::TextureHandle,
+    status: &Arc<RwLock<RunnerStatus>>,
+    progress: &RwLock<Option<(u32, u32)>>,
+) {
+    let writer = match std::fs::File::create(file) {
+        Ok(f) => std::io::BufWriter::new(f),
+        Err(e) => {
+            log::error!("could not create {file:?}: {e}");
+            return;
+        }
+    };
+
+    // quantization speed, 1 is the slowest and best, 30 the fastest
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    if let Err(e) = encoder.set_repeat(Repeat::Infinite) {
+        log::error!("could not write {file:?}: {e}");
+        return;
+    }
+
+    let frames = frames.max(1);
+    let step = (end - start) / frames as f32;
+    let delay = image::Delay::from_numer_denom_ms(delay_ms, 1);
+    let (old_time, old_fps) = {
+        let bg = state.read();
+        (bg.time, bg.fps)
+    };
+
+    let mut written = 0;
+
+    for frame in 0..frames {
+        *progress.write() = Some((frame, frames));
+
+        let mut bg = state.write();
+        bg.time = start + frame as f32 * step;
+        bg.fps = 1.0 / step;
+
+        if let Err(e) = bg.try_render(target.clone(), status.clone()) {
+            log::error!("gif stopped at frame {frame} ({:.3}s): {e}", bg.time);
+            break;
+        }
+
+        let snapshot = bg.snapshot(size);
+
+        // quantizing is slow, let the UI have the state back meanwhile
+        drop(bg);
+
+        let Some(mut pixels) = snapshot else {
+            log::error!("gif stopped, output buffer does not match the output size");
+            break;
+        };
+
+        flatten_alpha(&mut pixels);
+
+        if let Err(e) = encoder.encode_frame(image::Frame::from_parts(pixels, 0, 0, delay)) {
+            log::error!("gif stopped, could not write {file:?}: {e}");
+            break;
         }
+
+        written += 1;
     }
+
+    // writes the trailer
+    drop(encoder);
+
+    *progress.write() = None;
+
+    let mut bg = state.write();
+    bg.time = old_time;
+    bg.fps = old_fps;
+
+    log::info!("wrote {written} of {frames} frames to {file:?}");
 }
 
 pub struct RunnerState {
     pub runner: Arc<RwLock<BgThreadState>>,
     pub status: Arc<RwLock<RunnerStatus>>,
     pub sender: Sender<crate::AppMessage>,
+    // shared with the render thread so an in flight render can be cancelled
+    // without waiting on the runner lock.
+    pub cancel_token: CancellationToken,
+    // scripts are loaded by the main thread, some python packages assume
+    // thats where they are loaded. Written here, taken by the UI.
+    pub pending_load: Arc<RwLock<Option<LoadRequest>>>,
+    // likewise, lets the UI poll script progress mid render
+    pub progress: Progress,
+    pub last_frame: Arc<RwLock<crate::ImageDesc>>,
+    // image inputs as the UI shows them
+    pub previews: crate::thumbnails::Previews,
+    // frames done and total while a sequence renders
+    pub sequence_progress: Arc<RwLock<Option<(u32, u32)>>>,
+    // read by the HUD without waiting on the runner lock
+    pub render_stats: Arc<RwLock<RenderStats>>,
+    pub script_name: Arc<RwLock<Option<String>>>,
 }
 
 pub fn spawn_render_thread(mut target: egui::TextureHandle) -> RunnerState {
@@ -160,16 +788,42 @@ pub fn spawn_render_thread(mut target: egui::TextureHandle) -> RunnerState {
 
     let status = status_th.clone();
 
-    let runner = golob_lib::PythonRunner::default();
+    let mut runner = golob_lib::PythonRunner::default();
+    let progress = runner.progress_handle();
+
+    let load_token = CancellationToken::new();
+    runner.set_setup_cancellation_token(Some(load_token.clone()));
+    runner.set_setup_timeout(Some(SETUP_TIMEOUT));
 
     let (sender, receiver) = std::sync::mpsc::channel();
 
-    let watcher_clone = sender.clone();
+    // waits for the edits to settle before reloading
+    let (changed, changes) = std::sync::mpsc::channel::<()>();
+    let reload_sender = sender.clone();
+    let (watch_status, watch_token) = (status_th.clone(), load_token.clone());
+    std::thread::spawn(move || {
+        while changes.recv().is_ok() {
+            while changes.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+
+            // the render thread may be queued up behind a hung setup, so it's cancelled here
+            if matches!(*watch_status.read(), RunnerStatus::Loading(_)) {
+                watch_token.cancel();
+            }
+
+            let _ = reload_sender.send(crate::AppMessage::ReloadScript { force: false });
+        }
+    });
+
     let watcher = notify::RecommendedWatcher::new(
         move |res: notify::Result<notify::Event>| {
             if let Ok(event) = res {
-                if event.kind.is_modify() {
-                    let _ = watcher_clone.send(crate::AppMessage::ReloadScript);
+                let is_python = event
+                    .paths
+                    .iter()
+                    .any(|p| p.extension().is_some_and(|ext| ext == "py"));
+
+                if is_python && (event.kind.is_modify() || event.kind.is_create()) {
+                    let _ = changed.send(());
                 }
             }
         },
@@ -178,154 +832,327 @@ pub fn spawn_render_thread(mut target: egui::TextureHandle) -> RunnerState {
     .unwrap();
 
     let (height, width) = (255, 255);
+    let cancel_token = CancellationToken::new();
+    let last_frame = Arc::new(RwLock::new(crate::ImageDesc {
+        data: vec![],
+        width: 0,
+        height: 0,
+        fmt: golob_lib::ImageFormat::Rgba8,
+    }));
+
+    let render_stats = Arc::new(RwLock::new(RenderStats::default()));
+    let script_name = Arc::new(RwLock::new(None));
+    let previews = crate::thumbnails::Previews::default();
 
     let thread_state = BgThreadState {
         watcher,
+        watched: vec![],
         runner,
         dimensions: (height, width),
         image_inputs: std::collections::HashMap::new(),
+        previews: previews.clone(),
         staging_buffer: vec![0u8; width * height * 4],
+        buffer_generation: 0,
         current_path: None,
         filter_mode: egui::TextureFilter::Linear,
+        cancel_token: cancel_token.clone(),
+        load_token: load_token.clone(),
+        time: 0.0,
+        fps: PLAYGROUND_FPS,
+        last_frame: last_frame.clone(),
+        previous_frame: crate::ImageDesc {
+            data: vec![],
+            width: 0,
+            height: 0,
+            fmt: golob_lib::ImageFormat::Rgba8,
+        },
+        diff_gain: None,
+        diff_buffer: vec![],
+        shown_output: None,
+        output_buffer: vec![],
+        render_stats: render_stats.clone(),
+        script_name: script_name.clone(),
+    };
+
+    let sequence_progress = Arc::new(RwLock::new(None));
+    let sequence_progress_th = sequence_progress.clone();
+
+    let pending_load = Arc::new(RwLock::new(None));
+    let pending_load_th = pending_load.clone();
+    let request_load = move |request| {
+        // a load stuck in setup won't finish, the watchdog interrupts it on
+        // the main thread and this one runs after
+        load_token.cancel();
+        *pending_load_th.write() = Some(request);
     };
 
     let thread_state = Arc::new(RwLock::new(thread_state));
     let return_runner = thread_state.clone();
 
     std::thread::spawn(move || {
-        let start = std::time::Instant::now();
+        let mut clipboard: Option<arboard::Clipboard> = None;
 
         while let Ok(msg) = receiver.recv() {
             match msg {
                 crate::AppMessage::LoadVenv { path } => {
+                    // accept the venv root as well as its site packages
+                    let path = if path.join("pyvenv.cfg").exists() {
+                        golob_lib::venv_site_packages(&path)
+                    } else {
+                        path
+                    };
+
                     log::info!("loading venv {path:?}");
                     thread_state.write().runner.set_venv_path(path);
+
+                    // imports that failed before may work now
+                    let path = thread_state.read().current_path.clone();
+                    if let Some(path) = path {
+                        request_load(LoadRequest::reload(path, false));
+                    }
                 }
-                crate::AppMessage::ChangeFilterMode { mode } => {
-                    thread_state.write().filter_mode = mode;
+                crate::AppMessage::UnloadVenv => {
+                    log::info!("unloading venv");
+                    let out = thread_state.write().runner.clear_venv_path();
+                    let path = thread_state.read().current_path.clone();
 
-                    let data = egui::ColorImage::from_rgba_unmultiplied(
-                        [width, height],
-                        &thread_state.write().staging_buffer,
-                    );
+                    if let Err(e) = out {
+                        log::error!("{e:?}");
+                    } else if let Some(path) = path {
+                        request_load(LoadRequest::reload(path, false));
+                    }
+                }
+                crate::AppMessage::ChangeFilterMode { mode } => {
+                    let mut state = thread_state.write();
+                    state.filter_mode = mode;
+                    state.present(&mut target);
+                }
+                crate::AppMessage::ShowOutput { name } => {
+                    thread_state.write().shown_output = name;
 
-                    target.set(
-                        data,
-                        egui::TextureOptions {
-                            magnification: mode,
-                            minification: mode,
-                            wrap_mode: egui::TextureWrapMode::ClampToEdge,
-                        },
-                    );
+                    thread_state.write().render(target.clone(), status_th.clone());
+                }
+                crate::AppMessage::SetDiff { gain } => {
+                    let mut state = thread_state.write();
+                    state.diff_gain = gain;
+                    state.present(&mut target);
+                }
+                crate::AppMessage::ResizeOutput { width, height } => {
+                    let mut state = thread_state.write();
+                    state.resize_output(width, height, &mut target);
+                    state.render(target.clone(), status_th.clone());
                 }
                 crate::AppMessage::UnloadImage { var } => {
                     thread_state.write().image_inputs.remove(&var);
+                    thread_state.read().previews.write().remove(&var);
 
-                    thread_state.write().render(
-                        start.elapsed().as_secs_f32(),
-                        target.clone(),
-                        status_th.clone(),
-                    );
+                    thread_state.write().render(target.clone(), status_th.clone());
+                }
+                crate::AppMessage::UnloadAllImages => {
+                    thread_state.write().image_inputs.clear();
+                    thread_state.read().previews.write().clear();
+
+                    thread_state.write().render(target.clone(), status_th.clone());
                 }
                 crate::AppMessage::LoadImage { var, path } => {
-                    let Ok(image) = image::open(&path) else {
+                    if !thread_state.write().load_image(var, &path) {
                         continue;
-                    };
-
-                    let image = image.to_rgba8();
-                    let [im_width, im_height] = [image.width(), image.height()];
-                    log::info!(
-                        "loading image {path:?} with dimensions width : {im_width} height: {im_height}"
-                    );
+                    }
 
-                    let image_buffer = image.into_raw();
+                    thread_state.write().render(target.clone(), status_th.clone());
+                }
+                crate::AppMessage::SaveState { images } => {
+                    thread_state.read().save_state(&images);
+                }
+                crate::AppMessage::LoadState { images } => {
+                    thread_state.write().load_state(&images);
 
-                    thread_state.write().image_inputs.insert(
-                        var,
-                        crate::ImageDesc {
-                            data: image_buffer,
-                            width: im_width,
-                            height: im_height,
-                        },
-                    );
+                    thread_state.write().render(target.clone(), status_th.clone());
+                }
+                crate::AppMessage::LoadManifest { path, images } => {
+                    if !thread_state.write().load_manifest(&path, &images) {
+                        continue;
+                    }
 
-                    thread_state.write().render(
-                        start.elapsed().as_secs_f32(),
-                        target.clone(),
-                        status_th.clone(),
-                    );
+                    thread_state.write().render(target.clone(), status_th.clone());
                 }
                 crate::AppMessage::LoadScript { path } => {
-                    log::info!("loading script {path:?}");
-                    *status_th.write() = RunnerStatus::NeedsReload(path);
+                    request_load(LoadRequest::open(path));
                 }
-                crate::AppMessage::ReloadScript => {
-                    let path = thread_state.read().current_path.clone();
+                crate::AppMessage::ReloadScript { force } => {
+                    // the runner stays locked while the main thread is in setup,
+                    // saving the script again should still restart a load that hangs
+                    let loading = match &*status_th.read() {
+                        RunnerStatus::Loading(path) => Some(path.clone()),
+                        _ => None,
+                    };
+
+                    let path = loading.or_else(|| thread_state.read().current_path.clone());
                     if let Some(path) = path {
-                        let contents = std::fs::read_to_string(path).unwrap();
-                        let out = thread_state.write().runner.load_script(contents, None);
-
-                        log_run(&out);
-
-                        if out.is_err() {
-                            *status_th.write() = RunnerStatus::InitFailed;
-                        } else {
-                            *status_th.write() = RunnerStatus::Normal { width, height };
-                            thread_state.write().render(
-                                start.elapsed().as_secs_f32(),
-                                target.clone(),
-                                status_th.clone(),
-                            );
-                        }
+                        request_load(LoadRequest::reload(path, force));
+                    }
+                }
+                crate::AppMessage::InstallRequirements => {
+                    let Some(path) = thread_state.read().current_path.clone() else {
+                        log::error!("load a script before installing its requirements");
+                        continue;
+                    };
+
+                    let requirements = path.with_file_name("requirements.txt");
+
+                    if !requirements.exists() {
+                        log::error!("no requirements.txt found next to {path:?}");
+                        continue;
+                    }
+
+                    log::info!("installing {requirements:?}");
+
+                    // pip can take a while, don't hold the lock the UI draws from.
+                    let runner = thread_state.read().runner.clone();
+                    let out = runner.ensure_requirements(&requirements);
+
+                    log_run(&out);
+
+                    if out.is_ok() {
+                        request_load(LoadRequest::reload(path, true));
+                    }
+                }
+                crate::AppMessage::ResetInputs => {
+                    let mut state = thread_state.write();
+                    for (_, val) in state.runner.iter_inputs_mut() {
+                        crate::inputs_panel::reset_to_default(val);
                     }
                 }
+                crate::AppMessage::SetTime(time) => {
+                    thread_state.write().time = time;
+                }
                 crate::AppMessage::Render => {
-                    thread_state.write().render(
-                        start.elapsed().as_secs_f32(),
-                        target.clone(),
-                        status_th.clone(),
-                    );
+                    thread_state.write().render(target.clone(), status_th.clone());
                 }
-                crate::AppMessage::ScreenShot { params } => {
-                    let home_dir = match homedir::get_my_home() {
-                        Ok(Some(home)) => home,
-                        _ => "/".into(),
+                crate::AppMessage::RenderSequence {
+                    start,
+                    end,
+                    fps,
+                    size,
+                } => {
+                    let cur = thread_state.read().current_path.clone();
+                    let mut dialog = rfd::FileDialog::new().set_title("Render sequence into");
+
+                    if let Some(parent) = cur.as_ref().and_then(|p| p.parent()) {
+                        dialog = dialog.set_directory(parent);
+                    }
+
+                    let Some(directory) = dialog.pick_folder() else {
+                        continue;
                     };
 
+                    render_sequence(
+                        &thread_state,
+                        (start, end, fps),
+                        size,
+                        &directory,
+                        &target,
+                        &status_th,
+                        &sequence_progress_th,
+                    );
+                }
+                crate::AppMessage::RenderGif {
+                    start,
+                    end,
+                    frames,
+                    delay_ms,
+                    size,
+                } => {
                     let cur = thread_state.read().current_path.clone();
-                    let home_dir = cur.as_ref().and_then(|p| p.parent()).unwrap_or(&home_dir);
+                    let mut dialog = rfd::FileDialog::new()
+                        .add_filter("GIF", &["gif"])
+                        .set_file_name("loop.gif");
 
-                    let Some(file) = rfd::FileDialog::new()
-                        .set_directory(home_dir)
-                        .set_file_name("screenshot.png")
-                        .save_file()
-                    else {
+                    if let Some(parent) = cur.as_ref().and_then(|p| p.parent()) {
+                        dialog = dialog.set_directory(parent);
+                    }
+
+                    let Some(file) = dialog.save_file() else {
                         continue;
                     };
 
-                    let mut file = file;
+                    render_gif(
+                        &thread_state,
+                        (start, end),
+                        (frames, delay_ms),
+                        size,
+                        &file.with_extension("gif"),
+                        &target,
+                        &status_th,
+                        &sequence_progress_th,
+                    );
+                }
+                crate::AppMessage::ScreenShot { size } => {
+                    // taken now, the output may have moved on by the time a file is picked
+                    let Some(image) = thread_state.read().snapshot(size) else {
+                        log::error!("nothing to save, the output buffer is the wrong size");
+                        continue;
+                    };
+
+                    let dir = thread_state
+                        .read()
+                        .current_path
+                        .as_ref()
+                        .and_then(|p| p.parent())
+                        .map(Path::to_owned);
 
-                    let mut image = image::RgbaImage::from_raw(
-                        width as u32,
-                        height as u32,
-                        thread_state.read().staging_buffer.clone(),
-                    )
-                    .unwrap();
+                    // the dialog is modal, rendering carries on without us
+                    std::thread::spawn(move || {
+                        let dir = dir.unwrap_or_else(|| match homedir::get_my_home() {
+                            Ok(Some(home)) => home,
+                            _ => "/".into(),
+                        });
 
-                    if let Some((width, height, filter)) = params {
-                        let filter = match filter {
-                            egui::TextureFilter::Nearest => image::imageops::FilterType::Nearest,
-                            egui::TextureFilter::Linear => Triangle,
+                        let Some(mut file) = rfd::FileDialog::new()
+                            .set_directory(dir)
+                            .set_file_name("screenshot.png")
+                            .save_file()
+                        else {
+                            return;
                         };
 
-                        image = image::imageops::resize(&image, width, height, filter);
-                    }
+                        if file.extension().is_none() {
+                            file.set_extension("png");
+                        }
+
+                        match image.save(&file) {
+                            Ok(()) => log::info!("saved screenshot to {file:?}"),
+                            Err(e) => log::error!("failed to save {file:?}: {e}"),
+                        }
+                    });
+                }
+                crate::AppMessage::CopyToClipboard { size } => {
+                    let Some(image) = thread_state.read().snapshot(size) else {
+                        log::error!("nothing to copy, the output buffer is the wrong size");
+                        continue;
+                    };
 
-                    if file.extension().is_none() {
-                        file.set_extension("png");
+                    // on linux the contents only last as long as the clipboard does
+                    if clipboard.is_none() {
+                        clipboard = arboard::Clipboard::new()
+                            .map_err(|e| log::error!("clipboard unavailable: {e}"))
+                            .ok();
                     }
 
-                    image.save(file).unwrap();
+                    let Some(clipboard) = clipboard.as_mut() else {
+                        continue;
+                    };
+
+                    let data = arboard::ImageData {
+                        width: image.width() as usize,
+                        height: image.height() as usize,
+                        bytes: image.into_raw().into(),
+                    };
+
+                    match clipboard.set_image(data) {
+                        Ok(()) => log::info!("copied the render to the clipboard"),
+                        Err(e) => log::error!("could not copy to the clipboard: {e}"),
+                    }
                 }
             }
         }
@@ -335,6 +1162,14 @@ pub fn spawn_render_thread(mut target: egui::TextureHandle) -> RunnerState {
         status,
         sender,
         runner: return_runner,
+        cancel_token,
+        pending_load,
+        progress,
+        last_frame,
+        previews,
+        sequence_progress,
+        render_stats,
+        script_name,
     }
 }
 
@@ -344,11 +1179,18 @@ fn log_run(res: &std::result::Result<Option<String>, golob_lib::GolobulError>) {
             log::info!("{}", out.trim_end());
         }
         Err(e) => {
-            if let golob_lib::GolobulError::RuntimeError { stderr, stdout } = e {
+            if let golob_lib::GolobulError::RuntimeError { stderr, stdout, .. } = e {
                 if let Some(stdout) = stdout {
                     log::info!("{}", stdout.trim_end());
                 }
-                log::error!("{stderr:?}");
+                log::error!("{stderr}");
+
+                if e.is_missing_dependency() {
+                    log::warn!(
+                        "Missing dependency? Try File > Set Python Environment \
+                         or Tools > Install Requirements."
+                    );
+                }
             } else {
                 log::error!("{e:?}");
             }
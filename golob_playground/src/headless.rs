@@ -0,0 +1,100 @@
+// Renders a single frame without opening a window, for batch work and CI.
+
+use golob_lib::{ImageFormat, InDesc, OutDesc, PythonRunner};
+use std::error::Error;
+use std::path::PathBuf;
+
+pub struct HeadlessArgs {
+    pub script: PathBuf,
+    pub inputs: Vec<(String, PathBuf)>,
+    pub out: PathBuf,
+    pub size: (u32, u32),
+}
+
+pub fn render(args: &HeadlessArgs) -> Result<(), Box<dyn Error>> {
+    let mut runner = PythonRunner::default();
+
+    if let Some(parent) = args.script.parent() {
+        runner.set_script_parent_directory(parent.to_owned());
+    }
+
+    let script_name = args
+        .script
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned());
+    runner.set_layer_name(script_name);
+
+    let src = std::fs::read_to_string(&args.script)?;
+    let out = runner.load_script(src, args.script.to_str().map(|s| s.to_owned()))?;
+    print_stdout(out);
+
+    let images = args
+        .inputs
+        .iter()
+        .map(|(name, path)| {
+            log::info!("loading image {path:?} into {name}");
+            crate::ImageDesc::open(path).map(|image| (name, image))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (mut width, mut height) = args.size;
+    let mut buffer = vec![0u8; (width * height * 4) as usize];
+
+    // at most two passes, the second if the script asked for another size
+    loop {
+        let output = OutDesc {
+            fmt: ImageFormat::Rgba8,
+            data: &mut buffer,
+            width,
+            height,
+            stride: None,
+        };
+
+        let mut pass = runner.create_render_pass(output);
+
+        for (name, image) in images.iter() {
+            let input = InDesc {
+                fmt: image.fmt,
+                data: &image.data,
+                width: image.width,
+                height: image.height,
+                stride: None,
+            };
+            pass.load_input(input, name);
+        }
+
+        print_stdout(pass.submit()?.stdout);
+
+        match runner.requested_output_resize() {
+            Some(size) if (size.width, size.height) != (width, height) => {
+                log::debug!("Rerendering with exact buffer specified");
+                (width, height) = (size.width, size.height);
+                buffer = vec![0u8; (width * height * 4) as usize];
+            }
+            _ => break,
+        }
+    }
+
+    let image = image::RgbaImage::from_raw(width, height, buffer)
+        .ok_or("output buffer does not match the output size")?;
+
+    image.save(&args.out)?;
+
+    log::info!("wrote {:?}", args.out);
+
+    Ok(())
+}
+
+fn print_stdout(out: Option<String>) {
+    if let Some(out) = out {
+        print!("{out}");
+    }
+}
+
+/// Parses `1920x1080`.
+pub fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
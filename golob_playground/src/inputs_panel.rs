@@ -44,6 +44,51 @@ fn point_selector(ui: &mut egui::Ui, name: &str, input: &mut golob_lib::Cfg<[f32
         });
 }
 
+// a dial, 0 points up and angles grow clockwise like in AE
+fn angle_knob(ui: &mut egui::Ui, name: &str, degrees: &mut f32) {
+    ui.horizontal(|ui| {
+        let size = egui::Vec2::splat(ui.spacing().interact_size.y * 2.0);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+        let center = rect.center();
+        let radius = rect.width() / 2.0 - 1.0;
+
+        if response.clicked() || response.dragged() {
+            if let Some(p) = response.interact_pointer_pos() {
+                let d = p - center;
+                *degrees = d.x.atan2(-d.y).to_degrees().rem_euclid(360.0);
+            }
+        }
+
+        let visuals = ui.style().interact(&response);
+        let painter = ui.painter();
+        let theta = degrees.to_radians();
+        let tip = center + egui::vec2(theta.sin(), -theta.cos()) * radius;
+
+        painter.circle(center, radius, visuals.bg_fill, visuals.fg_stroke);
+        painter.line_segment([center, tip], visuals.fg_stroke);
+
+        ui.add(egui::DragValue::new(degrees).speed(1.0).suffix("°"));
+        ui.label(name);
+    });
+}
+
+/// Text the script reported with `ctx.set_status`, read only.
+pub fn statuses(ui: &mut egui::Ui, runner: &golob_lib::PythonRunner) {
+    let mut statuses = runner.iter_statuses().peekable();
+    if statuses.peek().is_none() {
+        return;
+    }
+
+    ui.separator();
+
+    for (name, text) in statuses {
+        ui.horizontal_wrapped(|ui| {
+            ui.strong(name);
+            ui.label(text);
+        });
+    }
+}
+
 pub fn input_widget(
     ctx: &egui::Context,
     ui: &mut egui::Ui,
@@ -53,8 +98,61 @@ pub fn input_widget(
     val: &mut golob_lib::Variant,
 ) -> bool {
     let before = val.clone();
+    let description = val.description().map(str::to_owned);
+
+    let response = ui
+        .horizontal(|ui| {
+            if !matches!(val, golob_lib::Variant::Image(..)) {
+                let reset = egui::Button::new("↺").small();
+                if ui
+                    .add_enabled(!is_default(val), reset)
+                    .on_hover_text("reset to default")
+                    .clicked()
+                {
+                    reset_to_default(val);
+                }
+            }
+
+            ui.vertical(|ui| variant_widget(ctx, ui, state, message_queue, name, val))
+        })
+        .response;
+
+    if let Some(description) = description {
+        response.on_hover_text(description);
+    }
+
+    before != *val
+}
+
+fn is_default(val: &golob_lib::Variant) -> bool {
+    let mut reset = val.clone();
+    reset_to_default(&mut reset);
+    reset == *val
+}
+
+pub fn reset_to_default(val: &mut golob_lib::Variant) {
+    match val {
+        golob_lib::Variant::Image(..) => {}
+        golob_lib::Variant::Bool(v) => v.current = v.default,
+        golob_lib::Variant::TaggedInt(v) => v.value = v.default,
+        golob_lib::Variant::Color(v) => v.current = v.default,
+        golob_lib::Variant::Int(v) => v.current = v.default,
+        golob_lib::Variant::Float(v, _) => v.current = v.default,
+        golob_lib::Variant::Vector2(v) => v.current = v.default,
+        golob_lib::Variant::Angle(v) => v.current = v.default,
+    }
+}
+
+fn variant_widget(
+    ctx: &egui::Context,
+    ui: &mut egui::Ui,
+    state: &mut crate::AppState,
+    message_queue: &Sender<AppMessage>,
+    name: &str,
+    val: &mut golob_lib::Variant,
+) {
     match val {
-        golob_lib::Variant::Image(d) => match d.current {
+        golob_lib::Variant::Image(d, _) => match d.current {
             golob_lib::Image::Input => {
                 file_selector(ui, ctx, name, state, message_queue);
             }
@@ -86,18 +184,30 @@ pub fn input_widget(
             });
         }
         golob_lib::Variant::Int(v) => {
-            ui.add(egui::Slider::new(&mut v.current, v.min..=v.max).text(name));
+            let mut slider = egui::Slider::new(&mut v.current, v.min..=v.max).text(name);
+            if let Some(step) = v.step {
+                slider = slider.step_by(step as f64);
+            }
+            ui.add(slider);
             ui.add_space(10.0);
         }
-        golob_lib::Variant::Float(v) => {
-            ui.add(egui::Slider::new(&mut v.current, v.min..=v.max).text(name));
+        golob_lib::Variant::Float(v, scale) => {
+            let mut slider = egui::Slider::new(&mut v.current, v.min..=v.max)
+                .logarithmic(*scale == golob_lib::Scale::Logarithmic)
+                .text(name);
+            if let Some(step) = v.step {
+                slider = slider.step_by(step as f64);
+            }
+            ui.add(slider);
             ui.add_space(10.0);
         }
         golob_lib::Variant::Vector2(ref mut v) => {
             point_selector(ui, name, v);
         }
+        golob_lib::Variant::Angle(a) => {
+            angle_knob(ui, name, &mut a.current);
+        }
     }
-    before != *val
 }
 
 fn file_selector(
@@ -107,10 +217,23 @@ fn file_selector(
     app_state: &mut crate::AppState,
     message_queue: &Sender<AppMessage>,
 ) {
-    ui.horizontal(|ui| {
+    let response = ui.horizontal(|ui| {
         ui.label(name);
-        if app_state.loaded_images.read().unwrap().contains_key(name) {
-            ui.label("Loaded");
+        let loaded = app_state.loaded_images.read().unwrap().get(name).cloned();
+        if let Some(path) = loaded {
+            match app_state.thumbnails.get(ctx, name, &path) {
+                Some(thumbnail) => {
+                    let button = ui
+                        .add(egui::ImageButton::new(&thumbnail))
+                        .on_hover_text(path.display().to_string());
+                    if button.clicked() {
+                        app_state.thumbnails.enlarge(ctx, name);
+                    }
+                }
+                None => {
+                    ui.label("Loaded");
+                }
+            }
             if ui.button("X").clicked() {
                 message_queue
                     .send(AppMessage::UnloadImage {
@@ -119,13 +242,22 @@ fn file_selector(
                     .unwrap();
                 app_state.loaded_images.write().unwrap().remove(name);
             }
-        } else if ui.button("Load Image").clicked() {
-            launch_image_dialog(
-                message_queue.clone(),
-                ctx.clone(),
-                name.to_owned(),
-                app_state.loaded_images.clone(),
-            );
+        } else {
+            app_state.thumbnails.forget(name);
+
+            if ui.button("Load Image").clicked() {
+                launch_image_dialog(
+                    message_queue.clone(),
+                    ctx.clone(),
+                    name.to_owned(),
+                    app_state.loaded_images.clone(),
+                );
+            }
         }
     });
+
+    // images dropped here load into this input
+    app_state
+        .image_drop_targets
+        .push((name.to_owned(), response.response.rect));
 }
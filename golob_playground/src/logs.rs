@@ -0,0 +1,145 @@
+// Collects log records for the Logs window. egui_logger did this before,
+// but it doesn't let us filter by level or get the records back out.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::sync::Mutex;
+
+// scripts that print every frame would grow this forever
+const MAX_RECORDS: usize = 10_000;
+
+static RECORDS: Mutex<Vec<(Level, String)>> = Mutex::new(vec![]);
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut records = RECORDS.lock().unwrap();
+        if records.len() >= MAX_RECORDS {
+            records.drain(..MAX_RECORDS / 10);
+        }
+
+        let line = format!("[{}] {}", record.target(), record.args());
+        records.push((record.level(), line));
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() -> Result<(), log::SetLoggerError> {
+    log::set_logger(&Logger)?;
+    log::set_max_level(LevelFilter::Debug);
+    Ok(())
+}
+
+/// Which levels the Logs window shows.
+pub struct LogFilter {
+    pub debug: bool,
+    pub info: bool,
+    pub warn: bool,
+    pub error: bool,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            info: true,
+            warn: true,
+            error: true,
+        }
+    }
+}
+
+impl LogFilter {
+    fn shows(&self, level: Level) -> bool {
+        match level {
+            Level::Error => self.error,
+            Level::Warn => self.warn,
+            Level::Info => self.info,
+            Level::Debug | Level::Trace => self.debug,
+        }
+    }
+}
+
+/// Everything collected so far, one record per line.
+pub fn log_text() -> String {
+    RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(level, line)| format!("{level:<5} {line}\n"))
+        .collect()
+}
+
+pub fn logs_ui(ui: &mut egui::Ui, filter: &mut LogFilter) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut filter.debug, "debug");
+        ui.checkbox(&mut filter.info, "info");
+        ui.checkbox(&mut filter.warn, "warn");
+        ui.checkbox(&mut filter.error, "error");
+
+        ui.separator();
+
+        if ui.button("Clear").clicked() {
+            RECORDS.lock().unwrap().clear();
+        }
+
+        if ui.button("Save Log").clicked() {
+            launch_save_dialog(log_text());
+        }
+    });
+
+    ui.separator();
+
+    // copied out so nothing logged while drawing can deadlock on the lock
+    let records: Vec<_> = RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(level, _)| filter.shows(*level))
+        .cloned()
+        .collect();
+
+    egui::ScrollArea::vertical()
+        .stick_to_bottom(true)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for (level, line) in records {
+                let color = match level {
+                    Level::Error => ui.visuals().error_fg_color,
+                    Level::Warn => ui.visuals().warn_fg_color,
+                    Level::Info => ui.visuals().text_color(),
+                    Level::Debug | Level::Trace => ui.visuals().weak_text_color(),
+                };
+
+                let text = egui::RichText::new(format!("{level:<5} {line}"))
+                    .monospace()
+                    .color(color);
+                ui.label(text);
+            }
+        });
+}
+
+fn launch_save_dialog(text: String) {
+    std::thread::spawn(move || {
+        let Some(file) = rfd::FileDialog::new()
+            .set_file_name("golobulus.log")
+            .save_file()
+        else {
+            return;
+        };
+
+        match std::fs::write(&file, text) {
+            Ok(()) => log::info!("saved log to {file:?}"),
+            Err(e) => log::error!("could not save log to {file:?}: {e}"),
+        }
+    });
+}
@@ -1,5 +1,12 @@
 mod background_thread;
+mod headless;
 mod inputs_panel;
+mod logs;
+mod manifest;
+mod persist;
+mod recent;
+mod settings;
+mod thumbnails;
 mod util;
 
 use background_thread::RunnerStatus;
@@ -9,6 +16,8 @@ use std::sync::{Arc, RwLock};
 use std::{collections::HashMap, path::PathBuf};
 use util::*;
 
+const APP_NAME: &str = "Golobulus Playground";
+
 #[derive(Debug)]
 pub enum AppMessage {
     LoadImage {
@@ -18,19 +27,74 @@ pub enum AppMessage {
     UnloadImage {
         var: String,
     },
+    UnloadAllImages,
     ChangeFilterMode {
         mode: egui::TextureFilter,
     },
+    // None shows the primary output, otherwise one the script registered
+    ShowOutput {
+        name: Option<String>,
+    },
+    // None shows the render, otherwise its difference from the previous one times gain
+    SetDiff {
+        gain: Option<f32>,
+    },
     LoadScript {
         path: PathBuf,
     },
     LoadVenv {
         path: PathBuf,
     },
+    UnloadVenv,
+    // sizes are resized to with the filter from ChangeFilterMode
     ScreenShot {
-        params: Option<(u32, u32, egui::TextureFilter)>,
+        size: Option<(u32, u32)>,
+    },
+    CopyToClipboard {
+        size: Option<(u32, u32)>,
+    },
+    // writes input values to a json file next to the script
+    SaveState {
+        images: HashMap<String, PathBuf>,
+    },
+    // restores them, reloaded images are recorded in `images`
+    LoadState {
+        images: Arc<RwLock<HashMap<String, PathBuf>>>,
+    },
+    // loads every image an inputs manifest names, recording them in `images`
+    LoadManifest {
+        path: PathBuf,
+        images: Arc<RwLock<HashMap<String, PathBuf>>>,
+    },
+    // the size of the output buffer, until the script asks for another
+    ResizeOutput {
+        width: u32,
+        height: u32,
+    },
+    // seconds, used by every render after it
+    SetTime(f32),
+    // asks for a folder then writes numbered pngs into it
+    RenderSequence {
+        start: f32,
+        end: f32,
+        fps: f32,
+        size: Option<(u32, u32)>,
+    },
+    // asks for a file then encodes `frames` evenly spaced renders into a looping gif
+    RenderGif {
+        start: f32,
+        end: f32,
+        frames: u32,
+        delay_ms: u32,
+        size: Option<(u32, u32)>,
+    },
+    // puts every input back to the default the script registered
+    ResetInputs,
+    // unless forced, a script whose source hasn't changed is left as is
+    ReloadScript {
+        force: bool,
     },
-    ReloadScript,
+    InstallRequirements,
     Render,
 }
 
@@ -38,6 +102,52 @@ pub struct ImageDesc {
     data: Vec<u8>,
     width: u32,
     height: u32,
+    fmt: golob_lib::ImageFormat,
+}
+
+impl ImageDesc {
+    /// Keeps the decoded bit depth, so 16 bit and float images reach the script
+    /// intact. Gray images stay single channel, anything else becomes RGBA.
+    pub fn open(path: &std::path::Path) -> image::ImageResult<Self> {
+        Ok(Self::from_image(image::open(path)?))
+    }
+
+    pub fn from_image(image: image::DynamicImage) -> Self {
+        use golob_lib::ImageFormat;
+        use image::DynamicImage;
+
+        let (width, height) = (image.width(), image.height());
+
+        let (data, fmt) = match image {
+            DynamicImage::ImageLuma8(gray) => (gray.into_raw(), ImageFormat::Gray8),
+            DynamicImage::ImageLuma16(gray) => {
+                let data = ne_bytes(gray.into_raw(), u16::to_ne_bytes);
+                (data, ImageFormat::Gray16)
+            }
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                let data = ne_bytes(image.to_rgba32f().into_raw(), f32::to_ne_bytes);
+                (data, ImageFormat::Rgba32)
+            }
+            DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => {
+                let data = ne_bytes(image.to_rgba16().into_raw(), u16::to_ne_bytes);
+                (data, ImageFormat::Rgba16)
+            }
+            image => (image.to_rgba8().into_raw(), ImageFormat::Rgba8),
+        };
+
+        Self {
+            data,
+            width,
+            height,
+            fmt,
+        }
+    }
+}
+
+fn ne_bytes<T, const N: usize>(values: Vec<T>, to_bytes: fn(T) -> [u8; N]) -> Vec<u8> {
+    values.into_iter().flat_map(to_bytes).collect()
 }
 
 pub struct AppState {
@@ -46,14 +156,50 @@ pub struct AppState {
     pub last_render: std::time::Instant,
     pub last_render_dim: [usize; 2],
     pub current_file: Arc<RwLock<Option<String>>>,
+    // site packages added to the runner's path, if any
+    pub current_venv: Arc<RwLock<Option<PathBuf>>>,
     pub input_panel_hidden: bool,
-    pub draw_continuously: bool,
+    // playback state, `time` advances by the frame delta while playing
+    pub playing: bool,
+    pub looping: bool,
     pub eager_updates: bool,
     pub show_logs: bool,
-    // all loading must be done on the main thread,
-    // some python packages assume thats where they are loaded
-    pub needs_reload: Option<PathBuf>,
+    pub log_filter: logs::LogFilter,
+    // all loading must be done on the main thread, some python packages
+    // assume thats where they are loaded. Loaded the frame after it's shown as loading.
+    pub needs_reload: Option<background_thread::LoadRequest>,
     pub filter_type: egui::TextureFilter,
+    // the timeline, playback wraps from `time_max` back to `time_min` when looping
+    pub time: f32,
+    pub time_min: f32,
+    pub time_max: f32,
+    pub last_tick: std::time::Instant,
+    // screen points per output pixel, None fits the output to the window
+    pub zoom: Option<f32>,
+    pub pan: egui::Vec2,
+    pub show_inspector: bool,
+    // drawn under the output so transparency is visible
+    pub checkerboard: bool,
+    pub show_hud: bool,
+    pub show_diff: bool,
+    pub diff_gain: f32,
+    pub show_sequence_dialog: bool,
+    pub sequence_fps: f32,
+    pub show_gif_dialog: bool,
+    // exports are resized to this when set, otherwise written at the output size
+    pub export_size: Option<[u32; 2]>,
+    pub gif_frames: u32,
+    pub gif_delay_ms: u32,
+    // None for the primary output
+    pub shown_output: Option<String>,
+    // the custom resolution fields in the View menu
+    pub output_size: [u32; 2],
+    pub recent: recent::RecentFiles,
+    // where each image input was drawn this frame
+    pub image_drop_targets: Vec<(String, egui::Rect)>,
+    // the script name currently in the window title
+    pub titled_as: Option<String>,
+    pub thumbnails: thumbnails::Thumbnails,
 }
 
 pub struct PlayGround {
@@ -62,7 +208,11 @@ pub struct PlayGround {
 }
 
 impl PlayGround {
-    pub fn new(cc: &eframe::CreationContext<'_>, path: Option<PathBuf>) -> Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        path: Option<PathBuf>,
+        manifest: Option<PathBuf>,
+    ) -> Self {
         let (width, height) = (255, 255);
         let data = vec![0; width * height * 4];
 
@@ -70,6 +220,7 @@ impl PlayGround {
             data,
             width: width as u32,
             height: height as u32,
+            fmt: golob_lib::ImageFormat::Rgba8,
         };
 
         let texture = cc.egui_ctx.load_texture(
@@ -82,39 +233,747 @@ impl PlayGround {
         );
 
         let runner = background_thread::spawn_render_thread(texture.clone());
+        let settings = settings::Settings::load(cc.storage);
+
+        // the render thread starts out with the defaults
+        let _ = runner.sender.send(AppMessage::ChangeFilterMode {
+            mode: settings.filter_type,
+        });
+        let _ = runner.sender.send(AppMessage::SetDiff {
+            gain: settings.show_diff.then_some(settings.diff_gain),
+        });
+
+        let loaded_images = Arc::<RwLock<HashMap<String, PathBuf>>>::default();
+        let mut recent = recent::RecentFiles::load();
 
         if let Some(path) = path.as_ref() {
-            let _ = runner.runner.write().load_script(path);
+            if runner.runner.write().load_script(path, false).is_ok() {
+                recent.push(path);
+                let _ = runner.sender.send(AppMessage::LoadState {
+                    images: loaded_images.clone(),
+                });
+            }
+        }
+
+        // after the saved state, so the manifest wins where they overlap
+        if let Some(path) = manifest {
+            let _ = runner.sender.send(AppMessage::LoadManifest {
+                path,
+                images: loaded_images.clone(),
+            });
         }
 
+        let thumbnails = thumbnails::Thumbnails::new(runner.previews.clone());
+
         Self {
             runner,
             state: AppState {
                 texture,
                 last_render: std::time::Instant::now(),
                 last_render_dim: [255, 255],
-                loaded_images: Arc::default(),
-                needs_reload: path.clone(),
+                loaded_images,
+                needs_reload: None,
                 current_file: Arc::new(RwLock::new(
                     path.and_then(|p| p.to_str().map(|s| s.to_owned())),
                 )),
-                input_panel_hidden: false,
-                draw_continuously: false,
-                show_logs: false,
-                eager_updates: true,
-                filter_type: egui::TextureFilter::Linear,
+                current_venv: Arc::default(),
+                input_panel_hidden: settings.input_panel_hidden,
+                playing: false,
+                looping: true,
+                show_logs: settings.show_logs,
+                log_filter: logs::LogFilter::default(),
+                eager_updates: settings.eager_updates,
+                filter_type: settings.filter_type,
+                time: 0.0,
+                time_min: 0.0,
+                time_max: 10.0,
+                last_tick: std::time::Instant::now(),
+                zoom: None,
+                pan: egui::Vec2::ZERO,
+                show_inspector: settings.show_inspector,
+                checkerboard: settings.checkerboard,
+                show_hud: settings.show_hud,
+                show_diff: settings.show_diff,
+                diff_gain: settings.diff_gain,
+                show_sequence_dialog: false,
+                sequence_fps: 30.0,
+                show_gif_dialog: false,
+                export_size: None,
+                gif_frames: 30,
+                gif_delay_ms: 40,
+                shown_output: None,
+                output_size: [width as u32, height as u32],
+                recent,
+                image_drop_targets: vec![],
+                titled_as: None,
+                thumbnails,
             },
         }
     }
 }
 
+impl PlayGround {
+    fn update_title(&mut self, ctx: &egui::Context) {
+        let name = self.runner.script_name.read().clone();
+        if name == self.state.titled_as {
+            return;
+        }
+
+        let title = match &name {
+            Some(name) => format!("{name} - {APP_NAME}"),
+            None => APP_NAME.to_owned(),
+        };
+
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+        self.state.titled_as = name;
+    }
+
+    fn reset_view(&mut self) {
+        self.state.zoom = None;
+        self.state.pan = egui::Vec2::ZERO;
+    }
+
+    // one output pixel to one physical pixel
+    fn actual_pixels(&mut self, ctx: &egui::Context) {
+        self.state.zoom = Some(1.0 / ctx.pixels_per_point());
+    }
+
+    // scroll to zoom around the cursor, middle drag to pan
+    fn view_input(&mut self, ui: &mut egui::Ui, view_rect: egui::Rect) {
+        let dims = self.state.last_render_dim;
+        let home = util::compute_letterbox(dims, view_rect);
+        let response = ui.interact(view_rect, ui.id().with("output_view"), egui::Sense::drag());
+
+        let zoom = self
+            .state
+            .zoom
+            .unwrap_or(home.width() / dims[0].max(1) as f32);
+
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            let cursor = ui.input(|i| i.pointer.hover_pos());
+
+            // ctrl + scroll and pinches arrive as zoom_delta
+            let factor = ui.input(|i| i.zoom_delta()) * (scroll / 200.0).exp();
+            if factor != 1.0 {
+                let new_zoom = (zoom * factor).clamp(0.01, 100.0);
+
+                // keep the pixel under the cursor where it is
+                if let Some(cursor) = cursor {
+                    let center = home.center() + self.state.pan;
+                    let new_center = cursor - (cursor - center) * (new_zoom / zoom);
+                    self.state.pan = new_center - home.center();
+                }
+
+                self.state.zoom = Some(new_zoom);
+            }
+        }
+
+        if response.dragged_by(egui::PointerButton::Middle) {
+            self.state.pan += response.drag_delta();
+            self.state.zoom = Some(zoom);
+        }
+
+        if !ui.ctx().wants_keyboard_input() {
+            if ui.input(|i| i.key_pressed(egui::Key::Num0)) {
+                self.reset_view();
+            }
+
+            if ui.input(|i| i.key_pressed(egui::Key::Num1)) {
+                self.actual_pixels(ui.ctx());
+            }
+        }
+    }
+
+    // shows the value of the output pixel under the cursor
+    fn pixel_inspector(&self, ui: &mut egui::Ui, view_rect: egui::Rect) {
+        let Some(cursor) = ui.input(|i| i.pointer.hover_pos()) else {
+            return;
+        };
+
+        if !ui.rect_contains_pointer(view_rect) {
+            return;
+        }
+
+        let frame = self.runner.last_frame.read();
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        let rect = util::compute_view([width, height], view_rect, self.state.zoom, self.state.pan);
+
+        if width == 0 || height == 0 || !rect.contains(cursor) {
+            return;
+        }
+
+        // screen -> image coordinates, undoing the letterbox and zoom
+        let uv = (cursor - rect.min) / rect.size();
+        let x = ((uv.x * width as f32) as usize).min(width - 1);
+        let y = ((uv.y * height as f32) as usize).min(height - 1);
+
+        let i = (y * width + x) * 4;
+        let Some(&[r, g, b, a]) = frame.data.get(i..i + 4) else {
+            return;
+        };
+
+        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("pixel_inspector"), |ui| {
+            ui.label(format!("x {x}  y {y}"));
+
+            let (swatch, _) = ui.allocate_exact_size(egui::vec2(64.0, 16.0), egui::Sense::hover());
+            ui.painter().rect_filled(
+                swatch,
+                2.0,
+                egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+            );
+
+            for (channel, value) in ["R", "G", "B", "A"].iter().zip([r, g, b, a]) {
+                let text = format!("{channel} {value:>3}  {:.3}", value as f32 / 255.0);
+                ui.label(egui::RichText::new(text).monospace());
+            }
+        });
+    }
+
+    // render time, size and memory in the corner of the view
+    fn render_hud(&self, ctx: &egui::Context) {
+        let stats = self.runner.render_stats.read();
+        let (Some(last), Some(average)) = (stats.last(), stats.average()) else {
+            return;
+        };
+
+        let [width, height] = self.state.last_render_dim;
+        let megabytes = stats.buffer_bytes as f32 / (1024.0 * 1024.0);
+        let text = [
+            format!("render   {:.1} ms", last * 1000.0),
+            format!("average  {:.1} ms of {}", average * 1000.0, stats.samples()),
+            format!("size     {width} × {height}"),
+            format!("buffers  {megabytes:.1} MB"),
+        ]
+        .join("\n");
+
+        egui::Area::new(egui::Id::new("render_hud"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 28.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(egui::RichText::new(text).monospace());
+                });
+            });
+    }
+
+    // start and end are shared with the timeline
+    fn time_range_rows(&mut self, ui: &mut egui::Ui) {
+        let max = self.state.time_max;
+        let start = egui::DragValue::new(&mut self.state.time_min)
+            .speed(0.1)
+            .clamp_range(f32::MIN..=max - 0.1)
+            .suffix("s");
+        ui.label("start");
+        ui.add(start);
+        ui.end_row();
+
+        let min = self.state.time_min;
+        let end = egui::DragValue::new(&mut self.state.time_max)
+            .speed(0.1)
+            .clamp_range(min + 0.1..=f32::MAX)
+            .suffix("s");
+        ui.label("end");
+        ui.add(end);
+        ui.end_row();
+    }
+
+    fn export_size_rows(&mut self, ui: &mut egui::Ui) {
+        let mut resize = self.state.export_size.is_some();
+        let [mut width, mut height] = self.state.export_size.unwrap_or(self.state.output_size);
+
+        ui.checkbox(&mut resize, "resize to");
+        ui.add_enabled_ui(resize, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut width).clamp_range(1..=16384));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut height).clamp_range(1..=16384));
+            });
+        });
+        ui.end_row();
+
+        self.state.export_size = resize.then_some([width, height]);
+    }
+
+    fn export_size(&self) -> Option<(u32, u32)> {
+        self.state.export_size.map(|[width, height]| (width, height))
+    }
+
+    fn filter_note(&self, ui: &mut egui::Ui) {
+        if self.state.export_size.is_some() {
+            ui.weak(format!(
+                "resized with the {:?} filter from Options",
+                self.state.filter_type
+            ));
+        }
+    }
+
+    fn sequence_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = self.state.show_sequence_dialog;
+        let mut render = false;
+
+        egui::Window::new("Render Sequence")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("sequence_settings").show(ui, |ui| {
+                    self.time_range_rows(ui);
+
+                    ui.label("fps");
+                    let fps = egui::DragValue::new(&mut self.state.sequence_fps)
+                        .clamp_range(1.0..=240.0)
+                        .speed(1.0);
+                    ui.add(fps);
+                    ui.end_row();
+
+                    self.export_size_rows(ui);
+                });
+
+                self.filter_note(ui);
+
+                ui.separator();
+
+                render = ui.button("Choose Folder and Render").clicked();
+            });
+
+        if render {
+            open = false;
+            self.runner
+                .sender
+                .send(AppMessage::RenderSequence {
+                    start: self.state.time_min,
+                    end: self.state.time_max,
+                    fps: self.state.sequence_fps,
+                    size: self.export_size(),
+                })
+                .unwrap();
+        }
+
+        self.state.show_sequence_dialog = open;
+    }
+
+    fn gif_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = self.state.show_gif_dialog;
+        let mut render = false;
+
+        egui::Window::new("Export GIF")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("gif_settings").show(ui, |ui| {
+                    self.time_range_rows(ui);
+
+                    ui.label("frames");
+                    let frames = egui::DragValue::new(&mut self.state.gif_frames)
+                        .clamp_range(1..=1000)
+                        .speed(1.0);
+                    ui.add(frames);
+                    ui.end_row();
+
+                    // gifs only store hundredths of a second
+                    ui.label("delay");
+                    let delay = egui::DragValue::new(&mut self.state.gif_delay_ms)
+                        .clamp_range(10..=10_000)
+                        .speed(10.0)
+                        .suffix("ms");
+                    ui.add(delay);
+                    ui.end_row();
+
+                    self.export_size_rows(ui);
+                });
+
+                self.filter_note(ui);
+
+                let span = self.state.time_max - self.state.time_min;
+                let playback = self.state.gif_frames * self.state.gif_delay_ms;
+                ui.weak(format!(
+                    "{:.1} fps of script time, plays back over {:.2}s",
+                    self.state.gif_frames as f32 / span,
+                    playback as f32 / 1000.0,
+                ));
+
+                ui.separator();
+
+                render = ui.button("Choose File and Export").clicked();
+            });
+
+        if render {
+            open = false;
+            self.runner
+                .sender
+                .send(AppMessage::RenderGif {
+                    start: self.state.time_min,
+                    end: self.state.time_max,
+                    frames: self.state.gif_frames,
+                    delay_ms: self.state.gif_delay_ms,
+                    size: self.export_size(),
+                })
+                .unwrap();
+        }
+
+        self.state.show_gif_dialog = open;
+    }
+
+    // the image input under the pointer, if any
+    fn image_drop_target(&self, ctx: &egui::Context) -> Option<&(String, egui::Rect)> {
+        let pointer = ctx.input(|i| i.pointer.latest_pos())?;
+
+        self.state
+            .image_drop_targets
+            .iter()
+            .find(|(_, rect)| rect.contains(pointer))
+    }
+
+    // blocks until setup is done, a reload requested meanwhile cancels it
+    fn load_script(&mut self, request: background_thread::LoadRequest) {
+        let out = self
+            .runner
+            .runner
+            .write()
+            .load_script(&request.path, request.force);
+
+        // a newer request cancelled this one, it runs next frame instead
+        if self.runner.pending_load.read().is_some() {
+            return;
+        }
+
+        if out.is_err() {
+            *self.runner.status.write() = RunnerStatus::InitFailed;
+            return;
+        }
+
+        *self.runner.status.write() = RunnerStatus::Busy;
+
+        if request.restore_inputs {
+            self.state.recent.push(&request.path);
+            // renders once the saved inputs are restored
+            let _ = self.runner.sender.send(AppMessage::LoadState {
+                images: self.state.loaded_images.clone(),
+            });
+        } else {
+            let _ = self.runner.sender.send(AppMessage::Render);
+        }
+    }
+
+    fn first_image_input(&self) -> Option<String> {
+        let runner = self.runner.runner.read();
+        let mut inputs = runner.runner.iter_inputs();
+
+        inputs.find_map(|(name, val)| match val {
+            golob_lib::Variant::Image(d, _) if matches!(d.current, golob_lib::Image::Input) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+    }
+
+    fn paint_drop_highlight(&self, ctx: &egui::Context) {
+        if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+            return;
+        }
+
+        let layer = egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_target"));
+        let painter = ctx.layer_painter(layer);
+        let screen = ctx.screen_rect();
+        let stroke = egui::Stroke::new(2.0, ctx.style().visuals.selection.stroke.color);
+
+        painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(96));
+        painter.text(
+            screen.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop a script or an image",
+            egui::FontId::proportional(20.0),
+            egui::Color32::WHITE,
+        );
+
+        if let Some((_, rect)) = self.image_drop_target(ctx) {
+            painter.rect_stroke(rect.expand(2.0), 2.0, stroke);
+        }
+    }
+
+    // scripts load like they were picked from the menu, images go to the
+    // input they were dropped on, or the first one
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+
+        for file in dropped {
+            let Some(path) = file.path else {
+                log::warn!("ignoring dropped file {:?}, it has no path", file.name);
+                continue;
+            };
+
+            if util::has_extension(&path, util::SCRIPT_EXTENSIONS) {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+                *self.state.current_file.write().unwrap() = name;
+                self.runner
+                    .sender
+                    .send(AppMessage::LoadScript { path })
+                    .unwrap();
+            } else if util::has_extension(&path, util::IMAGE_EXTENSIONS) {
+                let target = self.image_drop_target(ctx).map(|(name, _)| name.clone());
+                let Some(var) = target.or_else(|| self.first_image_input()) else {
+                    log::warn!("dropped {path:?} but the script has no image inputs");
+                    continue;
+                };
+
+                self.state
+                    .loaded_images
+                    .write()
+                    .unwrap()
+                    .insert(var.clone(), path.clone());
+                self.runner
+                    .sender
+                    .send(AppMessage::LoadImage { var, path })
+                    .unwrap();
+            } else {
+                log::info!("ignoring dropped file {path:?}, it isn't a script or an image");
+            }
+        }
+    }
+
+    fn resolution_menu(&mut self, ui: &mut egui::Ui) {
+        const PRESETS: [(&str, u32, u32); 5] = [
+            ("255 × 255", 255, 255),
+            ("1024 × 1024", 1024, 1024),
+            ("720p", 1280, 720),
+            ("1080p", 1920, 1080),
+            ("4K", 3840, 2160),
+        ];
+
+        let mut size = None;
+
+        for (label, width, height) in PRESETS {
+            if ui.button(label).clicked() {
+                self.state.output_size = [width, height];
+                size = Some((width, height));
+            }
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let [width, height] = &mut self.state.output_size;
+            ui.add(egui::DragValue::new(width).clamp_range(1..=16384));
+            ui.label("×");
+            ui.add(egui::DragValue::new(height).clamp_range(1..=16384));
+
+            if ui.button("Apply").clicked() {
+                size = Some((*width, *height));
+            }
+        });
+
+        if let Some((width, height)) = size {
+            self.runner.cancel_token.cancel();
+            self.runner
+                .sender
+                .send(AppMessage::ResizeOutput { width, height })
+                .unwrap();
+            ui.close_menu();
+        }
+    }
+
+    fn play(&mut self) {
+        // otherwise the first tick would jump by however long we were paused
+        self.state.last_tick = std::time::Instant::now();
+        if self.state.time >= self.state.time_max {
+            self.state.time = self.state.time_min;
+        }
+        self.state.playing = true;
+    }
+
+    fn toggle_playing(&mut self) {
+        if self.state.playing {
+            self.state.playing = false;
+        } else {
+            self.play();
+        }
+    }
+
+    fn stop(&mut self) {
+        self.state.playing = false;
+        self.state.time = self.state.time_min;
+        self.runner.cancel_token.cancel();
+        self.runner
+            .sender
+            .send(AppMessage::SetTime(self.state.time))
+            .unwrap();
+        self.runner.sender.send(AppMessage::Render).unwrap();
+    }
+
+    // the keys in brackets next to menu items, handled before anything
+    // is drawn so a focused button doesn't see them too
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+
+        let command = |key| KeyboardShortcut::new(Modifiers::COMMAND, key);
+        let reload = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::R);
+
+        // ctrl+r would match this too, so it goes first
+        let has_script = self.state.current_file.read().unwrap().is_some();
+        if ctx.input_mut(|i| i.consume_shortcut(&reload)) && has_script {
+            self.runner
+                .sender
+                .send(AppMessage::ReloadScript { force: true })
+                .unwrap();
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&command(Key::R))) {
+            self.runner.cancel_token.cancel();
+            self.runner.sender.send(AppMessage::Render).unwrap();
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&command(Key::O))) {
+            launch_script_dialog(
+                self.runner.sender.clone(),
+                ctx.clone(),
+                self.state.current_file.clone(),
+            );
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&command(Key::S))) {
+            self.runner
+                .sender
+                .send(AppMessage::ScreenShot { size: None })
+                .unwrap();
+        }
+
+        // spaces typed into a text field stay there
+        let typing = ctx.wants_keyboard_input();
+        if !typing && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Space)) {
+            self.toggle_playing();
+        }
+    }
+
+    // moves the playhead by the wall time since the last frame
+    fn advance_time(&mut self) {
+        let now = std::time::Instant::now();
+        let dt = now.duration_since(self.state.last_tick).as_secs_f32();
+        self.state.last_tick = now;
+
+        let (start, end) = (self.state.time_min, self.state.time_max);
+        let mut time = self.state.time + dt;
+
+        if time >= end {
+            if self.state.looping {
+                time = start + (time - start) % (end - start);
+            } else {
+                time = end;
+                self.state.playing = false;
+            }
+        }
+
+        self.state.time = time.max(start);
+    }
+
+    fn timeline_ui(&mut self, ui: &mut egui::Ui) {
+        let animating = self.state.playing;
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            let play_label = if animating { "⏸" } else { "▶" };
+            if ui.button(play_label).on_hover_text("play/pause [Space]").clicked() {
+                self.toggle_playing();
+            }
+
+            if ui.button("⏹").on_hover_text("stop").clicked() {
+                self.stop();
+            }
+
+            ui.toggle_value(&mut self.state.looping, "🔁")
+                .on_hover_text("loop between the start and end time");
+
+            let max = self.state.time_max;
+            let start = egui::DragValue::new(&mut self.state.time_min)
+                .speed(0.1)
+                .clamp_range(f32::MIN..=max - 0.1)
+                .suffix("s");
+            changed |= ui.add(start).changed();
+
+            // leave room for the end time and the readout
+            ui.spacing_mut().slider_width = (ui.available_width() - 220.0).max(50.0);
+
+            // while playing the slider only reports the playhead
+            let mut time = self.state.time;
+            let slider = egui::Slider::new(&mut time, self.state.time_min..=self.state.time_max)
+                .show_value(false);
+            if ui.add_enabled(!animating, slider).changed() {
+                self.state.time = time;
+                changed = true;
+            }
+
+            let min = self.state.time_min;
+            let end = egui::DragValue::new(&mut self.state.time_max)
+                .speed(0.1)
+                .clamp_range(min + 0.1..=f32::MAX)
+                .suffix("s");
+            changed |= ui.add(end).changed();
+
+            let frame = (self.state.time * background_thread::PLAYGROUND_FPS) as u32;
+            ui.label(format!("{:.2}s  frame {frame}", self.state.time));
+        });
+
+        if let Some((done, total)) = *self.runner.sequence_progress.read() {
+            let bar = egui::ProgressBar::new(done as f32 / total as f32)
+                .text(format!("rendering frame {} of {total}", done + 1));
+            ui.add(bar);
+        }
+
+        if changed && !animating {
+            self.state.time = self.state.time.clamp(self.state.time_min, self.state.time_max);
+            self.runner.cancel_token.cancel();
+            self.runner
+                .sender
+                .send(AppMessage::SetTime(self.state.time))
+                .unwrap();
+            self.runner.sender.send(AppMessage::Render).unwrap();
+        }
+    }
+}
+
 impl eframe::App for PlayGround {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.update_title(ctx);
+
         if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-            self.state.input_panel_hidden = !self.state.input_panel_hidden;
+            if self.state.thumbnails.is_enlarged() {
+                self.state.thumbnails.close();
+            } else {
+                self.state.input_panel_hidden = !self.state.input_panel_hidden;
+            }
+        }
+
+        self.handle_shortcuts(ctx);
+
+        // egui turns ctrl+c into a copy event, text fields get theirs first
+        let copy = ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        if copy && !ctx.wants_keyboard_input() {
+            self.runner
+                .sender
+                .send(AppMessage::CopyToClipboard { size: None })
+                .unwrap();
+        }
+
+        // read before advancing so the frame that reaches the end still renders
+        let playing = self.state.playing;
+        if playing {
+            self.advance_time();
+            self.runner
+                .sender
+                .send(AppMessage::SetTime(self.state.time))
+                .unwrap();
         }
 
+        let timeline = egui::TopBottomPanel::bottom("timeline").show(ctx, |ui| {
+            self.timeline_ui(ui);
+        });
+
+        // the output is letterboxed into whatever the timeline leaves
+        let mut view_rect = ctx.screen_rect();
+        view_rect.max.y -= timeline.response.rect.height();
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            self.view_input(ui, view_rect);
+
             let stat_copy = (*self.runner.status.read()).clone();
             match stat_copy {
                 background_thread::RunnerStatus::InitFailed => {
@@ -129,8 +988,15 @@ impl eframe::App for PlayGround {
                 background_thread::RunnerStatus::Busy => {
                     if self.state.last_render.elapsed() < std::time::Duration::from_millis(16 * 10)
                     {
-                        let lb =
-                            util::compute_letterbox(self.state.last_render_dim, ctx.screen_rect());
+                        let lb = util::compute_view(
+                            self.state.last_render_dim,
+                            view_rect,
+                            self.state.zoom,
+                            self.state.pan,
+                        );
+                        if self.state.checkerboard {
+                            util::paint_checkerboard(ui.painter(), lb);
+                        }
                         egui::Image::new(&self.state.texture).paint_at(ui, lb);
                     } else {
                         let space = ui.available_rect_before_wrap();
@@ -139,25 +1005,54 @@ impl eframe::App for PlayGround {
                         rect.min.y += space.height() / 6.0;
                         rect.max.x -= space.width() / 4.0;
                         rect.max.y -= space.height() / 4.0;
-                        egui::widgets::Spinner::new().paint_at(ui, rect);
+
+                        if let Some(fraction) = self.runner.progress.get() {
+                            ui.put(rect, egui::ProgressBar::new(fraction).show_percentage());
+                        } else {
+                            egui::widgets::Spinner::new().paint_at(ui, rect);
+                        }
                     }
                 }
                 background_thread::RunnerStatus::Normal { width, height } => {
-                    let lb = util::compute_letterbox([width, height], ctx.screen_rect());
+                    let lb = util::compute_view(
+                        [width, height],
+                        view_rect,
+                        self.state.zoom,
+                        self.state.pan,
+                    );
                     self.state.last_render_dim = [width, height];
                     self.state.last_render = std::time::Instant::now();
+                    if self.state.checkerboard {
+                        util::paint_checkerboard(ui.painter(), lb);
+                    }
                     egui::Image::new(&self.state.texture).paint_at(ui, lb);
-                }
-                background_thread::RunnerStatus::NeedsReload(path) => {
-                    let out = self.runner.runner.write().load_script(&path);
 
-                    if out.is_err() {
-                        *self.runner.status.write() = RunnerStatus::InitFailed;
-                    } else {
-                        let _ = self.runner.sender.send(AppMessage::Render);
+                    if self.state.show_inspector {
+                        self.pixel_inspector(ui, view_rect);
                     }
                 }
+                background_thread::RunnerStatus::Loading(path) => {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(view_rect.height() / 3.0);
+                        ui.spinner();
+                        ui.label("Loading script...");
+                        ui.weak(path.display().to_string());
+                        ui.weak(format!(
+                            "Saving the script again restarts the load, it gives up after {}s",
+                            background_thread::SETUP_TIMEOUT.as_secs()
+                        ));
+                    });
+                }
             };
+
+            // shown as loading for a frame before disappearing into setup
+            if let Some(request) = self.state.needs_reload.take() {
+                self.load_script(request);
+            } else if let Some(request) = self.runner.pending_load.write().take() {
+                *self.runner.status.write() = RunnerStatus::Loading(request.path.clone());
+                self.state.needs_reload = Some(request);
+                ctx.request_repaint();
+            }
         });
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
@@ -167,37 +1062,155 @@ impl eframe::App for PlayGround {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
 
-                    if ui.button("Load Python Script").clicked() {
+                    if ui.button("Load Python Script [Ctrl+O]").clicked() {
                         launch_script_dialog(
                             self.runner.sender.clone(),
                             ctx.clone(),
                             self.state.current_file.clone(),
                         );
                     }
+
+                    let has_recent = !self.state.recent.scripts.is_empty();
+                    ui.add_enabled_ui(has_recent, |ui| {
+                        ui.menu_button("Open Recent", |ui| {
+                            for path in self.state.recent.scripts.iter() {
+                                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                                let button = ui.button(name.as_ref());
+
+                                if button.on_hover_text(path.display().to_string()).clicked() {
+                                    *self.state.current_file.write().unwrap() =
+                                        Some(name.into_owned());
+                                    let path = path.clone();
+                                    self.runner
+                                        .sender
+                                        .send(AppMessage::LoadScript { path })
+                                        .unwrap();
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+
+                    let has_script = self.state.current_file.read().unwrap().is_some();
+                    let reload = egui::Button::new("Reload Script [Ctrl+Shift+R]");
+                    if ui.add_enabled(has_script, reload).clicked() {
+                        self.runner
+                            .sender
+                            .send(AppMessage::ReloadScript { force: true })
+                            .unwrap();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Set Python Environment").clicked() {
+                        launch_venv_dialog(
+                            self.runner.sender.clone(),
+                            ctx.clone(),
+                            self.state.current_venv.clone(),
+                        );
+                    }
+
+                    let has_venv = self.state.current_venv.read().unwrap().is_some();
+                    if ui
+                        .add_enabled(has_venv, egui::Button::new("Unset Python Environment"))
+                        .clicked()
+                    {
+                        *self.state.current_venv.write().unwrap() = None;
+                        self.runner.sender.send(AppMessage::UnloadVenv).unwrap();
+                    }
                 });
 
                 ui.menu_button("Tools", |ui| {
-                    if ui.button("Take Screenshot").clicked() {
+                    if ui.button("Install Requirements").clicked() {
+                        self.runner
+                            .sender
+                            .send(AppMessage::InstallRequirements)
+                            .unwrap();
+                    }
+
+                    if ui.button("Save Inputs").clicked() {
+                        let images = self.state.loaded_images.read().unwrap().clone();
+                        self.runner
+                            .sender
+                            .send(AppMessage::SaveState { images })
+                            .unwrap();
+                    }
+
+                    let has_images = !self.state.loaded_images.read().unwrap().is_empty();
+                    if ui
+                        .add_enabled(has_images, egui::Button::new("Unload All Images"))
+                        .clicked()
+                    {
+                        self.state.loaded_images.write().unwrap().clear();
+                        self.runner
+                            .sender
+                            .send(AppMessage::UnloadAllImages)
+                            .unwrap();
+                    }
+
+                    if ui.button("Load Inputs Manifest").clicked() {
+                        launch_manifest_dialog(
+                            self.runner.sender.clone(),
+                            ctx.clone(),
+                            self.state.loaded_images.clone(),
+                        );
+                    }
+
+                    if ui.button("Restore Saved Inputs").clicked() {
+                        self.runner
+                            .sender
+                            .send(AppMessage::LoadState {
+                                images: self.state.loaded_images.clone(),
+                            })
+                            .unwrap();
+                    }
+
+                    if ui.button("Render Sequence").clicked() {
+                        self.state.show_sequence_dialog = true;
+                    }
+
+                    if ui.button("Export GIF").clicked() {
+                        self.state.show_gif_dialog = true;
+                    }
+
+                    if ui.button("Take Screenshot [Ctrl+S]").clicked() {
                         self.runner
                             .sender
-                            .send(AppMessage::ScreenShot { params: None })
+                            .send(AppMessage::ScreenShot { size: None })
                             .unwrap();
                     }
 
                     if ui.button("Take Screenshot at Window Resolution").clicked() {
-                        let lb =
-                            util::compute_letterbox(self.state.last_render_dim, ctx.screen_rect());
+                        let lb = util::compute_letterbox(self.state.last_render_dim, view_rect);
                         self.runner
                             .sender
                             .send(AppMessage::ScreenShot {
-                                params: Some((
-                                    lb.width() as u32,
-                                    lb.height() as u32,
-                                    self.state.filter_type,
-                                )),
+                                size: Some((lb.width() as u32, lb.height() as u32)),
                             })
                             .unwrap();
                     }
+
+                    if ui.button("Copy to Clipboard [Ctrl+C]").clicked() {
+                        self.runner
+                            .sender
+                            .send(AppMessage::CopyToClipboard { size: None })
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("View", |ui| {
+                    if ui.button("Fit to Window [0]").clicked() {
+                        self.reset_view();
+                    }
+
+                    if ui.button("Actual Pixels [1]").clicked() {
+                        self.actual_pixels(ctx);
+                    }
+
+                    ui.separator();
+
+                    ui.menu_button("Output Resolution", |ui| self.resolution_menu(ui));
                 });
 
                 ui.menu_button("Options", |ui| {
@@ -227,9 +1240,37 @@ impl eframe::App for PlayGround {
 
                     ui.separator();
 
-                    ui.checkbox(&mut self.state.draw_continuously, "animate script");
                     ui.checkbox(&mut self.state.eager_updates, "eagerly update inputs");
                     ui.checkbox(&mut self.state.show_logs, "show logs");
+                    ui.checkbox(&mut self.state.show_inspector, "inspect pixels");
+                    ui.checkbox(&mut self.state.checkerboard, "checkerboard background");
+                    ui.checkbox(&mut self.state.show_hud, "render stats");
+
+                    ui.separator();
+
+                    if ui.button("Reset All Inputs").clicked() {
+                        self.runner.sender.send(AppMessage::ResetInputs).unwrap();
+                        if self.state.eager_updates {
+                            self.runner.sender.send(AppMessage::Render).unwrap();
+                        }
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    let mut diff_changed = ui
+                        .checkbox(&mut self.state.show_diff, "diff vs previous")
+                        .changed();
+
+                    let gain = egui::Slider::new(&mut self.state.diff_gain, 1.0..=100.0)
+                        .logarithmic(true)
+                        .text("diff gain");
+                    diff_changed |= ui.add_enabled(self.state.show_diff, gain).changed();
+
+                    if diff_changed {
+                        let gain = self.state.show_diff.then_some(self.state.diff_gain);
+                        self.runner.sender.send(AppMessage::SetDiff { gain }).unwrap();
+                    }
                 });
             });
         });
@@ -246,6 +1287,11 @@ impl eframe::App for PlayGround {
                             None => ui.label("No File Loaded"),
                             Some(ref s) => ui.label(format!("Watched File: {s}")),
                         };
+
+                        match self.state.current_venv.read().unwrap().as_ref() {
+                            None => ui.label("No Python Environment"),
+                            Some(p) => ui.label(format!("Python Environment: {}", p.display())),
+                        };
                     });
 
                     ui.separator();
@@ -253,18 +1299,42 @@ impl eframe::App for PlayGround {
                     let mut changed = false;
                     let sender = &self.runner.sender;
                     let mut runner = self.runner.runner.write();
+
+                    let outputs = runner.runner.outputs();
+                    if !outputs.is_empty() {
+                        let before = self.state.shown_output.clone();
+                        egui::ComboBox::from_label("Shown Output")
+                            .selected_text(before.as_deref().unwrap_or("main"))
+                            .show_ui(ui, |ui| {
+                                let shown = &mut self.state.shown_output;
+                                ui.selectable_value(shown, None, "main");
+                                for name in outputs {
+                                    ui.selectable_value(shown, Some(name.clone()), name);
+                                }
+                            });
+
+                        if before != self.state.shown_output {
+                            let name = self.state.shown_output.clone();
+                            sender.send(AppMessage::ShowOutput { name }).unwrap();
+                        }
+
+                        ui.separator();
+                    }
                     for (name, ref mut val) in runner.runner.iter_inputs_mut() {
                         changed |=
                             inputs_panel::input_widget(ctx, ui, &mut self.state, sender, name, val);
                     }
 
+                    inputs_panel::statuses(ui, &runner.runner);
+
                     if self.state.eager_updates && changed {
                         self.runner.sender.send(AppMessage::Render).unwrap();
                     }
 
                     if !self.state.eager_updates {
                         ui.vertical_centered(|ui| {
-                            if ui.button("Redraw").clicked() {
+                            if ui.button("Redraw [Ctrl+R]").clicked() {
+                                self.runner.cancel_token.cancel();
                                 self.runner.sender.send(AppMessage::Render).unwrap();
                             }
                         });
@@ -283,55 +1353,148 @@ impl eframe::App for PlayGround {
             },
         );
 
+        if self.state.show_hud {
+            self.render_hud(ctx);
+        }
+
+        self.paint_drop_highlight(ctx);
+        self.handle_dropped_files(ctx);
+        self.state.image_drop_targets.clear();
+
+        self.sequence_dialog(ctx);
+        self.gif_dialog(ctx);
+        self.state.thumbnails.show_enlarged(ctx);
+
         egui::Window::new("Logs")
             .open(&mut self.state.show_logs)
             .show(ctx, |ui| {
-                egui_logger::logger_ui(ui);
+                logs::logs_ui(ui, &mut self.state.log_filter);
             });
 
-        if self.state.draw_continuously {
+        if playing {
             self.runner.sender.send(AppMessage::Render).unwrap();
         }
 
         ctx.request_repaint_after(std::time::Duration::from_millis(16));
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        settings::Settings::capture(&self.state).save(storage);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&glow::Context>) {
+        // the render thread won't outlive us, so save from here
+        let images = self.state.loaded_images.read().unwrap().clone();
+        self.runner.runner.read().save_state(&images);
+    }
 }
 
 fn main() -> eframe::Result<()> {
-    egui_logger::init().unwrap();
+    let mut script_path = None;
+    let mut inputs = vec![];
+    let mut manifest_path = None;
+    let mut out_path = None;
+    let mut size = None;
 
-    let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([800.0, 800.0])
-            .with_min_inner_size([300.0, 220.0]),
-        ..Default::default()
-    };
+    let mut args = std::env::args().skip(1);
 
-    let mut script_path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--file" => {
+                let Some(file_path) = args.next() else {
+                    eprintln!("Missing file path after --file flag");
+                    return Ok(());
+                };
 
-    let args_owned: Vec<String> = std::env::args().collect();
-    let args: Vec<&str> = args_owned.iter().map(|s| s.as_str()).collect();
+                let path = PathBuf::from(file_path);
+                if !path.exists() {
+                    eprintln!("File does not exist: {:?}", path);
+                    return Ok(());
+                }
 
-    match args.as_slice() {
-        [.., "--file", file_path] => {
-            let path = PathBuf::from(file_path);
-            if path.exists() {
                 script_path = Some(path);
-            } else {
-                eprintln!("File does not exist: {:?}", path);
-                return Ok(());
+            }
+            "--input" => match args.next().as_deref().and_then(|a| a.split_once('=')) {
+                Some((name, path)) => inputs.push((name.to_owned(), PathBuf::from(path))),
+                None => {
+                    eprintln!("Expected name=path after --input flag");
+                    std::process::exit(2);
+                }
+            },
+            "--inputs" => match args.next() {
+                Some(path) => manifest_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("Missing manifest path after --inputs flag");
+                    std::process::exit(2);
+                }
+            },
+            "--out" => match args.next() {
+                Some(path) => out_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("Missing output path after --out flag");
+                    std::process::exit(2);
+                }
+            },
+            "--size" => match args.next().as_deref().and_then(headless::parse_size) {
+                Some(s) => size = Some(s),
+                None => {
+                    eprintln!("Expected WIDTHxHEIGHT after --size flag");
+                    std::process::exit(2);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    // with an output path we render once and exit without a window
+    if let Some(out) = out_path {
+        let Some(script) = script_path else {
+            eprintln!("--out needs a script, pass one with --file");
+            std::process::exit(2);
+        };
+
+        env_logger::init();
+
+        // --input flags come last so they can override the manifest
+        if let Some(manifest) = manifest_path {
+            match manifest::load(&manifest) {
+                Ok(entries) => inputs = entries.into_iter().chain(inputs).collect(),
+                Err(e) => {
+                    eprintln!("Could not read {manifest:?}: {e}");
+                    std::process::exit(2);
+                }
             }
         }
-        [_, "--file"] => {
-            eprintln!("Missing file path after --file flag");
-            return Ok(());
+
+        let args = headless::HeadlessArgs {
+            script,
+            inputs,
+            out,
+            size: size.unwrap_or((255, 255)),
+        };
+
+        if let Err(e) = headless::render(&args) {
+            eprintln!("{e}");
+            std::process::exit(1);
         }
-        _ => {}
+
+        return Ok(());
+    }
+
+    logs::init().unwrap();
+
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([800.0, 800.0])
+            .with_min_inner_size([300.0, 220.0]),
+        // the settings and window geometry live next to the recent scripts
+        persistence_path: util::config_dir().map(|dir| dir.join("playground.ron")),
+        ..Default::default()
     };
 
     eframe::run_native(
-        "Golobulus Playground",
+        APP_NAME,
         native_options,
-        Box::new(|cc| Box::new(PlayGround::new(cc, script_path))),
+        Box::new(|cc| Box::new(PlayGround::new(cc, script_path, manifest_path))),
     )
 }
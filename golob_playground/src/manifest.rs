@@ -0,0 +1,22 @@
+// A manifest maps image inputs to files, so a scene with several images
+// comes back with one flag instead of a dialog per input:
+//
+//     { "background": "plates/bg.png", "matte": "/renders/matte.png" }
+//
+// Relative paths are relative to the manifest.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Input names and the images to load into them, sorted by name.
+pub fn load(manifest: &Path) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(manifest)?;
+    let entries: BTreeMap<String, PathBuf> = serde_json::from_str(&contents)?;
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, path)| (name, dir.join(path)))
+        .collect())
+}
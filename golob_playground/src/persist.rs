@@ -0,0 +1,66 @@
+// Input values live in a json file next to the script, so
+// tweaks survive closing the playground.
+
+use golob_lib::{GolobulError, PythonRunner, Variant};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SavedState {
+    pub inputs: HashMap<String, Variant>,
+    pub images: HashMap<String, PathBuf>,
+}
+
+/// `effect.py` saves to `effect.golob.json`.
+pub fn sidecar_path(script: &Path) -> PathBuf {
+    script.with_extension("golob.json")
+}
+
+impl SavedState {
+    pub fn capture(runner: &PythonRunner, images: &HashMap<String, PathBuf>) -> Self {
+        let inputs: HashMap<String, Variant> = runner
+            .iter_inputs()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        // paths for inputs the script dropped are stale
+        let images = images
+            .iter()
+            .filter(|(name, _)| matches!(inputs.get(*name), Some(Variant::Image(..))))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        Self { inputs, images }
+    }
+
+    pub fn save(&self, script: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(sidecar_path(script), json)
+    }
+
+    pub fn load(script: &Path) -> Option<Self> {
+        let path = sidecar_path(script);
+        let contents = std::fs::read_to_string(&path).ok()?;
+
+        match serde_json::from_str(&contents) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                log::warn!("ignoring malformed {path:?}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Pushes the saved values into the runner, inputs that no longer
+    /// exist or changed type are skipped.
+    pub fn restore(&self, runner: &mut PythonRunner) {
+        for (name, value) in self.inputs.iter() {
+            match runner.try_set_var(name, value.clone()) {
+                Ok(()) => {}
+                Err(GolobulError::MissingVar(_)) => log::debug!("dropping stale input {name}"),
+                Err(e) => log::debug!("skipping saved input {name}: {e}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// The scripts listed under File > Open Recent, kept in the user's
+// config dir so they outlive the playground.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecentFiles {
+    // most recent first
+    pub scripts: Vec<PathBuf>,
+}
+
+fn recent_path() -> Option<PathBuf> {
+    crate::util::config_dir().map(|dir| dir.join("recent_scripts.json"))
+}
+
+impl RecentFiles {
+    /// Scripts that have since been moved or deleted are dropped.
+    pub fn load() -> Self {
+        let Some(contents) = recent_path().and_then(|p| std::fs::read_to_string(p).ok()) else {
+            return Self::default();
+        };
+
+        let mut recent: Self = serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log::warn!("ignoring malformed recent scripts: {e}");
+            Self::default()
+        });
+
+        recent.scripts.retain(|p| p.is_file());
+        recent
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = recent_path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Moves `script` to the top of the list and saves it.
+    pub fn push(&mut self, script: &Path) {
+        let script = std::fs::canonicalize(script).unwrap_or_else(|_| script.to_owned());
+
+        self.scripts.retain(|p| *p != script);
+        self.scripts.insert(0, script);
+        self.scripts.truncate(MAX_RECENT);
+
+        if let Err(e) = self.save() {
+            log::warn!("could not save recent scripts: {e}");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Options from the menus, restored on the next launch through eframe's
+// storage. eframe saves the window's size and position alongside them.
+
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "settings";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub input_panel_hidden: bool,
+    pub filter_type: egui::TextureFilter,
+    pub eager_updates: bool,
+    pub show_logs: bool,
+    pub show_inspector: bool,
+    pub checkerboard: bool,
+    pub show_hud: bool,
+    pub show_diff: bool,
+    pub diff_gain: f32,
+}
+
+// what a first launch looks like
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            input_panel_hidden: false,
+            filter_type: egui::TextureFilter::Linear,
+            eager_updates: true,
+            show_logs: false,
+            show_inspector: false,
+            checkerboard: true,
+            show_hud: false,
+            show_diff: false,
+            diff_gain: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn capture(state: &crate::AppState) -> Self {
+        Self {
+            input_panel_hidden: state.input_panel_hidden,
+            filter_type: state.filter_type,
+            eager_updates: state.eager_updates,
+            show_logs: state.show_logs,
+            show_inspector: state.show_inspector,
+            checkerboard: state.checkerboard,
+            show_hud: state.show_hud,
+            show_diff: state.show_diff,
+            diff_gain: state.diff_gain,
+        }
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+}
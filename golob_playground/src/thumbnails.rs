@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use egui::mutex::RwLock;
+use egui::{ColorImage, TextureHandle};
+
+// longest side of a thumbnail in pixels
+const THUMBNAIL_SIZE: u32 = 48;
+
+/// Image inputs by name, written by the render thread as it decodes them.
+pub type Previews = Arc<RwLock<HashMap<String, Preview>>>;
+
+/// An image input ready to upload, so the UI thread never decodes anything.
+pub struct Preview {
+    pub path: PathBuf,
+    pub thumbnail: ColorImage,
+    pub full: ColorImage,
+}
+
+impl Preview {
+    pub fn new(path: &Path, image: &image::DynamicImage) -> Self {
+        let image = image.to_rgba8();
+
+        let scale = THUMBNAIL_SIZE as f32 / image.width().max(image.height()) as f32;
+        let scale = scale.min(1.0);
+        let width = ((image.width() as f32 * scale).round() as u32).max(1);
+        let height = ((image.height() as f32 * scale).round() as u32).max(1);
+        let thumbnail = image::imageops::thumbnail(&image, width, height);
+
+        Self {
+            path: path.to_owned(),
+            thumbnail: color_image(&thumbnail),
+            full: color_image(&image),
+        }
+    }
+}
+
+/// Small previews of the loaded image inputs, uploaded once per path.
+pub struct Thumbnails {
+    previews: Previews,
+    cache: HashMap<String, (PathBuf, TextureHandle)>,
+    // shown over the whole window at full size until clicked away
+    enlarged: Option<TextureHandle>,
+}
+
+impl Thumbnails {
+    pub fn new(previews: Previews) -> Self {
+        Self {
+            previews,
+            cache: HashMap::new(),
+            enlarged: None,
+        }
+    }
+
+    /// None until the render thread has decoded the image, or if it couldn't.
+    pub fn get(&mut self, ctx: &egui::Context, name: &str, path: &Path) -> Option<TextureHandle> {
+        if let Some((cached, texture)) = self.cache.get(name) {
+            if cached == path {
+                return Some(texture.clone());
+            }
+        }
+
+        let previews = self.previews.read();
+        let preview = previews.get(name).filter(|p| p.path == path)?;
+        let texture = ctx.load_texture(
+            format!("thumbnail_{name}"),
+            preview.thumbnail.clone(),
+            egui::TextureOptions::LINEAR,
+        );
+
+        self.cache
+            .insert(name.to_owned(), (path.to_owned(), texture.clone()));
+        Some(texture)
+    }
+
+    pub fn forget(&mut self, name: &str) {
+        self.cache.remove(name);
+    }
+
+    pub fn enlarge(&mut self, ctx: &egui::Context, name: &str) {
+        self.enlarged = self.previews.read().get(name).map(|preview| {
+            ctx.load_texture(
+                "thumbnail_enlarged",
+                preview.full.clone(),
+                egui::TextureOptions::LINEAR,
+            )
+        });
+    }
+
+    pub fn is_enlarged(&self) -> bool {
+        self.enlarged.is_some()
+    }
+
+    pub fn close(&mut self) {
+        self.enlarged = None;
+    }
+
+    pub fn show_enlarged(&mut self, ctx: &egui::Context) {
+        let Some(texture) = self.enlarged.as_ref() else {
+            return;
+        };
+
+        let screen = ctx.screen_rect();
+        let clicked = egui::Area::new(egui::Id::new("enlarged_input"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(screen.min)
+            .show(ctx, |ui| {
+                let response = ui.allocate_rect(screen, egui::Sense::click());
+                ui.painter()
+                    .rect_filled(screen, 0.0, egui::Color32::from_black_alpha(220));
+
+                let rect = crate::util::compute_letterbox(texture.size(), screen);
+                egui::Image::new(texture).paint_at(ui, rect);
+
+                response.clicked()
+            })
+            .inner;
+
+        if clicked {
+            self.close();
+        }
+    }
+}
+
+fn color_image(image: &image::RgbaImage) -> ColorImage {
+    let size = [image.width() as usize, image.height() as usize];
+    ColorImage::from_rgba_unmultiplied(size, image.as_raw())
+}
@@ -1,6 +1,35 @@
 use crate::*;
+use std::path::Path;
 use std::sync::mpsc::Sender;
 
+pub const SCRIPT_EXTENSIONS: &[&str] = &["py"];
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpeg", "jpg", "exr", "webm"];
+
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Where the playground keeps its own files, like the recent scripts.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(app_data) = std::env::var_os("APPDATA") {
+        return Some(PathBuf::from(app_data).join("golobulus"));
+    }
+
+    let home = homedir::get_my_home().ok()??;
+
+    let base = if cfg!(target_os = "macos") {
+        home.join("Library/Application Support")
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".config"))
+    };
+
+    Some(base.join("golobulus"))
+}
+
 pub fn launch_script_dialog(
     sender: Sender<AppMessage>,
     ctx: egui::Context,
@@ -21,7 +50,7 @@ pub fn launch_script_dialog(
 
         let Some(file_path) = rfd::FileDialog::new()
             .set_directory(home_dir)
-            .add_filter("python", &["py"])
+            .add_filter("python", SCRIPT_EXTENSIONS)
             .pick_file()
         else {
             return;
@@ -38,6 +67,39 @@ pub fn launch_script_dialog(
     });
 }
 
+pub fn launch_venv_dialog(
+    sender: Sender<AppMessage>,
+    ctx: egui::Context,
+    current_venv: Arc<RwLock<Option<PathBuf>>>,
+) {
+    std::thread::spawn(move || {
+        let home_dir = match homedir::get_my_home() {
+            Ok(Some(home)) => home,
+            _ => "/".into(),
+        };
+
+        let Some(dir) = rfd::FileDialog::new()
+            .set_directory(home_dir)
+            .set_title("Select a venv or site-packages folder")
+            .pick_folder()
+        else {
+            return;
+        };
+
+        let path = if dir.join("pyvenv.cfg").exists() {
+            golob_lib::venv_site_packages(&dir)
+        } else {
+            dir
+        };
+
+        *current_venv.write().unwrap() = Some(path.clone());
+
+        let _ = sender.send(AppMessage::LoadVenv { path });
+
+        ctx.request_repaint();
+    });
+}
+
 pub fn launch_image_dialog(
     sender: Sender<AppMessage>,
     ctx: egui::Context,
@@ -59,7 +121,7 @@ pub fn launch_image_dialog(
 
         let Some(file_path) = rfd::FileDialog::new()
             .set_directory(home_dir)
-            .add_filter("image", &["png", "jpeg", "jpg", "exr", "webm"])
+            .add_filter("image", IMAGE_EXTENSIONS)
             .pick_file()
         else {
             return;
@@ -73,6 +135,73 @@ pub fn launch_image_dialog(
     });
 }
 
+pub fn launch_manifest_dialog(
+    sender: Sender<AppMessage>,
+    ctx: egui::Context,
+    images: Arc<RwLock<HashMap<String, PathBuf>>>,
+) {
+    std::thread::spawn(move || {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Select an inputs manifest")
+            .add_filter("json", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let _ = sender.send(AppMessage::LoadManifest { path, images });
+        ctx.request_repaint();
+    });
+}
+
+/// Where the output is painted, the letterbox moved and scaled by the user.
+pub fn compute_view(
+    texture_size: [usize; 2],
+    view_rect: egui::Rect,
+    zoom: Option<f32>,
+    pan: egui::Vec2,
+) -> egui::Rect {
+    let home = compute_letterbox(texture_size, view_rect);
+
+    let Some(zoom) = zoom else {
+        return home;
+    };
+
+    let size = egui::vec2(texture_size[0] as f32, texture_size[1] as f32) * zoom;
+    egui::Rect::from_center_size(home.center() + pan, size)
+}
+
+/// Paints the usual transparency checkerboard under `rect`, only
+/// the squares that are actually on screen are drawn.
+pub fn paint_checkerboard(painter: &egui::Painter, rect: egui::Rect) {
+    const SQUARE: f32 = 10.0;
+
+    let visible = rect.intersect(painter.clip_rect());
+    if !visible.is_positive() {
+        return;
+    }
+
+    painter.rect_filled(visible, 0.0, egui::Color32::from_gray(204));
+
+    // squares are anchored to the image so they move with it when panning
+    let first_col = ((visible.min.x - rect.min.x) / SQUARE).floor() as i32;
+    let first_row = ((visible.min.y - rect.min.y) / SQUARE).floor() as i32;
+    let last_col = ((visible.max.x - rect.min.x) / SQUARE).ceil() as i32;
+    let last_row = ((visible.max.y - rect.min.y) / SQUARE).ceil() as i32;
+
+    for row in first_row..last_row {
+        for col in first_col..last_col {
+            if (row + col).rem_euclid(2) == 0 {
+                continue;
+            }
+
+            let min = rect.min + egui::vec2(col as f32, row as f32) * SQUARE;
+            let square = egui::Rect::from_min_size(min, egui::Vec2::splat(SQUARE));
+            painter.rect_filled(square.intersect(visible), 0.0, egui::Color32::from_gray(153));
+        }
+    }
+}
+
 pub fn compute_letterbox(texture_size: [usize; 2], screen_rect: egui::Rect) -> egui::Rect {
     // pad letterbox
     let mut new_rect = screen_rect;
@@ -2,12 +2,13 @@
 // to handle sequential background renders. differs from idle_task
 // in that it runs on a background thread to not block the UI.
 
-use golob_lib::{PythonRunner, Variant};
+use golob_lib::{CancellationToken, PythonRunner, Variant};
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
 
 use crate::footage_utils;
+use crate::video_encoder::{OutputContainer, VideoEncoder};
 
 pub type JobId = usize;
 
@@ -26,7 +27,7 @@ pub enum TaskStatus {
 impl From<golob_lib::GolobulError> for TaskStatus {
     fn from(e: golob_lib::GolobulError) -> Self {
         match e {
-            golob_lib::GolobulError::RuntimeError { stderr, stdout } => TaskStatus::Error {
+            golob_lib::GolobulError::RuntimeError { stderr, stdout, .. } => TaskStatus::Error {
                 stdout,
                 error: stderr,
             },
@@ -65,6 +66,7 @@ pub enum TaskMessage {
     Job {
         inputs: Vec<(String, Variant)>,
         time: f32,
+        fps: f32,
         frame: u32,
     },
 }
@@ -73,11 +75,15 @@ pub struct BackgroundTask {
     pub tx: Sender<TaskMessage>,
     pub status: TaskStatus,
     pub buffers: Vec<ImageBuffer>,
+    pub cancel_token: CancellationToken,
 }
 
 pub struct OutputDesc {
     pub fmt: golob_lib::ImageFormat,
     pub directory: PathBuf,
+    pub container: OutputContainer,
+    pub file_format: footage_utils::SequenceFormat,
+    pub alpha: footage_utils::AlphaMode,
     pub last_frame: u32,
     pub width: u32,
     pub height: u32,
@@ -91,6 +97,7 @@ impl OutputDesc {
 
 impl BackgroundTask {
     pub fn cancel(&self) {
+        self.cancel_token.cancel();
         let _ = self.tx.send(TaskMessage::Cancel);
     }
 
@@ -99,13 +106,15 @@ impl BackgroundTask {
         mut runner: PythonRunner,
         mut desc: OutputDesc,
         task_pool: Arc<dashmap::DashMap<JobId, BackgroundTask>>,
-    ) {
+    ) -> CancellationToken {
         let (tx, rx) = channel();
+        let cancel_token = CancellationToken::new();
 
         let task = BackgroundTask {
             tx,
             status: TaskStatus::Ready,
             buffers: vec![],
+            cancel_token: cancel_token.clone(),
         };
 
         desc.fmt = match desc.fmt {
@@ -117,19 +126,32 @@ impl BackgroundTask {
 
         task_pool.insert(id, task);
 
+        let worker_token = cancel_token.clone();
         std::thread::spawn(move || {
             let mut output_buffer = vec![0u8; desc.buffer_len()];
+            // started on the first finished frame, once we know the frame rate
+            let mut encoder: Option<VideoEncoder> = None;
+            let mut encode = desc.container.is_video();
             while let Ok(msg) = rx.recv() {
                 match msg {
                     TaskMessage::Job {
                         inputs,
                         time,
+                        fps,
                         frame,
                     } => {
                         task_pool.get_mut(&id).unwrap().status = TaskStatus::Busy;
 
-                        for (input_name, input_value) in inputs {
-                            let _ = runner.try_set_var(&input_name, input_value);
+                        // rendering on with the last frame's inputs would quietly
+                        // bake the wrong values into the sequence, so stop and say why
+                        if let Err(e) = runner.try_set_vars(&inputs) {
+                            log::error!("could not apply the inputs for frame {frame}: {e}");
+                            let _ = std::fs::remove_dir_all(&desc.directory);
+                            task_pool.get_mut(&id).unwrap().status = TaskStatus::Error {
+                                stdout: None,
+                                error: format!("frame {frame}: {e}"),
+                            };
+                            break;
                         }
 
                         output_buffer.fill(0);
@@ -143,8 +165,14 @@ impl BackgroundTask {
                         };
 
                         runner.set_time(time);
+                        runner.set_fps(fps);
+                        // the index the file is numbered with, time * fps drifts on NTSC rates
+                        runner.set_frame(frame);
 
                         let mut render_pass = runner.create_render_pass(output);
+                        render_pass.set_cancellation_token(worker_token.clone());
+                        // output_buffer lives as long as the task, its view can too
+                        render_pass.set_output_generation(0);
                         let mut task = task_pool.get_mut(&id).unwrap();
                         for layer in task.buffers.iter() {
                             let input = golob_lib::InDesc {
@@ -164,20 +192,51 @@ impl BackgroundTask {
 
                         match res {
                             Ok(_) => {
+                                if encode && encoder.is_none() {
+                                    match VideoEncoder::new(
+                                        &desc.directory,
+                                        desc.container,
+                                        desc.width,
+                                        desc.height,
+                                        fps,
+                                        desc.fmt,
+                                    ) {
+                                        Ok(e) => encoder = Some(e),
+                                        Err(e) => {
+                                            log::warn!("no encoder, saving images instead: {e}");
+                                            encode = false;
+                                        }
+                                    }
+                                }
+
                                 // save frame
-                                let e = footage_utils::write_image_to_file(
-                                    desc.directory.join(name),
-                                    &output_buffer,
-                                    desc.width,
-                                    desc.height,
-                                    desc.fmt,
-                                );
+                                let mut e = match encoder.as_mut() {
+                                    Some(encoder) => encoder
+                                        .push_frame(&output_buffer)
+                                        .map_err(|e| e.to_string()),
+                                    None => footage_utils::write_image_to_file(
+                                        desc.directory.join(name),
+                                        &output_buffer,
+                                        desc.width,
+                                        desc.height,
+                                        desc.fmt,
+                                        desc.file_format,
+                                        desc.alpha,
+                                    )
+                                    .map_err(|e| e.to_string()),
+                                };
+
+                                if frame == desc.last_frame && e.is_ok() {
+                                    if let Some(encoder) = encoder.take() {
+                                        e = encoder.finish().map_err(|e| e.to_string());
+                                    }
+                                }
 
                                 if let Err(e) = e {
                                     log::error!("error while writing file {e}");
                                     task.status = TaskStatus::Error {
                                         stdout: None,
-                                        error: format!("{e:?}"),
+                                        error: e,
                                     };
                                     let _ = std::fs::remove_dir_all(desc.directory);
                                     break;
@@ -190,6 +249,12 @@ impl BackgroundTask {
                                     task.status = TaskStatus::Ready;
                                 }
                             }
+                            Err(golob_lib::GolobulError::Cancelled) => {
+                                log::debug!("task {id} cancelled mid frame");
+                                let _ = std::fs::remove_dir_all(&desc.directory);
+                                task.status = TaskStatus::Cancelled;
+                                break;
+                            }
                             Err(e) => {
                                 log::error!("error in Background thread {id}: {e}");
                                 task.status = e.into();
@@ -208,5 +273,7 @@ impl BackgroundTask {
                 }
             }
         });
+
+        cancel_token
     }
 }
@@ -31,13 +31,14 @@ pub fn handle_run_output(
                 crate::instance::DebugContents {
                     error: None,
                     stdout: Some(out),
+                    status: None,
                 },
             );
         }
         Err(e) => {
             error!("Error: {e:?}");
             match e {
-                golob_lib::GolobulError::RuntimeError { stderr, stdout } => {
+                golob_lib::GolobulError::RuntimeError { stderr, stdout, .. } => {
                     if stdout.is_some() {
                         info!("{}", stdout.as_ref().unwrap().trim_end());
                     }
@@ -47,6 +48,7 @@ pub fn handle_run_output(
                         crate::instance::DebugContents {
                             error: Some(stderr),
                             stdout,
+                            status: None,
                         },
                     );
                 }
@@ -56,6 +58,7 @@ pub fn handle_run_output(
                         crate::instance::DebugContents {
                             error: Some(format!("{e}")),
                             stdout: None,
+                            status: None,
                         },
                     );
                 }
@@ -64,18 +67,35 @@ pub fn handle_run_output(
         }
         _ => {}
     };
+
+    let status: Vec<String> = instance
+        .runner
+        .iter_statuses()
+        .map(|(name, text)| format!("{name}: {text}"))
+        .collect();
+
+    if !status.is_empty() {
+        map.entry(time).or_default().status = Some(status.join("\n"));
+    }
 }
 
 pub fn startup_error_message(error: golob_lib::GolobulError, out_data: &mut OutData) {
-    if let golob_lib::GolobulError::RuntimeError { stderr, stdout } = error {
-        error!("{stderr:?}");
+    // a reload can only fix imports once the packages are findable
+    let hint = if error.is_missing_dependency() {
+        "missing dependency? set the site packages path then reload the script.\n"
+    } else {
+        ""
+    };
+
+    if let golob_lib::GolobulError::RuntimeError { stderr, stdout, .. } = error {
+        error!("{stderr}");
         if let Some(stdout) = stdout {
             info!("{}", stdout.trim_end());
             out_data.set_return_msg(&format!(
-                "failed to load script: \n error: {stderr} \n stdout: {stdout}"
+                "{hint}failed to load script: \n error: {stderr} \n stdout: {stdout}"
             ));
         } else {
-            out_data.set_return_msg(&format!(" failed to load script: {stderr}"));
+            out_data.set_return_msg(&format!("{hint} failed to load script: {stderr}"));
         }
     } else {
         error!("{error:?}");
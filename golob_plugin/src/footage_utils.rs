@@ -1,8 +1,9 @@
 use crate::{background_task, PLUGIN_ID};
 use after_effects as ae;
 use golob_lib::ImageFormat;
-use image::{error::ImageError, ImageBuffer, Rgba};
-use std::path::PathBuf;
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::{DynamicImage, ImageBuffer, Luma, Rgb, Rgba};
+use std::path::{Path, PathBuf};
 
 pub struct FootageImportTask {
     pub comp_handle: ae::aegp::CompHandle,
@@ -89,6 +90,9 @@ pub fn import_footage(task: FootageImportTask) -> Result<(), ae::Error> {
         insertion_time,
     } = task;
 
+    // video renders leave a single movie in the output directory
+    let path = crate::video_encoder::find_movie(&path).unwrap_or(path);
+
     log::debug!("importing footage {path:?}.");
 
     let footage_suites = ae::aegp::suites::Footage::new()?;
@@ -151,6 +155,43 @@ pub fn get_project_dir() -> Option<PathBuf> {
     }
 }
 
+// `get_project_dir` hands back the .aep itself
+fn project_folder() -> Option<PathBuf> {
+    let project = get_project_dir()?;
+
+    if project.is_file() {
+        project.parent().map(Path::to_owned)
+    } else {
+        Some(project)
+    }
+}
+
+/// Relative to the project's folder, or unchanged if the project was never saved.
+pub fn relative_to_project(path: &Path) -> PathBuf {
+    project_folder()
+        .filter(|_| path.is_absolute())
+        .and_then(|folder| pathdiff::diff_paths(path, folder))
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Undoes `relative_to_project`, absolute paths are returned as is.
+pub fn resolve_from_project(path: &Path) -> PathBuf {
+    match project_folder() {
+        Some(folder) if path.is_relative() => folder.join(path),
+        _ => path.to_owned(),
+    }
+}
+
+/// Saves a path relative to the project, so projects can move between machines.
+pub fn serialize_project_relative<S: serde::Serializer>(
+    path: &Option<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::Serialize;
+
+    path.as_deref().map(relative_to_project).serialize(serializer)
+}
+
 /// Calculate the number of frames inside the region of interest currently selected by the user.
 pub fn get_region_of_interest_frame_count(in_data: &ae::InData) -> Result<u32, ae::Error> {
     let pf_interface = ae::aegp::suites::PFInterface::new()?;
@@ -165,6 +206,26 @@ pub fn get_region_of_interest_frame_count(in_data: &ae::InData) -> Result<u32, a
     Ok(frame_count as u32)
 }
 
+/// The name of the layer this effect is on and the (width, height) of its comp.
+pub fn get_layer_info(in_data: &ae::InData) -> Result<(String, (u32, u32)), ae::Error> {
+    let pf_interface = ae::aegp::suites::PFInterface::new()?;
+    let layer_suite = ae::aegp::suites::Layer::new()?;
+    let comp_suite = ae::aegp::suites::Comp::new()?;
+    let item_suite = ae::aegp::suites::Item::new()?;
+
+    let this_layer = pf_interface.effect_layer(in_data.effect())?;
+    let (name, source) = layer_suite.layer_name(this_layer, *PLUGIN_ID.get().unwrap())?;
+
+    let parent_comp = layer_suite.layer_parent_comp(this_layer)?;
+    let comp_item = comp_suite.item_from_comp(parent_comp)?;
+    let (width, height) = item_suite.item_dimensions(comp_item)?;
+
+    // unrenamed layers are called after their source
+    let name = if name.is_empty() { source } else { name };
+
+    Ok((name, (width as u32, height as u32)))
+}
+
 /// Get the project bit depth in Golobulus format.
 pub fn get_sequence_output_format() -> Result<golob_lib::ImageFormat, ae::Error> {
     let proj_suites = ae::aegp::suites::Project::new()?;
@@ -211,42 +272,224 @@ pub fn output_dir_name(
     Ok(output_dir)
 }
 
-/// Writes an image to a file with an appropriate format.
-/// given its bit depth.
+/// File format of the frames of a sequential render, matches the
+/// order of the options in the sequence format popup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceFormat {
+    /// PNG for 8 and 16 bit projects, EXR for 32 bit
+    MatchProject,
+    Png,
+    Exr,
+    Tiff,
+}
+
+impl SequenceFormat {
+    pub const OPTIONS: [&'static str; 4] = ["Match Project", "PNG", "EXR", "TIFF"];
+
+    /// From the 1 based popup value.
+    pub fn from_popup(value: i32) -> Self {
+        match value {
+            2 => Self::Png,
+            3 => Self::Exr,
+            4 => Self::Tiff,
+            _ => Self::MatchProject,
+        }
+    }
+}
+
+/// What the alpha of a rendered frame means, see `ctx.set_alpha_mode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// pixels are written as they are
+    #[default]
+    Straight,
+    /// color was multiplied by alpha, PNG and TIFF are read as straight so it's
+    /// divided out again for them. EXR is premultiplied already and left alone.
+    Premultiplied,
+}
+
+impl AlphaMode {
+    /// The layers come in premultiplied and the frames go out as RGBA, which is
+    /// never premultiplied again. Only scripts in straight mode undo it on the way.
+    pub fn for_frames(straight_script: bool, layer_fmt: ImageFormat) -> Self {
+        if straight_script || !layer_fmt.is_premultiplied() {
+            AlphaMode::Straight
+        } else {
+            AlphaMode::Premultiplied
+        }
+    }
+}
+
+/// Writes an image to a file in `file_format`, converting the pixels
+/// if the file can't hold the bit depth of the image. Floats are clamped
+/// when written to integer formats.
 pub fn write_image_to_file(
     mut path: PathBuf,
     image: &[u8],
     width: u32,
     height: u32,
     fmt: ImageFormat,
+    file_format: SequenceFormat,
+    alpha: AlphaMode,
 ) -> Result<(), ImageError> {
-    match fmt {
-        ImageFormat::Rgba8 | ImageFormat::Argb8 => {
-            let buf =
-                ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, bytemuck::cast_slice(image))
-                    .unwrap();
-            path.set_extension("png");
-            buf.save(path)?;
+    let mismatch =
+        || ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch));
+
+    if image.len() != width as usize * height as usize * fmt.bytes_per_pixel() {
+        return Err(mismatch());
+    }
+
+    // copies so the wider types don't have to be aligned in `image`
+    let mut img = match fmt {
+        ImageFormat::Rgba8 => DynamicImage::ImageRgba8(
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, image.to_vec())
+                .ok_or_else(mismatch)?,
+        ),
+        ImageFormat::Argb8 => DynamicImage::ImageRgba8(
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, argb_to_rgba(image))
+                .ok_or_else(mismatch)?,
+        ),
+        ImageFormat::Rgba16 => {
+            let data = bytemuck::pod_collect_to_vec(image);
+            DynamicImage::ImageRgba16(
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, data).ok_or_else(mismatch)?,
+            )
+        }
+        ImageFormat::Argb16ae => {
+            // AE's 16 bit runs to 32768, stretch it to the full range
+            let data = argb_to_rgba(&bytemuck::pod_collect_to_vec::<u8, u16>(image))
+                .into_iter()
+                .map(|v| ((v.min(32768) as u32 * 65535 + 16384) / 32768) as u16)
+                .collect();
+            DynamicImage::ImageRgba16(
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, data).ok_or_else(mismatch)?,
+            )
         }
-        ImageFormat::Argb16ae | ImageFormat::Rgba16 => {
-            let buf =
-                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, bytemuck::cast_slice(image))
-                    .unwrap();
-            path.set_extension("png");
-            buf.save(path)?;
+        ImageFormat::Rgba32 => {
+            let data = bytemuck::pod_collect_to_vec(image);
+            DynamicImage::ImageRgba32F(
+                ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, data).ok_or_else(mismatch)?,
+            )
         }
-        ImageFormat::Argb32 | ImageFormat::Rgba32 => {
-            let buf =
-                ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, bytemuck::cast_slice(image))
-                    .unwrap();
-            path.set_extension("exr");
-            buf.save(path)?;
+        ImageFormat::Argb32 => {
+            let data = argb_to_rgba(&bytemuck::pod_collect_to_vec::<u8, f32>(image));
+            DynamicImage::ImageRgba32F(
+                ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, data).ok_or_else(mismatch)?,
+            )
+        }
+        ImageFormat::Gray8 => DynamicImage::ImageLuma8(
+            ImageBuffer::<Luma<u8>, _>::from_raw(width, height, image.to_vec())
+                .ok_or_else(mismatch)?,
+        ),
+        ImageFormat::Rgb8 => DynamicImage::ImageRgb8(
+            ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, image.to_vec())
+                .ok_or_else(mismatch)?,
+        ),
+        ImageFormat::Gray16 => {
+            let data = bytemuck::pod_collect_to_vec(image);
+            DynamicImage::ImageLuma16(
+                ImageBuffer::<Luma<u16>, _>::from_raw(width, height, data).ok_or_else(mismatch)?,
+            )
+        }
+    };
+
+    let is_float = matches!(img, DynamicImage::ImageRgba32F(_));
+    let is_exr = match file_format {
+        SequenceFormat::MatchProject => is_float,
+        SequenceFormat::Exr => true,
+        SequenceFormat::Png | SequenceFormat::Tiff => false,
+    };
+
+    // before narrowing, so dark semi-transparent floats keep their precision
+    if alpha == AlphaMode::Premultiplied && !is_exr {
+        unpremultiply(&mut img);
+    }
+
+    let (img, extension, format) = match file_format {
+        SequenceFormat::MatchProject if is_float => (img, "exr", image::ImageFormat::OpenExr),
+        SequenceFormat::MatchProject | SequenceFormat::Png => {
+            (narrow_float(img)?, "png", image::ImageFormat::Png)
         }
+        SequenceFormat::Exr => (
+            DynamicImage::ImageRgba32F(img.into_rgba32f()),
+            "exr",
+            image::ImageFormat::OpenExr,
+        ),
+        SequenceFormat::Tiff => (narrow_float(img)?, "tiff", image::ImageFormat::Tiff),
     };
 
+    path.set_extension(extension);
+    img.save_with_format(path, format)?;
+
     Ok(())
 }
 
+// Divides the color of an RGBA image by its alpha, for writing premultiplied
+// frames to files that are read as straight. Fully transparent pixels are left
+// alone and images without alpha aren't changed.
+fn unpremultiply(img: &mut DynamicImage) {
+    match img {
+        DynamicImage::ImageRgba8(img) => {
+            for px in img.pixels_mut() {
+                let a = px[3] as u32;
+                if a != 0 {
+                    for c in &mut px.0[..3] {
+                        *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+                    }
+                }
+            }
+        }
+        DynamicImage::ImageRgba16(img) => {
+            for px in img.pixels_mut() {
+                let a = px[3] as u64;
+                if a != 0 {
+                    for c in &mut px.0[..3] {
+                        *c = ((*c as u64 * 65535 + a / 2) / a).min(65535) as u16;
+                    }
+                }
+            }
+        }
+        // floats aren't clamped, hdr color can be brighter than its alpha
+        DynamicImage::ImageRgba32F(img) => {
+            for px in img.pixels_mut() {
+                let a = px[3];
+                if a > 0.0 {
+                    for c in &mut px.0[..3] {
+                        *c /= a;
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn argb_to_rgba<T: Copy>(pixels: &[T]) -> Vec<T> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|p| [p[1], p[2], p[3], p[0]])
+        .collect()
+}
+
+// png and tiff top out at 16 bits, floats are clamped rather than reinterpreted
+fn narrow_float(img: DynamicImage) -> Result<DynamicImage, ImageError> {
+    let DynamicImage::ImageRgba32F(img) = img else {
+        return Ok(img);
+    };
+
+    let data = img
+        .as_raw()
+        .iter()
+        .map(|f| (f.clamp(0.0, 1.0) * 65535.0).round() as u16)
+        .collect();
+
+    ImageBuffer::<Rgba<u16>, _>::from_raw(img.width(), img.height(), data)
+        .map(DynamicImage::ImageRgba16)
+        .ok_or_else(|| {
+            ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+        })
+}
+
 /// Creates a directory with name `path` or a suffixed number if it already exists.
 pub fn create_suffixed_directory(path: &std::path::Path) -> PathBuf {
     let mut suffix = 1;
@@ -262,3 +505,55 @@ pub fn create_suffixed_directory(path: &std::path::Path) -> PathBuf {
     std::fs::create_dir(&new_path).unwrap();
     new_path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one RGBA8 pixel through a PNG and back
+    fn write_and_read(name: &str, pixel: &[u8], alpha: AlphaMode) -> Vec<u8> {
+        let path = std::env::temp_dir().join(name);
+        let fmt = ImageFormat::Rgba8;
+        write_image_to_file(path.clone(), pixel, 1, 1, fmt, SequenceFormat::Png, alpha).unwrap();
+
+        let path = path.with_extension("png");
+        let saved = image::open(&path).unwrap().to_rgba8().into_raw();
+        let _ = std::fs::remove_file(&path);
+        saved
+    }
+
+    #[test]
+    fn premultiplied_frames_saved_straight() {
+        // a half transparent mid gray, premultiplied
+        let pixel = [64u8, 64, 64, 128];
+
+        let alpha = AlphaMode::for_frames(false, ImageFormat::Argb8);
+        assert_eq!(alpha, AlphaMode::Premultiplied);
+        let saved = write_and_read("golob_premultiplied_frame", &pixel, alpha);
+        assert_eq!(saved, [128, 128, 128, 128]);
+
+        // straight scripts already undid it, dividing again would save 255
+        let alpha = AlphaMode::for_frames(true, ImageFormat::Argb8);
+        let saved = write_and_read("golob_straight_frame", &pixel, alpha);
+        assert_eq!(saved, pixel);
+    }
+
+    #[test]
+    fn unpremultiply_formats() {
+        // a fully transparent pixel is left alone
+        let pixels = vec![64u8, 64, 64, 128, 0, 0, 0, 0];
+        let mut img = DynamicImage::ImageRgba8(ImageBuffer::from_raw(2, 1, pixels).unwrap());
+        unpremultiply(&mut img);
+        assert_eq!(img.as_bytes(), &[128, 128, 128, 128, 0, 0, 0, 0]);
+
+        let pixels = vec![16384u16, 0, 32768, 32768];
+        let mut img = DynamicImage::ImageRgba16(ImageBuffer::from_raw(1, 1, pixels).unwrap());
+        unpremultiply(&mut img);
+        assert_eq!(img.as_rgba16().unwrap().as_raw(), &[32768, 0, 65535, 32768]);
+
+        let pixels = vec![0.25f32, 0.5, 0.75, 0.5];
+        let mut img = DynamicImage::ImageRgba32F(ImageBuffer::from_raw(1, 1, pixels).unwrap());
+        unpremultiply(&mut img);
+        assert_eq!(img.as_rgba32f().unwrap().as_raw(), &[0.5, 1.0, 1.5, 0.5]);
+    }
+}
@@ -6,7 +6,11 @@ use after_effects::{self as ae, *};
 use after_effects_sys as ae_sys;
 use dashmap::try_result::TryResult;
 use golob_lib::PythonRunner;
-use std::{sync::Arc, vec};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+    vec,
+};
 use sys::SPBasicSuite;
 
 type IdleHook<T> = fn(&mut T, &mut i32) -> Result<(), Error>;
@@ -15,14 +19,27 @@ struct IdleData<T> {
     data: T,
 }
 
+// Jobs are only created while a tick has spent less than this, gathering
+// layer pixels blocks AE's event loop.
+const TICK_BUDGET: Duration = Duration::from_millis(16);
+
+// Bounds on the sleep hint we hand AE while renders are in flight, in ms.
+const MIN_ACTIVE_SLEEP: i32 = 1;
+const MAX_ACTIVE_SLEEP: i32 = 33;
+
 // This function runs periodically *on the main thread*. At startlingly
 // regular intervals, usually around every three frames unless instructed otherwise.
 // It's important to keep this under about 16 ms of execution time because it
-// blocks the event loop.
+// blocks the event loop. While a render is in flight we instruct AE to call
+// back about as soon as it has had the main thread for as long as we just took,
+// so frames are dispatched as soon as a worker is free.
 fn idle_callback(
     idle_task_info: &mut IdleTaskInfo,
-    _max_sleep_time: &mut i32,
+    max_sleep_time: &mut i32,
 ) -> Result<(), Error> {
+    let started = Instant::now();
+    let mut in_flight = false;
+
     let keys = idle_task_info
         .task_map
         .iter()
@@ -40,8 +57,18 @@ fn idle_callback(
         };
 
         match &background_task.status {
-            crate::background_task::TaskStatus::Busy => continue,
+            crate::background_task::TaskStatus::Busy => {
+                in_flight = true;
+                continue;
+            }
             crate::background_task::TaskStatus::Ready => {
+                in_flight = true;
+
+                if started.elapsed() >= TICK_BUDGET {
+                    log::debug!("idle tick over budget, bg task {key} waits for the next one.");
+                    continue;
+                }
+
                 log::debug!("bg task {key} ready, sending a job.");
                 let res: Result<(), ae::Error> = crate::MAIN_THREAD_IDLE_DATA.with(|data| {
                     let mut data = data.borrow_mut();
@@ -65,6 +92,10 @@ fn idle_callback(
                 }
 
                 res?;
+
+                // the worker only marks itself busy once it picks the job up,
+                // without this a quick next tick would queue a second frame
+                background_task.status = crate::background_task::TaskStatus::Busy;
             }
             crate::background_task::TaskStatus::Done => {
                 log::debug!("bg task {key} done, loading footage.");
@@ -119,6 +150,12 @@ fn idle_callback(
         adv_app.refresh_all_windows()?;
     }
 
+    if in_flight {
+        let spent = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+        *max_sleep_time = spent.clamp(MIN_ACTIVE_SLEEP, MAX_ACTIVE_SLEEP);
+        log::trace!("idle tick took {spent}ms, sleeping at most {max_sleep_time}ms");
+    }
+
     // This forces the ui to update *immediately*
     // so we have to make sure we aren't holding any locks.
     Ok(())
@@ -204,7 +241,7 @@ impl TaskCreationCtx {
                 false,
             )?;
 
-            if let (golob_lib::Variant::Image(_), ae::aegp::StreamValue::LayerId(id)) =
+            if let (golob_lib::Variant::Image(..), ae::aegp::StreamValue::LayerId(id)) =
                 (variant, stream_val)
             {
                 // Is None,
@@ -242,6 +279,7 @@ impl TaskCreationCtx {
         let job = background_task::TaskMessage::Job {
             inputs,
             time: self.current_time.value as f32 / self.current_time.scale as f32,
+            fps: self.current_time.scale as f32 / self.time_step as f32,
             frame: self.current_frame,
         };
 
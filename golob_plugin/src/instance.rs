@@ -5,22 +5,28 @@ use crate::footage_utils;
 use crate::footage_utils::create_suffixed_directory;
 use crate::idle_task;
 use crate::param_util;
+use crate::temporal_checkout_id;
+use crate::video_encoder::OutputContainer;
 use crate::GlobalPlugin;
 use crate::ParamIdx;
 use crate::PluginState;
 use crate::INPUT_LAYER_CHECKOUT_ID;
+use crate::MAX_TEMPORAL_WINDOW;
 use after_effects as ae;
 use after_effects::*;
 use after_effects_sys as ae_sys;
-use golob_lib::{ImageFormat, InDesc, OutDesc, PythonRunner};
+use golob_lib::{CancellationToken, ImageFormat, InDesc, OutDesc, PythonRunner};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 pub type InstanceId = usize;
 
+#[derive(Default)]
 pub struct DebugContents {
     pub error: Option<String>,
     pub stdout: Option<String>,
+    // `ctx.set_status` values, one per line
+    pub status: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -29,13 +35,75 @@ pub struct Instance {
     pub runner: PythonRunner,
     pub src: Option<String>,
     pub last_known_path: Option<PathBuf>,
+    // absolute while loaded, saved relative to the project once it has been saved
+    #[serde(serialize_with = "footage_utils::serialize_project_relative")]
     pub venv_path: Option<PathBuf>,
     pub id: InstanceId,
     #[serde(skip_serializing, skip_deserializing)]
     pub job_id: Option<JobId>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub cancel_token: Option<CancellationToken>,
+    // the last name from `ctx.set_name` we labelled the effect with, saved so
+    // reopening a project doesn't clobber a name the user typed in since
+    pub effect_name: Option<String>,
+    // the last `ctx.set_image_filter_default` the checkbox was reset to, None is
+    // the param's own default of ticked. Saved so reopening keeps the user's choice
+    pub image_filter_default: Option<bool>,
+    // projects from before popups held their 1 based position stored the tag's value,
+    // read that way until `param_util::migrate_legacy_popups` rewrites them
+    pub legacy_popups: bool,
+}
+
+// What `flatten` wrote as version 1, before anything past `id` was saved.
+#[derive(Deserialize)]
+pub struct InstanceV1 {
+    src: Option<String>,
+    last_known_path: Option<PathBuf>,
+    venv_path: Option<PathBuf>,
+    id: InstanceId,
+}
+
+impl From<InstanceV1> for Instance {
+    fn from(old: InstanceV1) -> Self {
+        Self {
+            src: old.src,
+            last_known_path: old.last_known_path,
+            venv_path: old.venv_path,
+            id: old.id,
+            legacy_popups: true,
+            ..Default::default()
+        }
+    }
 }
 
 impl Instance {
+    /// Loads a script, refusing ones with more inputs than we have params for.
+    pub fn load_script(
+        &mut self,
+        src: &str,
+        file_name: Option<String>,
+    ) -> Result<Option<String>, golob_lib::GolobulError> {
+        self.configure_runner();
+        self.runner.load_script(src, file_name)
+    }
+
+    /// Like `load_script`, but recompiles even if the source hasn't changed.
+    pub fn reload_script(
+        &mut self,
+        src: &str,
+        file_name: Option<String>,
+    ) -> Result<Option<String>, golob_lib::GolobulError> {
+        self.configure_runner();
+        self.runner.reload_script(src, file_name)
+    }
+
+    // what this host supports, before setup runs
+    fn configure_runner(&mut self) {
+        self.runner
+            .set_max_inputs(Some(param_util::MAX_INPUTS as usize));
+        self.runner.set_sequential_supported(true);
+    }
+
     pub fn launch_script_dialog(&mut self, out_data: &mut OutData) -> Result<(), Error> {
         let projec_dir = footage_utils::get_project_dir();
 
@@ -69,8 +137,25 @@ impl Instance {
             self.runner.set_script_parent_directory(parent);
         }
 
-        self.runner
-            .load_script(&source, Some(file_path.clone()))
+        let requirements = file.with_file_name("requirements.txt");
+
+        // pip can take minutes and AE is frozen while we're in here, so the script loads
+        // without waiting. If it's missing packages it can be reloaded once they're in.
+        if requirements.exists() {
+            log::info!("installing {requirements:?} in the background");
+            let runner = self.runner.clone();
+            std::thread::spawn(move || match runner.ensure_requirements(&requirements) {
+                Ok(out) => {
+                    if let Some(out) = out {
+                        log::info!("{}", out.trim_end());
+                    }
+                    log::info!("requirements installed, reload the script if it failed to load");
+                }
+                Err(e) => log::warn!("couldn't install {requirements:?}: {e}"),
+            });
+        }
+
+        self.load_script(&source, Some(file_path.clone()))
             .map_err(|e| {
                 crate::error::startup_error_message(e, out_data);
                 Error::None
@@ -113,33 +198,44 @@ impl Instance {
         global: &GlobalPlugin,
         out_data: &mut OutData,
     ) -> Result<(), Error> {
-        if let Some(ref file_path) = self.last_known_path {
-            let source = std::fs::read_to_string(file_path).ok().unwrap_or_default();
-
-            if source.is_empty() {
-                return Err(Error::Generic);
+        let file_name = self
+            .last_known_path
+            .as_ref()
+            .and_then(|path| path.file_name()?.to_str().map(str::to_owned));
+
+        let on_disk = self
+            .last_known_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .filter(|source| !source.is_empty());
+
+        let source = match (on_disk, self.src.clone()) {
+            (Some(source), _) => source,
+            // The script was moved or deleted, the copy saved with the project
+            // keeps the comp rendering.
+            (None, Some(embedded)) => {
+                out_data.set_return_msg(
+                    "Could not read the script from disk, reloaded the copy saved in the project.",
+                );
+                embedded
             }
+            (None, None) => return Err(Error::Generic),
+        };
 
-            let file_path = file_path.file_name().unwrap().to_str().unwrap();
-
-            self.runner
-                .load_script(&source, Some(file_path.to_owned()))
-                .map_err(|e| {
-                    crate::error::startup_error_message(e, out_data);
-                    Error::Generic
-                })?;
+        self.reload_script(&source, file_name).map_err(|e| {
+            crate::error::startup_error_message(e, out_data);
+            Error::Generic
+        })?;
 
-            global.errors.get_mut(&self.id).map(|mut c| c.clear());
-            self.src = Some(source);
-            Ok(())
-        } else {
-            Err(Error::Generic)
-        }
+        global.errors.get_mut(&self.id).map(|mut c| c.clear());
+        self.src = Some(source);
+        Ok(())
     }
 
     pub fn smart_render(
         &mut self,
         in_data: &InData,
+        out_data: &mut OutData,
         global: &GlobalPlugin,
         cb: &SmartRenderCallbacks,
     ) -> Result<(), Error> {
@@ -150,7 +246,28 @@ impl Instance {
             return Ok(());
         }
 
-        let layers = crate::param_util::set_params(in_data, &mut self.runner)?;
+        let layers = crate::param_util::set_params(in_data, &mut self.runner, self.legacy_popups)?;
+
+        let window = self.runner.temporal_window().min(MAX_TEMPORAL_WINDOW as u32) as i32;
+
+        // frames missing here were past the ends of the comp in pre render
+        let neighbours: Vec<_> = layers
+            .iter()
+            .flat_map(|(name, index)| {
+                let layer_param = if *index == INPUT_LAYER_CHECKOUT_ID {
+                    0
+                } else {
+                    index.idx()
+                };
+
+                (-window..=window)
+                    .filter(|offset| *offset != 0)
+                    .filter_map(move |offset| {
+                        let id = temporal_checkout_id(layer_param, offset) as u32;
+                        Some((name, offset, cb.checkout_layer_pixels(id).ok()?))
+                    })
+            })
+            .collect();
 
         let layers: Vec<_> = layers
             .iter()
@@ -168,10 +285,22 @@ impl Instance {
         // margins.
         out_layer.fill(None, None)?;
 
+        let bit_depth = out_layer.bit_depth();
+        let Ok(out_fmt) = format(bit_depth) else {
+            // rather than crash the host, show the input untouched if we have it
+            if let Ok(in_layer) = cb.checkout_layer_pixels(INPUT_LAYER_CHECKOUT_ID.idx() as u32) {
+                out_layer.copy_from(&in_layer, None, None)?;
+            }
+            out_data.set_return_msg(&format!(
+                "Golobulus: {bit_depth} bit color isn't supported, the frame was passed through."
+            ));
+            return Ok(());
+        };
+
         let stride = out_layer.buffer_stride();
 
         let output = OutDesc {
-            fmt: format(out_layer.bit_depth()),
+            fmt: out_fmt,
             width: out_layer.width() as u32,
             height: out_layer.height() as u32,
             data: out_layer.buffer_mut(),
@@ -179,11 +308,16 @@ impl Instance {
         };
 
         let e = {
+            // AE only gets the primary output, named ones are written to scratch space
             let mut pass = self.runner.create_render_pass(output);
 
             for (name, layer) in layers.iter() {
+                let Ok(fmt) = format(layer.bit_depth()) else {
+                    continue;
+                };
+
                 let i = InDesc {
-                    fmt: format(layer.bit_depth()),
+                    fmt,
                     width: layer.width() as u32,
                     height: layer.height() as u32,
                     data: layer.buffer(),
@@ -192,7 +326,22 @@ impl Instance {
                 pass.load_input(i, name);
             }
 
-            pass.submit()
+            for (name, offset, layer) in neighbours.iter() {
+                let Ok(fmt) = format(layer.bit_depth()) else {
+                    continue;
+                };
+
+                let i = InDesc {
+                    fmt,
+                    width: layer.width() as u32,
+                    height: layer.height() as u32,
+                    data: layer.buffer(),
+                    stride: Some(layer.buffer_stride() as u32),
+                };
+                pass.load_input_at(i, name, *offset);
+            }
+
+            pass.submit().map(|report| report.stdout)
         };
 
         // If we fail, we just become a transparent layer and log the error
@@ -217,52 +366,100 @@ impl Instance {
         let time_step = in_data.time_step();
         let time_scale = in_data.time_scale();
 
-        for (index, (_, v)) in self
+        req.field = ae_sys::PF_Field_FRAME as i32;
+        req.preserve_rgb_of_zero_alpha = 1;
+        req.channel_mask = ae_sys::PF_ChannelMask_ARGB as i32;
+
+        let mut result_rect = None;
+
+        // the checkbox may be keyframed, generators at this time don't need the layer
+        let uses_input_layer = self.runner.is_sequential()
+            || crate::param_util::is_image_filter_at(in_data, current_time)?;
+
+        // We checkout once just to see what the max rect is :(
+        if let Ok(width_test) = cb.checkout_layer(
+            0,
+            INPUT_LAYER_CHECKOUT_ID.idx() - 1,
+            &req,
+            current_time,
+            time_step,
+            time_scale,
+        ) {
+            req.rect = width_test.max_result_rect;
+            result_rect = Some(width_test.result_rect);
+
+            if uses_input_layer {
+                let full_checkout = cb.checkout_layer(
+                    0,
+                    INPUT_LAYER_CHECKOUT_ID.idx(),
+                    &req,
+                    current_time,
+                    time_step,
+                    time_scale,
+                )?;
+
+                result_rect = Some(full_checkout.result_rect);
+            }
+        }
+
+        let image_params: Vec<i32> = self
             .runner
             .iter_inputs()
             .enumerate()
-            .filter(|(_, (_, v))| matches!(v, golob_lib::Variant::Image(_)))
-        {
-            let id_and_index = crate::param_util::as_param_index(index, v).idx();
+            .filter(|(_, (_, v))| matches!(v, golob_lib::Variant::Image(..)))
+            .map(|(index, (_, v))| crate::param_util::as_param_index(index, v).idx())
+            .collect();
 
-            cb.checkout_layer(
+        // every image input is checked out under its own param index,
+        // inputs with no layer selected just don't contribute to the rect.
+        for &id_and_index in image_params.iter() {
+            if let Ok(checkout) = cb.checkout_layer(
                 id_and_index,
                 id_and_index,
                 &req,
                 current_time,
                 time_step,
                 time_scale,
-            )?;
+            ) {
+                result_rect = Some(match result_rect {
+                    Some(rect) => union_rect(rect, checkout.result_rect),
+                    None => checkout.result_rect,
+                });
+            }
         }
 
-        req.field = ae_sys::PF_Field_FRAME as i32;
-        req.preserve_rgb_of_zero_alpha = 1;
-        req.channel_mask = ae_sys::PF_ChannelMask_ARGB as i32;
+        let window = self.runner.temporal_window().min(MAX_TEMPORAL_WINDOW as u32) as i32;
 
-        // We checkout once just to see what the max rect is :(
-        if let Ok(width_test) = cb.checkout_layer(
-            0,
-            INPUT_LAYER_CHECKOUT_ID.idx() - 1,
-            &req,
-            in_data.current_time(),
-            in_data.time_step(),
-            in_data.time_scale(),
-        ) {
-            req.rect = width_test.max_result_rect;
+        if window > 0 {
+            let input_layer = uses_input_layer.then_some(0);
 
-            let full_checkout = cb.checkout_layer(
-                0,
-                INPUT_LAYER_CHECKOUT_ID.idx(),
-                &req,
-                in_data.current_time(),
-                in_data.time_step(),
-                in_data.time_scale(),
-            )?;
+            for layer_param in input_layer.into_iter().chain(image_params) {
+                for offset in (-window..=window).filter(|offset| *offset != 0) {
+                    let time = current_time + offset * time_step;
+
+                    // scripts get None for frames past the ends of the comp
+                    if time < 0 || time >= in_data.total_time() {
+                        continue;
+                    }
+
+                    let _ = cb.checkout_layer(
+                        layer_param,
+                        temporal_checkout_id(layer_param, offset),
+                        &req,
+                        time,
+                        time_step,
+                        time_scale,
+                    );
+                }
+            }
+        }
 
-            extra.set_result_rect(full_checkout.result_rect.into());
-            extra.set_max_result_rect(full_checkout.result_rect.into());
+        if let Some(rect) = result_rect {
+            extra.set_result_rect(rect.into());
+            extra.set_max_result_rect(rect.into());
             extra.set_returns_extra_pixels(true);
         }
+
         Ok(())
     }
 
@@ -328,6 +525,12 @@ impl Instance {
                 plugin.out_data.set_force_rerender();
             }
             ParamIdx::CancelRender => {
+                // The worker holds its task map entry while a frame renders,
+                // so stop the frame first or we'd wait for it to finish.
+                if let Some(token) = self.cancel_token.take() {
+                    token.cancel();
+                }
+
                 if let Some(job_id) = self.job_id {
                     if let Some(task) = plugin.global.task_map.get(&job_id) {
                         task.cancel();
@@ -335,6 +538,9 @@ impl Instance {
                 }
             }
             ParamIdx::StartRender => {
+                // the render reads the popups straight from their streams
+                param_util::migrate_legacy_popups(plugin, self)?;
+
                 let Some(project_path) = footage_utils::get_project_dir() else {
                     plugin.out_data.set_return_msg("You must save before triggering a golobulus render. This operation will create footage next to your project file.");
                     return Ok(());
@@ -356,6 +562,22 @@ impl Instance {
                     footage_utils::output_dir_name(&plugin.in_data.effect_ref(), directory)?;
 
                 let fmt = footage_utils::get_sequence_output_format()?;
+                let container = OutputContainer::from_popup(
+                    plugin
+                        .params
+                        .get(ParamIdx::OutputContainer)?
+                        .as_popup()?
+                        .value(),
+                );
+                let file_format = footage_utils::SequenceFormat::from_popup(
+                    plugin
+                        .params
+                        .get(ParamIdx::SequenceFormat)?
+                        .as_popup()?
+                        .value(),
+                );
+                let straight = self.runner.straight_alpha();
+                let alpha = footage_utils::AlphaMode::for_frames(straight, fmt);
                 let frame_count =
                     footage_utils::get_region_of_interest_frame_count(&plugin.in_data)?;
 
@@ -408,18 +630,21 @@ impl Instance {
 
                 e?;
 
-                BackgroundTask::spawn_task(
+                self.cancel_token = Some(BackgroundTask::spawn_task(
                     plugin.global.current_id,
                     self.runner.clone(),
                     OutputDesc {
                         directory,
                         fmt,
+                        container,
+                        file_format,
+                        alpha,
                         last_frame: frame_count,
                         width: plugin.in_data.width() as u32,
                         height: plugin.in_data.height() as u32,
                     },
                     plugin.global.task_map.clone(),
-                );
+                ));
             }
             ParamIdx::IsImageFilter => {
                 let is_image_filter = plugin
@@ -432,7 +657,7 @@ impl Instance {
                     .runner
                     .iter_inputs()
                     .enumerate()
-                    .find(|(_, (_, i))| matches!(i, golob_lib::Variant::Image(_)));
+                    .find(|(_, (_, i))| matches!(i, golob_lib::Variant::Image(..)));
 
                 if let Some((i, (_, ty))) = first_image {
                     let index = param_util::as_param_index(i, ty);
@@ -446,21 +671,51 @@ impl Instance {
                     param_util::set_param_visibility(plugin.in_data, index, !is_image_filter)?;
 
                     param_util::update_input_visibilities(plugin, self)?;
-                    plugin.out_data.set_force_rerender();
                 }
+
+                // pre render checks out different layers depending on it
+                plugin.out_data.set_force_rerender();
             }
-            ParamIdx::ShowDebug | ParamIdx::DebugOffset | ParamIdx::TemporalWindow => {}
+            ParamIdx::ShowDebug
+            | ParamIdx::DebugOffset
+            | ParamIdx::TemporalWindow
+            | ParamIdx::OutputContainer
+            | ParamIdx::SequenceFormat => {}
             _ => {}
         };
         Ok(())
     }
 }
 
-pub fn format(bit_depth: i16) -> ImageFormat {
+pub fn format(bit_depth: i16) -> Result<ImageFormat, Error> {
     match bit_depth {
-        8 => ImageFormat::Argb8,
-        16 => ImageFormat::Argb16ae,
-        32 => ImageFormat::Argb32,
-        _ => unreachable!(),
+        8 => Ok(ImageFormat::Argb8),
+        16 => Ok(ImageFormat::Argb16ae),
+        32 => Ok(ImageFormat::Argb32),
+        _ => {
+            log::error!(
+                "After Effects handed us a {bit_depth} bit world, we only know 8, 16 and 32"
+            );
+            Err(Error::Generic)
+        }
+    }
+}
+
+fn union_rect(a: ae_sys::PF_LRect, b: ae_sys::PF_LRect) -> ae_sys::PF_LRect {
+    let is_empty = |r: &ae_sys::PF_LRect| r.left >= r.right || r.top >= r.bottom;
+
+    if is_empty(&b) {
+        return a;
+    }
+
+    if is_empty(&a) {
+        return b;
+    }
+
+    ae_sys::PF_LRect {
+        left: a.left.min(b.left),
+        top: a.top.min(b.top),
+        right: a.right.max(b.right),
+        bottom: a.bottom.max(b.bottom),
     }
 }
@@ -6,6 +6,7 @@ pub(crate) mod instance;
 pub(crate) mod param_util;
 mod setup_env;
 pub(crate) mod ui;
+pub(crate) mod video_encoder;
 
 use after_effects as ae;
 use after_effects_sys as ae_sys;
@@ -37,6 +38,12 @@ pub enum ParamIdx {
     ContinuousRenderGroupEnd,
     ParametersStart,
     ParametersEnd,
+    ExtraParametersStart,
+    ExtraParametersEnd,
+    OutputGroupBegin,
+    OutputContainer,
+    SequenceFormat,
+    OutputGroupEnd,
     Dynamic(i32),
 }
 
@@ -65,7 +72,19 @@ impl GlobalPlugin {
     }
 }
 
-const INPUT_LAYER_CHECKOUT_ID: ParamIdx = ParamIdx::Dynamic(255);
+// Checkout ids share a namespace with the dynamic params, which check out
+// under their own index, so keep this one past the end of them.
+const INPUT_LAYER_CHECKOUT_ID: ParamIdx = ParamIdx::Dynamic(param_util::LAST_PARAM.idx() + 2);
+
+// Most frames on either side we'll check out for `ctx.get_input_at`
+const MAX_TEMPORAL_WINDOW: i32 = 16;
+
+/// Checkout id for a neighbouring frame of a layer param, 0 being our own layer.
+/// Every layer gets a block of ids past the input layer checkout.
+fn temporal_checkout_id(layer_param: i32, frame_offset: i32) -> i32 {
+    let block = layer_param * (2 * MAX_TEMPORAL_WINDOW + 1);
+    INPUT_LAYER_CHECKOUT_ID.idx() + 1 + block + frame_offset + MAX_TEMPORAL_WINDOW
+}
 
 static PLUGIN_ID: std::sync::OnceLock<i32> = std::sync::OnceLock::new();
 
@@ -90,6 +109,7 @@ impl AdobePluginGlobal for GlobalPlugin {
     ) -> Result<(), ae::Error> {
         param_util::setup_static_params(params)?;
         param_util::create_variant_backing(params)?;
+        param_util::setup_output_params(params)?;
 
         in_data.interact().register_ui(
             CustomUIInfo::new().events(ae::CustomEventFlags::COMP | ae::CustomEventFlags::EFFECT),
@@ -161,21 +181,22 @@ impl AdobePluginInstance for Instance {
             }
             Command::SmartRender { extra } => {
                 let cb = extra.callbacks();
-                self.smart_render(&plugin.in_data, plugin.global, &cb)?;
+                self.smart_render(&plugin.in_data, &mut plugin.out_data, plugin.global, &cb)?;
             }
             Command::SequenceSetup => {
                 self.id = fastrand::usize(..);
                 if let Some(src) = self.src.clone() {
-                    self.runner.load_script(src, None).map_err(|e| {
+                    self.load_script(&src, None).map_err(|e| {
                         error::startup_error_message(e, &mut plugin.out_data);
                         Error::Generic
                     })?;
                 }
             }
             Command::SequenceResetup => {
-                if let Some(venv_path) = self.venv_path.as_ref() {
-                    let venv_path = std::path::PathBuf::from(venv_path);
-                    self.runner.set_venv_path(venv_path);
+                if let Some(venv_path) = self.venv_path.take() {
+                    let venv_path = footage_utils::resolve_from_project(&venv_path);
+                    self.runner.set_venv_path(venv_path.clone());
+                    self.venv_path = Some(venv_path);
                 }
 
                 if let Some(last_known) = self.last_known_path.as_ref() {
@@ -186,7 +207,7 @@ impl AdobePluginInstance for Instance {
                 }
 
                 if let Some(src) = self.src.clone() {
-                    self.runner.load_script(src, None).map_err(|e| {
+                    self.load_script(&src, None).map_err(|e| {
                         error::startup_error_message(e, &mut plugin.out_data);
                         Error::Generic
                     })?;
@@ -203,12 +224,18 @@ impl AdobePluginInstance for Instance {
             log::error!("Serialization Error");
             Error::Generic
         })?;
-        Ok((1, out))
+        Ok((2, out))
     }
 
     fn unflatten(version: u16, serialized: &[u8]) -> Result<Self, Error> {
         match version {
+            // bincode is positional, older layouts need their own struct
             1 => {
+                let out: instance::InstanceV1 =
+                    bincode::deserialize(serialized).map_err(|_| Error::Generic)?;
+                Ok(out.into())
+            }
+            2 => {
                 let out: Self = bincode::deserialize(serialized).map_err(|_| Error::Generic)?;
                 Ok(out)
             }
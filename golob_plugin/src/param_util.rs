@@ -1,3 +1,5 @@
+use crate::footage_utils::SequenceFormat;
+use crate::video_encoder::OutputContainer;
 use crate::ParamIdx;
 use crate::INPUT_LAYER_CHECKOUT_ID;
 use ae::aegp::suites;
@@ -7,11 +9,22 @@ use ae::ParamFlag;
 use ae::{Error, InData};
 use after_effects as ae;
 use after_effects_sys::PF_Pixel;
-use golob_lib::Variant;
+use golob_lib::{Scale, Variant};
 
-pub const MAX_INPUTS: i32 = 32;
+pub const MAX_INPUTS: i32 = 64;
+// inputs backed by the original block, the rest are in the extra block
+pub const BLOCK_INPUTS: i32 = 32;
+// params per input in the original block. Saved projects find params by index,
+// so types added since live in the extra block after it.
 pub const PARAM_TYPE_COUNT: i32 = 7;
+// enums with more options than this are truncated in the popup
+pub const MAX_POPUP_OPTIONS: usize = 32;
 pub const STATIC_PARAMS_OFFSET: i32 = ParamIdx::IsImageFilter.idx() + 1;
+// an angle per input, then the inputs that didn't fit in the original block
+pub const EXTRA_PARAMS_OFFSET: i32 = ParamIdx::ExtraParametersStart.idx() + 1;
+pub const EXTRA_INPUTS_OFFSET: i32 = EXTRA_PARAMS_OFFSET + MAX_INPUTS;
+// the last param we add, anything past it is free for checkout ids
+pub const LAST_PARAM: ParamIdx = ParamIdx::OutputGroupEnd;
 
 impl ParamIdx {
     pub const fn idx(&self) -> i32 {
@@ -35,7 +48,17 @@ impl ParamIdx {
             Self::ParametersStart => 17,
             Self::IsImageFilter => 18,
             Self::Dynamic(x) => *x,
-            Self::ParametersEnd => Self::IsImageFilter.idx() + (MAX_INPUTS * PARAM_TYPE_COUNT) + 1,
+            Self::ParametersEnd => {
+                Self::IsImageFilter.idx() + (BLOCK_INPUTS * PARAM_TYPE_COUNT) + 1
+            }
+            Self::ExtraParametersStart => Self::ParametersEnd.idx() + 1,
+            Self::ExtraParametersEnd => {
+                EXTRA_INPUTS_OFFSET + (MAX_INPUTS - BLOCK_INPUTS) * PARAM_TYPE_COUNT
+            }
+            Self::OutputGroupBegin => Self::ExtraParametersEnd.idx() + 1,
+            Self::OutputContainer => Self::OutputGroupBegin.idx() + 1,
+            Self::SequenceFormat => Self::OutputContainer.idx() + 1,
+            Self::OutputGroupEnd => Self::SequenceFormat.idx() + 1,
         }
     }
 }
@@ -62,10 +85,16 @@ impl From<usize> for ParamIdx {
             17 => Self::ParametersStart,
             18 => Self::IsImageFilter,
             end if end as i32
-                == (Self::IsImageFilter.idx() + (MAX_INPUTS * PARAM_TYPE_COUNT) + 1) =>
+                == (Self::IsImageFilter.idx() + (BLOCK_INPUTS * PARAM_TYPE_COUNT) + 1) =>
             {
                 Self::ParametersEnd
             }
+            n if n as i32 == Self::ExtraParametersStart.idx() => Self::ExtraParametersStart,
+            n if n as i32 == Self::ExtraParametersEnd.idx() => Self::ExtraParametersEnd,
+            n if n as i32 == Self::OutputGroupBegin.idx() => Self::OutputGroupBegin,
+            n if n as i32 == Self::OutputContainer.idx() => Self::OutputContainer,
+            n if n as i32 == Self::SequenceFormat.idx() => Self::SequenceFormat,
+            n if n as i32 == Self::OutputGroupEnd.idx() => Self::OutputGroupEnd,
             n => Self::Dynamic(n as i32),
         }
     }
@@ -79,27 +108,41 @@ pub enum AeVariant {
     Bool,
     Color,
     Image,
+    Angle,
 }
 impl AeVariant {}
 
 pub fn as_param_index(index: usize, variant: &Variant) -> ParamIdx {
     let variant: i32 = match variant {
-        Variant::Float(_) => AeVariant::Float as _,
+        Variant::Float(..) => AeVariant::Float as _,
         Variant::Int(_) => AeVariant::Int as _,
         Variant::TaggedInt(_) => AeVariant::IntList as _,
         Variant::Vector2(_) => AeVariant::Point as _,
         Variant::Bool(_) => AeVariant::Bool as _,
         Variant::Color(_) => AeVariant::Color as _,
-        Variant::Image(_) => AeVariant::Image as _,
+        Variant::Image(..) => AeVariant::Image as _,
+        Variant::Angle(_) => AeVariant::Angle as _,
     };
 
-    ParamIdx::Dynamic((index as i32 * PARAM_TYPE_COUNT) + STATIC_PARAMS_OFFSET + variant)
+    let index = index as i32;
+    if variant == AeVariant::Angle as i32 {
+        return ParamIdx::Dynamic(EXTRA_PARAMS_OFFSET + index);
+    }
+
+    if index >= BLOCK_INPUTS {
+        let offset = (index - BLOCK_INPUTS) * PARAM_TYPE_COUNT;
+        return ParamIdx::Dynamic(EXTRA_INPUTS_OFFSET + offset + variant);
+    }
+
+    ParamIdx::Dynamic((index * PARAM_TYPE_COUNT) + STATIC_PARAMS_OFFSET + variant)
 }
 
 pub fn update_param_defaults_and_labels(
     state: &mut crate::PluginState,
     local: &mut crate::instance::Instance,
 ) -> Result<(), ae::Error> {
+    migrate_legacy_popups(state, local)?;
+
     for (i, (name, var)) in local.runner.iter_inputs().enumerate() {
         let index = as_param_index(i, var);
         let mut def = state.params.get_mut(index)?;
@@ -130,13 +173,21 @@ pub fn update_param_defaults_and_labels(
                 }
             }
             ae::Param::FloatSlider(mut fl) => {
-                if let Variant::Float(f) = var {
+                if let Variant::Float(f, scale) = var {
                     fl.set_default(f.default as f64);
                     fl.set_value(f.current as f64);
                     fl.set_valid_min(f.min);
                     fl.set_valid_max(f.max);
                     fl.set_slider_min(f.min);
                     fl.set_slider_max(f.max);
+                    // AE has no log sliders, an exponent curve is the closest thing.
+                    // slots are reused between scripts so linear floats reset it.
+                    let exponent = if *scale == Scale::Logarithmic {
+                        (f.max / f.min).log10().max(1.0)
+                    } else {
+                        1.0
+                    };
+                    fl.set_exponent(exponent);
                 }
             }
             ae::Param::Point(mut p) => {
@@ -147,7 +198,24 @@ pub fn update_param_defaults_and_labels(
             }
             ae::Param::Popup(mut il) => {
                 if let Variant::TaggedInt(v) = var {
-                    il.set_value(v.value);
+                    if v.tags.len() > MAX_POPUP_OPTIONS {
+                        log::warn!(
+                            "{name} has {} options, only the first {MAX_POPUP_OPTIONS} are shown",
+                            v.tags.len()
+                        );
+                    }
+
+                    let labels: Vec<&str> = v
+                        .tags
+                        .keys()
+                        .take(MAX_POPUP_OPTIONS)
+                        .map(|k| k.as_str())
+                        .collect();
+
+                    il.set_options(&labels);
+                    // popups are 1 based positions, not tag values
+                    il.set_default((v.default_position().unwrap_or(0) + 1) as _);
+                    il.set_value(v.position().unwrap_or(0) as i32 + 1);
                 }
             }
             ae::Param::Slider(mut i) => {
@@ -163,14 +231,147 @@ pub fn update_param_defaults_and_labels(
             ae::Param::Layer(mut im) => {
                 im.set_default_to_this_layer();
             }
+            ae::Param::Angle(mut a) => {
+                if let Variant::Angle(v) = var {
+                    a.set_default(v.default as f64);
+                    a.set_value(v.current as f64);
+                }
+            }
             _ => {}
         }
 
+        // PF_ParamDef has nowhere to put a tooltip, so descriptions
+        // only show up in the playground.
         def.set_name(name);
         def.update_param_ui()?;
         def.set_value_changed();
     }
 
+    set_image_filter_default(state, local)?;
+    set_effect_name(state.in_data, local)?;
+
+    Ok(())
+}
+
+/// Rewrites the popups of a project saved with their tag values as positions.
+pub fn migrate_legacy_popups(
+    state: &mut crate::PluginState,
+    local: &mut crate::instance::Instance,
+) -> Result<(), ae::Error> {
+    if !local.legacy_popups {
+        return Ok(());
+    }
+
+    // until the script loads we can't tell which params are popups
+    if local.src.is_some() && local.runner.iter_inputs().next().is_none() {
+        return Ok(());
+    }
+
+    let popups: Vec<_> = local
+        .runner
+        .iter_inputs()
+        .enumerate()
+        .filter_map(|(i, (name, var))| match var {
+            Variant::TaggedInt(v) => Some((as_param_index(i, var), name.clone(), v.clone())),
+            _ => None,
+        })
+        .collect();
+
+    for (index, name, mut v) in popups {
+        let value = state.params.get(index)?.as_popup()?.value();
+        select_popup(&mut v, value, true);
+
+        let mut def = state.params.get_mut(index)?;
+        if let ae::Param::Popup(mut il) = def.as_param_mut()? {
+            il.set_value(v.position().unwrap_or(0) as i32 + 1);
+        }
+        def.update_param_ui()?;
+        def.set_value_changed();
+
+        let _ = local.runner.try_set_var(&name, Variant::TaggedInt(v));
+    }
+
+    local.legacy_popups = false;
+    Ok(())
+}
+
+// Popups hold the 1 based position of their tag. With `legacy` a value that
+// matches a tag is taken as that tag, the way projects used to store them.
+fn select_popup(v: &mut golob_lib::TaggedInt, value: i32, legacy: bool) {
+    if legacy && v.tags.values().any(|t| *t == value) {
+        v.value = value;
+    } else {
+        v.select((value - 1).max(0) as usize);
+    }
+}
+
+// Applies `ctx.set_image_filter_default`. The value is only reset when the script's
+// default differs from the last one we applied, so unticking it by hand survives
+// reloads. Without an image input the checkbox is hidden and means nothing, so it's
+// left as it is.
+fn set_image_filter_default(
+    state: &mut crate::PluginState,
+    local: &mut crate::instance::Instance,
+) -> Result<(), ae::Error> {
+    let has_image_input = local
+        .runner
+        .iter_inputs()
+        .any(|(_, v)| matches!(v, Variant::Image(..)));
+
+    if !has_image_input {
+        return Ok(());
+    }
+
+    let default = local.runner.image_filter_default();
+    let reset = local.image_filter_default.unwrap_or(true) != default;
+
+    let mut def = state.params.get_mut(ParamIdx::IsImageFilter)?;
+    if let ae::Param::CheckBox(mut cb) = def.as_param_mut()? {
+        cb.set_default(default);
+        if reset {
+            cb.set_value(default);
+        }
+    }
+
+    def.update_param_ui()?;
+    if reset {
+        def.set_value_changed();
+        local.image_filter_default = Some(default);
+    }
+
+    Ok(())
+}
+
+// Renames the effect to whatever the script called itself with `ctx.set_name`.
+// Only done when that changes, so a name the user typed in afterwards sticks.
+fn set_effect_name(
+    in_data: InData,
+    local: &mut crate::instance::Instance,
+) -> Result<(), ae::Error> {
+    let name = local.runner.name().map(str::to_owned);
+    if name == local.effect_name {
+        return Ok(());
+    }
+
+    // without a name we leave the label alone
+    if let Some(name) = name.as_deref() {
+        let dyn_stream_suite = suites::DynamicStream::new()?;
+        let stream_suite = suites::Stream::new()?;
+        let interface = suites::PFInterface::new()?;
+        let plugin_id = *crate::PLUGIN_ID.get().unwrap();
+
+        let effect = interface.new_effect_for_effect(in_data.effect(), plugin_id)?;
+        // any of our params will do, the effect's own stream is their parent
+        let param = stream_suite.new_effect_stream_by_index(
+            effect,
+            plugin_id,
+            ParamIdx::ScriptGroupStart.idx(),
+        )?;
+        let effect_stream = dyn_stream_suite.new_parent_stream_ref(plugin_id, param)?;
+        dyn_stream_suite.set_stream_name(effect_stream, name)?;
+    }
+
+    local.effect_name = name;
     Ok(())
 }
 
@@ -218,6 +419,22 @@ fn set_sequential_control_vis(
         is_sequential && render_progress.is_some(),
     )?;
 
+    set_param_visibility(state.in_data, ParamIdx::OutputGroupBegin, is_sequential)?;
+
+    set_param_visibility(
+        state.in_data,
+        ParamIdx::OutputContainer,
+        is_sequential && render_progress.is_none(),
+    )?;
+
+    set_param_visibility(state.in_data, ParamIdx::OutputGroupEnd, is_sequential)?;
+
+    set_param_visibility(
+        state.in_data,
+        ParamIdx::SequenceFormat,
+        is_sequential && render_progress.is_none(),
+    )?;
+
     set_param_visibility(
         state.in_data,
         ParamIdx::ContinuousRenderGroupEnd,
@@ -250,7 +467,9 @@ fn set_user_param_vis(
     local: &mut crate::instance::Instance,
     script_loaded: bool,
 ) -> Result<(), ae::Error> {
-    for index in ParamIdx::IsImageFilter.idx()..ParamIdx::ParametersEnd.idx() {
+    let user_params = ParamIdx::IsImageFilter.idx()..ParamIdx::ParametersEnd.idx();
+    let extra_params = EXTRA_PARAMS_OFFSET..ParamIdx::ExtraParametersEnd.idx();
+    for index in user_params.chain(extra_params) {
         set_param_visibility(state.in_data, ParamIdx::from(index as usize), false)?;
     }
 
@@ -263,6 +482,18 @@ fn set_user_param_vis(
         script_loaded && params_exist,
     )?;
 
+    let extra_params_exist = local
+        .runner
+        .iter_inputs()
+        .enumerate()
+        .any(|(i, (_, var))| as_param_index(i, var).idx() > ParamIdx::ParametersEnd.idx());
+
+    set_param_visibility(
+        state.in_data,
+        ParamIdx::ExtraParametersStart,
+        script_loaded && extra_params_exist,
+    )?;
+
     for (i, (_, var)) in local.runner.iter_inputs().enumerate() {
         let index = as_param_index(i, var);
         set_param_visibility(state.in_data, index, true)?;
@@ -272,7 +503,7 @@ fn set_user_param_vis(
         .runner
         .iter_inputs()
         .enumerate()
-        .find(|(_, (_, v))| matches!(v, Variant::Image(_)));
+        .find(|(_, (_, v))| matches!(v, Variant::Image(..)));
 
     // only show image filter options IF we have at least one image input
     set_param_visibility(
@@ -303,7 +534,11 @@ pub fn update_input_visibilities(
     let script_loaded = local.src.is_some();
     let venv_loaded = local.venv_path.is_some();
     let is_sequential = local.runner.is_sequential();
-    let render_progress = local.job_id.and_then(|id| state.global.render_progress(id));
+    // prefer what the script reports over the frame count when it has something to say
+    let render_progress = local
+        .job_id
+        .and_then(|id| state.global.render_progress(id))
+        .map(|frames| local.runner.progress().map_or(frames, |p| 100.0 * p));
 
     set_debug_vis(state, script_loaded)?;
     set_script_vis(state, script_loaded, venv_loaded)?;
@@ -312,9 +547,19 @@ pub fn update_input_visibilities(
     Ok(())
 }
 
+/// The filter checkbox can be keyframed, so renders ask for it at their own time.
+pub fn is_image_filter_at(in_data: &InData, time: i32) -> Result<bool, Error> {
+    let (step, scale) = (in_data.time_step(), in_data.time_scale());
+    let param =
+        ae::ParamDef::checkout(*in_data, ParamIdx::IsImageFilter.idx(), time, step, scale, None)?;
+
+    Ok(param.as_checkbox()?.value())
+}
+
 pub fn set_params(
     in_data: &ae::InData,
     runner: &mut golob_lib::PythonRunner,
+    legacy_popups: bool,
 ) -> Result<Vec<(String, ParamIdx)>, Error> {
     let curr = in_data.current_time();
     let step = in_data.time_step();
@@ -322,26 +567,27 @@ pub fn set_params(
     let mut first_image = true;
 
     runner.set_time(curr as f32 / scale as f32);
+    runner.set_frame((curr / step).max(0) as u32);
+    runner.set_fps(scale as f32 / step as f32);
+
+    match crate::footage_utils::get_layer_info(in_data) {
+        Ok((name, (width, height))) => {
+            runner.set_layer_name(Some(name));
+            runner.set_comp_size(width, height);
+        }
+        Err(e) => log::debug!("no layer info for the script: {e:?}"),
+    }
+
     let mut out = vec![];
+    let is_image_filter = is_image_filter_at(in_data, curr)?;
 
     for (i, (name, val)) in runner.iter_inputs_mut().enumerate() {
         let index = as_param_index(i, &*val);
 
         let param = ae::ParamDef::checkout(*in_data, index.idx(), curr, step, scale, None)?;
 
-        let is_image_filter = ae::ParamDef::checkout(
-            *in_data,
-            ParamIdx::IsImageFilter.idx(),
-            curr,
-            step,
-            scale,
-            None,
-        )?
-        .as_checkbox()?
-        .value();
-
         match val {
-            Variant::Image(_) => {
+            Variant::Image(..) => {
                 if first_image && is_image_filter {
                     first_image = false;
                     out.push((name.clone(), INPUT_LAYER_CHECKOUT_ID));
@@ -355,7 +601,7 @@ pub fn set_params(
             }
             Variant::TaggedInt(i) => {
                 let popup = param.as_popup()?;
-                i.value = popup.value();
+                select_popup(i, popup.value(), legacy_popups);
             }
             Variant::Color(c) => {
                 let color = param.as_color()?;
@@ -369,16 +615,20 @@ pub fn set_params(
             }
             Variant::Int(i) => {
                 let int = param.as_slider()?;
-                i.current = int.value();
+                i.current = i.snap(int.value());
             }
-            Variant::Float(f) => {
+            Variant::Float(f, _) => {
                 let float = param.as_float_slider()?;
-                f.current = float.value() as f32;
+                f.current = f.snap(float.value() as f32);
             }
             Variant::Vector2(p) => {
                 let vec = param.as_point()?;
                 p.current = vec.value().into();
             }
+            Variant::Angle(a) => {
+                let angle = param.as_angle()?;
+                a.current = angle.value() as f32;
+            }
         }
     }
 
@@ -434,15 +684,12 @@ pub fn setup_static_params(params: &mut ae::Parameters<ParamIdx>) -> Result<(),
                 .unwrap();
 
             params.add_with_flags(
-                ParamIdx::SetVenv,
+                ParamIdx::UnsetVenv,
                 "Unset Site Packages Path",
                 ae::ButtonDef::setup(|f| {
                     f.set_label("Unset Site Packages Path");
                 }),
-                ParamFlag::CANNOT_TIME_VARY
-                    | ParamFlag::TWIRLY
-                    | ParamFlag::SUPERVISE
-                    | ParamFlag::SKIP_REVEAL_WHEN_UNHIDDEN,
+                static_params_cfg(),
                 ae::ParamUIFlags::empty(),
             )?;
 
@@ -535,6 +782,41 @@ pub fn setup_static_params(params: &mut ae::Parameters<ParamIdx>) -> Result<(),
     Ok(())
 }
 
+// Sequential render options, added after the user params so
+// projects saved before they existed keep their param indices.
+pub fn setup_output_params(params: &mut ae::Parameters<ParamIdx>) -> Result<(), Error> {
+    params.add_group(
+        ParamIdx::OutputGroupBegin,
+        ParamIdx::OutputGroupEnd,
+        "Sequential Output",
+        |params| {
+            params.add_with_flags(
+                ParamIdx::OutputContainer,
+                "Output",
+                ae::PopupDef::setup(|f| {
+                    f.set_options(&OutputContainer::OPTIONS);
+                    f.set_default(1);
+                }),
+                static_params_cfg(),
+                ae::ParamUIFlags::empty(),
+            )?;
+
+            params.add_with_flags(
+                ParamIdx::SequenceFormat,
+                "Sequence Format",
+                ae::PopupDef::setup(|f| {
+                    f.set_options(&SequenceFormat::OPTIONS);
+                    f.set_default(1);
+                }),
+                static_params_cfg(),
+                ae::ParamUIFlags::empty(),
+            )?;
+
+            Ok(())
+        },
+    )
+}
+
 // create one param of every type to back
 // a single input variant in the render context
 pub fn create_variant_backing(params: &mut ae::Parameters<ParamIdx>) -> Result<(), Error> {
@@ -544,6 +826,9 @@ pub fn create_variant_backing(params: &mut ae::Parameters<ParamIdx>) -> Result<(
         ParamIdx::ParametersEnd,
         "User Parameters",
         |params| {
+            // Can be keyframed to switch between filter and generator, hold keyframes
+            // flip it on the frame they land on. Renders use the value at their own
+            // time, the first image's layer picker only follows the current time.
             params.add_with_flags(
                 ParamIdx::IsImageFilter,
                 "Is Image Filter",
@@ -551,74 +836,40 @@ pub fn create_variant_backing(params: &mut ae::Parameters<ParamIdx>) -> Result<(
                     f.set_label("Enabled");
                     f.set_default(true);
                 }),
-                ParamFlag::CANNOT_TIME_VARY
-                    | ParamFlag::TWIRLY
-                    | ParamFlag::SKIP_REVEAL_WHEN_UNHIDDEN,
+                ParamFlag::TWIRLY | ParamFlag::SKIP_REVEAL_WHEN_UNHIDDEN,
                 ae::ParamUIFlags::empty(),
             )?;
 
-            for _ in 0..MAX_INPUTS {
-                for offset in 0..PARAM_TYPE_COUNT {
-                    let name = format!("INPUT {}", base_index + offset);
-                    let index = ParamIdx::Dynamic(base_index + offset);
-                    let ui_flags = ae::ParamUIFlags::empty();
-                    let param_flag = ParamFlag::TWIRLY | ParamFlag::SKIP_REVEAL_WHEN_UNHIDDEN;
-                    match offset as usize {
-                        f if f == AeVariant::Float as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::FloatSliderDef::setup(float),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        i if i == AeVariant::Int as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::SliderDef::setup(int),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        i if i == AeVariant::IntList as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::PopupDef::setup(options),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        pt if pt == AeVariant::Point as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::PointDef::setup(point),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        b if b == AeVariant::Bool as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::CheckBoxDef::setup(bool),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        c if c == AeVariant::Color as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::ColorDef::setup(color),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        i if i == AeVariant::Image as usize => params.add_with_flags(
-                            index,
-                            &name,
-                            ae::LayerDef::setup(layer),
-                            param_flag,
-                            ui_flags,
-                        )?,
-                        _ => {
-                            unreachable!("Arithmetic mistake while setting up input unions.")
-                        }
-                    }
-                }
+            for _ in 0..BLOCK_INPUTS {
+                add_input_params(params, base_index)?;
+                base_index += PARAM_TYPE_COUNT;
+            }
+
+            Ok(())
+        },
+    )?;
 
+    // after everything older projects know about, so their params keep their indices
+    params.add_group(
+        ParamIdx::ExtraParametersStart,
+        ParamIdx::ExtraParametersEnd,
+        "More Parameters",
+        |params| {
+            for i in 0..MAX_INPUTS {
+                let index = EXTRA_PARAMS_OFFSET + i;
+                params.add_with_flags(
+                    ParamIdx::Dynamic(index),
+                    &format!("INPUT {index}"),
+                    ae::AngleDef::setup(angle),
+                    ParamFlag::TWIRLY | ParamFlag::SKIP_REVEAL_WHEN_UNHIDDEN,
+                    ae::ParamUIFlags::empty(),
+                )?;
+            }
+
+            // inputs past the original block
+            let mut base_index = EXTRA_INPUTS_OFFSET;
+            for _ in BLOCK_INPUTS..MAX_INPUTS {
+                add_input_params(params, base_index)?;
                 base_index += PARAM_TYPE_COUNT;
             }
 
@@ -629,6 +880,72 @@ pub fn create_variant_backing(params: &mut ae::Parameters<ParamIdx>) -> Result<(
     Ok(())
 }
 
+// one param of every type at `base_index` onwards, backing a single input
+fn add_input_params(params: &mut ae::Parameters<ParamIdx>, base_index: i32) -> Result<(), Error> {
+    for offset in 0..PARAM_TYPE_COUNT {
+        let name = format!("INPUT {}", base_index + offset);
+        let index = ParamIdx::Dynamic(base_index + offset);
+        let ui_flags = ae::ParamUIFlags::empty();
+        let param_flag = ParamFlag::TWIRLY | ParamFlag::SKIP_REVEAL_WHEN_UNHIDDEN;
+        match offset as usize {
+            f if f == AeVariant::Float as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::FloatSliderDef::setup(float),
+                param_flag,
+                ui_flags,
+            )?,
+            i if i == AeVariant::Int as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::SliderDef::setup(int),
+                param_flag,
+                ui_flags,
+            )?,
+            i if i == AeVariant::IntList as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::PopupDef::setup(options),
+                param_flag,
+                ui_flags,
+            )?,
+            pt if pt == AeVariant::Point as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::PointDef::setup(point),
+                param_flag,
+                ui_flags,
+            )?,
+            b if b == AeVariant::Bool as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::CheckBoxDef::setup(bool),
+                param_flag,
+                ui_flags,
+            )?,
+            c if c == AeVariant::Color as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::ColorDef::setup(color),
+                param_flag,
+                ui_flags,
+            )?,
+            i if i == AeVariant::Image as usize => params.add_with_flags(
+                index,
+                &name,
+                ae::LayerDef::setup(layer),
+                param_flag,
+                ui_flags,
+            )?,
+            _ => {
+                unreachable!("Arithmetic mistake while setting up input unions.")
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// This is get values to pass to background thread renders
 /// it pulls data from the API meant for making panel plugins.
 pub fn set_variant_from_stream_val(
@@ -636,12 +953,15 @@ pub fn set_variant_from_stream_val(
     stream_val: StreamValue,
 ) -> Result<(), Error> {
     match (variant, stream_val) {
-        (Variant::Image(_), StreamValue::LayerId(_)) => {}
+        (Variant::Image(..), StreamValue::LayerId(_)) => {}
         (Variant::Bool(val), StreamValue::OneD(fl)) => val.current = fl == 1.0,
-        (Variant::TaggedInt(options), StreamValue::OneD(opt)) => options.value = opt as _,
-        (Variant::Int(val), StreamValue::OneD(fl)) => val.current = fl as i32,
-        (Variant::Float(val), StreamValue::OneD(fl)) => val.current = fl as f32,
+        (Variant::TaggedInt(options), StreamValue::OneD(opt)) => {
+            options.select((opt as usize).saturating_sub(1))
+        }
+        (Variant::Int(val), StreamValue::OneD(fl)) => val.current = val.snap(fl as i32),
+        (Variant::Float(val, _), StreamValue::OneD(fl)) => val.current = val.snap(fl as f32),
         (Variant::Vector2(val), StreamValue::TwoD { x, y }) => val.current = [x as f32, y as f32],
+        (Variant::Angle(val), StreamValue::OneD(deg)) => val.current = deg as f32,
         (
             Variant::Color(val),
             StreamValue::Color {
@@ -694,6 +1014,10 @@ fn color(f: &mut ae::ColorDef) {
     });
 }
 
+fn angle(f: &mut ae::AngleDef) {
+    f.set_default(0.0);
+}
+
 fn point(f: &mut ae::PointDef) {
     f.set_default((0.0, 0.0));
 }
@@ -704,11 +1028,10 @@ fn bool(f: &mut ae::CheckBoxDef) {
 }
 
 fn options(f: &mut ae::PopupDef) {
-    // it is unsafe to dynamically set options
-    // But from reading the adobe forums it *seems* like it
-    // is okay IFF you set the num options constant, so I pad with "-"
-    f.set_options(&["option"; 8]);
-    f.set_default(0);
+    // the real labels, and how many there are, are set when a script
+    // is loaded. These are never visible, the param is hidden until then.
+    f.set_options(&["-"; MAX_POPUP_OPTIONS]);
+    f.set_default(1);
 }
 
 fn int(f: &mut ae::SliderDef) {
@@ -108,6 +108,7 @@ fn draw_text(
             let string = match label {
                 "stdout" => &contents.stdout,
                 "err" => &contents.error,
+                "status" => &contents.status,
                 _ => &None,
             };
 
@@ -155,6 +156,7 @@ fn draw_text(
     let mut offset = 0.0;
     draw_messages(error_map, &error_color, &mut offset, "err")?;
     draw_messages(error_map, &std_color, &mut offset, "stdout")?;
+    draw_messages(error_map, &std_color, &mut offset, "status")?;
 
     event.set_event_out_flags(ae::EventOutFlags::HANDLED_EVENT);
     Ok(())
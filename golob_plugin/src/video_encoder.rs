@@ -0,0 +1,189 @@
+// Encodes sequential renders straight to a movie by piping raw frames
+// into an ffmpeg process, so we don't need a codec in the plugin itself.
+
+use golob_lib::ImageFormat;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::JoinHandle;
+
+/// What a sequential render gets written to, matches the
+/// order of the options in the output popup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputContainer {
+    ImageSequence,
+    Mp4,
+    Mov,
+}
+
+impl OutputContainer {
+    pub const OPTIONS: [&'static str; 3] = ["Image Sequence", "MP4 (H.264)", "MOV (ProRes 4444)"];
+
+    /// From the 1 based popup value.
+    pub fn from_popup(value: i32) -> Self {
+        match value {
+            2 => Self::Mp4,
+            3 => Self::Mov,
+            _ => Self::ImageSequence,
+        }
+    }
+
+    pub fn is_video(&self) -> bool {
+        !matches!(self, Self::ImageSequence)
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::ImageSequence => "",
+            Self::Mp4 => "mp4",
+            Self::Mov => "mov",
+        }
+    }
+}
+
+pub struct VideoEncoder {
+    child: Option<Child>,
+    stdin: Option<ChildStdin>,
+    // read as it's written, a full pipe would stall ffmpeg and with it the render
+    stderr: Option<JoinHandle<String>>,
+    fmt: ImageFormat,
+    // scratch space for formats ffmpeg can't take as is
+    converted: Vec<u8>,
+}
+
+impl VideoEncoder {
+    /// Starts ffmpeg writing a movie into `directory`. Set `GOLOBULUS_FFMPEG`
+    /// to use an ffmpeg that isn't on the path.
+    pub fn new(
+        directory: &Path,
+        container: OutputContainer,
+        width: u32,
+        height: u32,
+        fps: f32,
+        fmt: ImageFormat,
+    ) -> io::Result<Self> {
+        let pix_fmt = match fmt {
+            ImageFormat::Rgba8 => "rgba",
+            // floats are narrowed to 16 bits before they are piped in
+            ImageFormat::Rgba16 | ImageFormat::Rgba32 => "rgba64le",
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("can't encode {fmt:?} frames"),
+                ))
+            }
+        };
+
+        let codec: &[&str] = match container {
+            OutputContainer::Mp4 => &["-c:v", "libx264", "-pix_fmt", "yuv420p", "-crf", "16"],
+            OutputContainer::Mov => &[
+                "-c:v",
+                "prores_ks",
+                "-profile:v",
+                "4444",
+                "-pix_fmt",
+                "yuva444p10le",
+            ],
+            OutputContainer::ImageSequence => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "image sequences aren't encoded",
+                ))
+            }
+        };
+
+        let ffmpeg = std::env::var_os("GOLOBULUS_FFMPEG").unwrap_or_else(|| "ffmpeg".into());
+        let output = directory.join(format!("render.{}", container.extension()));
+
+        let mut child = Command::new(ffmpeg)
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo"])
+            .args(["-pix_fmt", pix_fmt])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-r", &fps.to_string()])
+            .args(["-i", "-"])
+            // h264 wants even dimensions
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+            .args(codec)
+            .arg(&output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        let stderr = child.stderr.take().map(|mut pipe| {
+            std::thread::spawn(move || {
+                let mut text = String::new();
+                let _ = pipe.read_to_string(&mut text);
+                text
+            })
+        });
+
+        Ok(Self {
+            child: Some(child),
+            stdin,
+            stderr,
+            fmt,
+            converted: vec![],
+        })
+    }
+
+    /// Frames must be pushed in order, tightly packed.
+    pub fn push_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        };
+
+        if let ImageFormat::Rgba32 = self.fmt {
+            // the frame buffer isn't necessarily aligned for f32
+            self.converted.clear();
+            self.converted.extend(frame.chunks_exact(4).flat_map(|bytes| {
+                let f = f32::from_ne_bytes(bytes.try_into().unwrap());
+                ((f.clamp(0.0, 1.0) * 65535.0).round() as u16).to_le_bytes()
+            }));
+            stdin.write_all(&self.converted)
+        } else {
+            stdin.write_all(frame)
+        }
+    }
+
+    /// Closes the stream and waits for ffmpeg to write the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        drop(self.stdin.take());
+
+        let Some(mut child) = self.child.take() else {
+            return Ok(());
+        };
+
+        let status = child.wait()?;
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(stderr))
+        }
+    }
+}
+
+impl Drop for VideoEncoder {
+    // an unfinished encoder was cancelled or failed, don't leave ffmpeg running
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// The movie a video render left in `directory`, if there is one.
+pub fn find_movie(directory: &Path) -> Option<PathBuf> {
+    [OutputContainer::Mp4, OutputContainer::Mov]
+        .iter()
+        .map(|c| directory.join(format!("render.{}", c.extension())))
+        .find(|path| path.is_file())
+}